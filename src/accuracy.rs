@@ -0,0 +1,486 @@
+use dirs::data_dir;
+use std::path::PathBuf;
+use std::fs::{
+    File,
+    create_dir_all,
+};
+use std::time::SystemTime;
+use serde::{
+    Serialize,
+    Deserialize,
+};
+use super::api::weather::{
+    CurrentWeather,
+    HourlyWeather,
+    WeatherData,
+};
+use super::api::units::Units;
+use super::api::location::LocationPoint;
+use super::api::provider::speed_in;
+
+const WEATHER_DATA_DIR: &str = "weather-mobile";
+
+/// Furthest ahead forecasts are kept for comparison
+const MAX_LEAD_HOURS: i64 = 48;
+/// How far an observation may be from the forecast hour to be compared with it
+const MATCH_SECONDS: i64 = 30 * 60;
+/// Most recent comparisons kept per location
+const MAX_SAMPLES: usize = 5000;
+
+/// Lead times the report is split into, in hours from (inclusive) and to (exclusive)
+pub const LEAD_BUCKETS: &[(i64, i64)] = &[
+    (0, 6),
+    (6, 12),
+    (12, 24),
+    (24, MAX_LEAD_HOURS),
+];
+
+/// Forecast for one future hour, in metric units so changing units keeps the history
+#[derive(Serialize, Deserialize, Clone)]
+struct IssuedForecast {
+    provider: String,
+    issued_at: i64,
+    valid_at: i64,
+    temp: f64,
+    wind_speed: f64,
+    precipitation: f64,
+}
+
+/// Forecast minus observation for one forecast hour, in metric units
+#[derive(Serialize, Deserialize, Clone)]
+struct Sample {
+    provider: String,
+    valid_at: i64,
+    lead_hours: i64,
+    temp: f64,
+    wind_speed: f64,
+    precipitation: f64,
+}
+
+/// Mean absolute error and mean error (bias, positive when forecasting too high)
+#[derive(Clone, Copy, Default)]
+pub struct ErrorStats {
+    pub mae: f64,
+    pub bias: f64,
+}
+
+pub struct LeadAccuracy {
+    pub from_hours: i64,
+    pub to_hours: i64,
+    pub samples: usize,
+    pub temp: ErrorStats,
+    pub wind_speed: ErrorStats,
+    pub precipitation: ErrorStats,
+}
+
+pub struct ProviderAccuracy {
+    pub provider: String,
+    pub leads: Vec<LeadAccuracy>,
+}
+
+impl ProviderAccuracy {
+    pub fn samples(&self) -> usize {
+        self.leads.iter().map(|l| l.samples).sum()
+    }
+
+    /// Temperature error over all lead times, used to rank providers
+    pub fn temp_mae(&self) -> Option<f64> {
+        let samples = self.samples();
+        if samples == 0 {
+            return None;
+        }
+
+        Some(self.leads.iter()
+            .map(|l| l.temp.mae * l.samples as f64)
+            .sum::<f64>() / samples as f64)
+    }
+}
+
+/// Errors in the user's units, best provider (lowest temperature error) first
+pub struct AccuracyReport {
+    pub units: Units,
+    pub providers: Vec<ProviderAccuracy>,
+    pub pending: usize,
+}
+
+/// Forecasts waiting for their hour to come, and how past ones turned out, for one location
+#[derive(Serialize, Deserialize, Default)]
+pub struct ForecastAccuracy {
+    issued: Vec<IssuedForecast>,
+    samples: Vec<Sample>,
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+fn accuracy_path(lat: f64, lon: f64) -> Option<PathBuf> {
+    let mut dir = data_dir()?;
+    dir.push(WEATHER_DATA_DIR);
+    dir.push(format!("accuracy_{:.4}_{:.4}.json", lat, lon));
+
+    Some(dir)
+}
+
+fn to_celsius(units: Units, temperature: f64) -> f64 {
+    match units {
+        Units::Metric => temperature,
+        Units::Imperial => (temperature - 32.0) * 5.0 / 9.0,
+    }
+}
+
+fn to_metres_per_second(units: Units, speed: f64) -> f64 {
+    match units {
+        Units::Metric => speed,
+        Units::Imperial => speed / 2.236_936,
+    }
+}
+
+fn precipitation(weather: &HourlyWeather) -> f64 {
+    weather.rain.unwrap_or(0.0) + weather.snow.unwrap_or(0.0)
+}
+
+fn stats(errors: &[f64], scale: f64) -> ErrorStats {
+    if errors.is_empty() {
+        return ErrorStats::default();
+    }
+    let count = errors.len() as f64;
+
+    ErrorStats {
+        mae: errors.iter().map(|e| e.abs()).sum::<f64>() / count * scale,
+        bias: errors.iter().sum::<f64>() / count * scale,
+    }
+}
+
+impl ForecastAccuracy {
+    pub fn load(location: &LocationPoint) -> ForecastAccuracy {
+        let file = match accuracy_path(location.lat, location.lon).map(File::open) {
+            Some(Ok(file)) => file,
+            _ => return ForecastAccuracy::default(),
+        };
+
+        match serde_json::from_reader(file) {
+            Ok(accuracy) => accuracy,
+            Err(err) => {
+                println!("Ignoring unreadable forecast accuracy: {}", err);
+                ForecastAccuracy::default()
+            }
+        }
+    }
+
+    fn save(&self, location: &LocationPoint) {
+        let path = if let Some(path) = accuracy_path(location.lat, location.lon) {
+            path
+        } else {
+            println!("Unable to resolve forecast accuracy path");
+            return;
+        };
+        if let Some(dir) = path.parent() {
+            if let Err(err) = create_dir_all(dir) {
+                println!("Unable to create data directory: {}", err);
+                return;
+            }
+        }
+
+        let result = File::create(path)
+            .map_err(|err| err.to_string())
+            .and_then(|file| serde_json::to_writer(file, self)
+                .map_err(|err| err.to_string()));
+        if let Err(err) = result {
+            println!("Unable to write forecast accuracy: {}", err);
+        }
+    }
+
+    /// Scores waiting forecasts for the observed hour, then keeps the newly issued
+    /// forecast for each future hour. Returns the updated report
+    pub fn record(location: &LocationPoint, data: &WeatherData) -> AccuracyReport {
+        let units = data.units.unwrap_or(Units::Metric);
+        let mut accuracy = Self::load(location);
+
+        accuracy.observe(units, &data.current);
+        if let Some(provider) = data.provider.as_deref() {
+            accuracy.issue(units, provider, data);
+        }
+        accuracy.save(location);
+
+        accuracy.report(units)
+    }
+
+    fn observe(&mut self, units: Units, current: &CurrentWeather) {
+        let temp = to_celsius(units, current.temp);
+        let wind_speed = to_metres_per_second(units, current.wind_speed);
+        let observed_precipitation = precipitation(current);
+
+        let (matched, waiting): (Vec<_>, Vec<_>) = self.issued.drain(..)
+            .partition(|f| (f.valid_at - current.dt).abs() <= MATCH_SECONDS);
+        for forecast in matched {
+            self.samples.push(Sample {
+                lead_hours: (forecast.valid_at - forecast.issued_at) / 3600,
+                temp: forecast.temp - temp,
+                wind_speed: forecast.wind_speed - wind_speed,
+                precipitation: forecast.precipitation - observed_precipitation,
+                provider: forecast.provider,
+                valid_at: forecast.valid_at,
+            });
+        }
+
+        // Forecasts whose hour passed without an observation can no longer be scored
+        self.issued = waiting.into_iter()
+            .filter(|f| f.valid_at + MATCH_SECONDS >= current.dt)
+            .collect();
+        if self.samples.len() > MAX_SAMPLES {
+            let excess = self.samples.len() - MAX_SAMPLES;
+            self.samples.drain(..excess);
+        }
+    }
+
+    fn issue(&mut self, units: Units, provider: &str, data: &WeatherData) {
+        let issued_at = now();
+
+        for hour in data.hourly.iter() {
+            let lead_hours = (hour.dt - issued_at) / 3600;
+            if hour.dt <= issued_at + MATCH_SECONDS || lead_hours >= MAX_LEAD_HOURS {
+                continue;
+            }
+            // One forecast per provider, hour and lead time, refreshing often adds nothing new
+            let repeated = self.issued.iter().any(|f| f.provider == provider
+                && f.valid_at == hour.dt
+                && (f.valid_at - f.issued_at) / 3600 == lead_hours);
+            if repeated {
+                continue;
+            }
+
+            self.issued.push(IssuedForecast {
+                provider: provider.to_string(),
+                issued_at,
+                valid_at: hour.dt,
+                temp: to_celsius(units, hour.temp),
+                wind_speed: to_metres_per_second(units, hour.wind_speed),
+                precipitation: precipitation(hour),
+            });
+        }
+    }
+
+    pub fn report(&self, units: Units) -> AccuracyReport {
+        let temp_scale = match units {
+            Units::Metric => 1.0,
+            Units::Imperial => 1.8,
+        };
+        let wind_scale = speed_in(units, 1.0);
+
+        let mut names: Vec<&str> = Vec::new();
+        for sample in self.samples.iter() {
+            if !names.contains(&sample.provider.as_str()) {
+                names.push(&sample.provider);
+            }
+        }
+
+        let mut providers = names.into_iter()
+            .map(|name| {
+                let leads = LEAD_BUCKETS.iter()
+                    .map(|(from_hours, to_hours)| {
+                        let samples = self.samples.iter()
+                            .filter(|s| s.provider == name
+                                && s.lead_hours >= *from_hours
+                                && s.lead_hours < *to_hours)
+                            .collect::<Vec<_>>();
+                        let errors = |error: fn(&Sample) -> f64| samples.iter()
+                            .map(|s| error(s))
+                            .collect::<Vec<_>>();
+
+                        LeadAccuracy {
+                            from_hours: *from_hours,
+                            to_hours: *to_hours,
+                            samples: samples.len(),
+                            temp: stats(&errors(|s| s.temp), temp_scale),
+                            wind_speed: stats(&errors(|s| s.wind_speed), wind_scale),
+                            precipitation: stats(&errors(|s| s.precipitation), 1.0),
+                        }
+                    })
+                    .collect();
+
+                ProviderAccuracy {
+                    provider: name.to_string(),
+                    leads,
+                }
+            })
+            .collect::<Vec<_>>();
+        providers.sort_by(|a, b| a.temp_mae()
+            .partial_cmp(&b.temp_mae())
+            .unwrap_or(std::cmp::Ordering::Equal));
+
+        AccuracyReport {
+            units,
+            providers,
+            pending: self.issued.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: i64 = 3600;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    fn hour(dt: i64, temp: f64, wind_speed: f64, rain: Option<f64>) -> HourlyWeather {
+        HourlyWeather {
+            dt,
+            sunrise: None,
+            sunset: None,
+            temp,
+            feels_like: temp,
+            pressure: 1013,
+            humidity: 60,
+            dew_point: 5.0,
+            uvi: 0.0,
+            clouds: 50.0,
+            visibility: None,
+            wind_speed,
+            wind_deg: 0,
+            wind_gust: None,
+            status: Vec::new(),
+            pop: 0.0,
+            rain,
+            snow: None,
+        }
+    }
+
+    fn forecast(provider: &str, valid_hour: i64, temp: f64) -> IssuedForecast {
+        IssuedForecast {
+            provider: String::from(provider),
+            issued_at: 0,
+            valid_at: valid_hour * HOUR,
+            temp,
+            wind_speed: 5.0,
+            precipitation: 1.0,
+        }
+    }
+
+    /// A at 3h and 4h ahead is out by +2 and -1, B at 3h by -1. A's forecast
+    /// 10h ahead is never observed
+    fn scored() -> ForecastAccuracy {
+        let mut accuracy = ForecastAccuracy::default();
+        accuracy.issued = vec![
+            forecast("A", 3, 12.0),
+            forecast("A", 4, 8.0),
+            forecast("B", 3, 9.0),
+            forecast("A", 10, 20.0),
+        ];
+
+        accuracy.observe(Units::Metric, &hour(3 * HOUR + 600, 10.0, 4.0, Some(0.5)));
+        assert_eq!(accuracy.samples.len(), 2);
+        assert_eq!(accuracy.issued.len(), 2);
+        accuracy.observe(Units::Metric, &hour(4 * HOUR, 9.0, 6.0, None));
+        assert_eq!(accuracy.samples.len(), 3);
+        // The 10h forecast's hour passes with no observation near it
+        accuracy.observe(Units::Metric, &hour(11 * HOUR, 15.0, 2.0, None));
+        assert_eq!(accuracy.samples.len(), 3);
+
+        accuracy
+    }
+
+    #[test]
+    fn scores_observed_hours() {
+        let accuracy = scored();
+        assert!(accuracy.issued.is_empty());
+
+        let sample = &accuracy.samples[0];
+        assert_eq!(sample.provider, "A");
+        assert_eq!(sample.lead_hours, 3);
+        assert!(close(sample.temp, 2.0));
+        assert!(close(sample.wind_speed, 1.0));
+        assert!(close(sample.precipitation, 0.5));
+    }
+
+    #[test]
+    fn reports_metric_errors() {
+        let report = scored().report(Units::Metric);
+        assert_eq!(report.pending, 0);
+
+        // Ranked by temperature error
+        let names = report.providers.iter().map(|p| p.provider.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["B", "A"]);
+
+        let a = &report.providers[1];
+        assert_eq!(a.samples(), 2);
+        let short = &a.leads[0];
+        assert_eq!((short.from_hours, short.to_hours, short.samples), (0, 6, 2));
+        assert!(close(short.temp.mae, 1.5));
+        assert!(close(short.temp.bias, 0.5));
+        assert!(close(short.wind_speed.mae, 1.0));
+        assert!(close(short.wind_speed.bias, 0.0));
+        assert!(close(short.precipitation.mae, 0.75));
+        assert!(close(short.precipitation.bias, 0.75));
+        assert!(a.leads[1..].iter().all(|l| l.samples == 0));
+        assert!(close(a.temp_mae().unwrap(), 1.5));
+
+        let b = &report.providers[0];
+        assert!(close(b.leads[0].temp.mae, 1.0));
+        assert!(close(b.leads[0].temp.bias, -1.0));
+    }
+
+    #[test]
+    fn reports_imperial_errors() {
+        let report = scored().report(Units::Imperial);
+        let short = &report.providers[1].leads[0];
+
+        assert!(close(short.temp.mae, 1.5 * 1.8));
+        assert!(close(short.temp.bias, 0.5 * 1.8));
+        assert!(close(short.wind_speed.mae, 2.236_936));
+        // Precipitation stays in millimetres
+        assert!(close(short.precipitation.mae, 0.75));
+    }
+
+    #[test]
+    fn observes_in_imperial_units() {
+        let mut accuracy = ForecastAccuracy::default();
+        accuracy.issued = vec![forecast("A", 1, 12.0)];
+
+        // 50°F and 10 mph
+        accuracy.observe(Units::Imperial, &hour(HOUR, 50.0, 10.0, None));
+        let sample = &accuracy.samples[0];
+        assert!(close(sample.temp, 2.0));
+        assert!(close(sample.wind_speed, 5.0 - 10.0 / 2.236_936));
+    }
+
+    #[test]
+    fn issues_each_future_hour_once() {
+        let start = now();
+        let data = WeatherData {
+            current: hour(start, 10.0, 3.0, None),
+            hourly: vec![
+                hour(start - HOUR, 9.0, 3.0, None),
+                // Too close to now to be a forecast
+                hour(start + 600, 10.0, 3.0, None),
+                hour(start + 2 * HOUR + 60, 32.0, 2.0, Some(1.0)),
+                hour(start + MAX_LEAD_HOURS * HOUR + 60, 11.0, 3.0, None),
+            ],
+            minutely: Vec::new(),
+            daily: Vec::new(),
+            alerts: Vec::new(),
+            units: Some(Units::Imperial),
+            provider: Some(String::from("A")),
+            provenance: Vec::new(),
+            utc_offset: None,
+        };
+
+        let mut accuracy = ForecastAccuracy::default();
+        accuracy.issue(Units::Imperial, "A", &data);
+        accuracy.issue(Units::Imperial, "A", &data);
+
+        assert_eq!(accuracy.issued.len(), 1);
+        let issued = &accuracy.issued[0];
+        assert_eq!(issued.valid_at, start + 2 * HOUR + 60);
+        assert!(close(issued.temp, 0.0));
+        assert!(close(issued.precipitation, 1.0));
+        assert_eq!(accuracy.report(Units::Metric).pending, 1);
+    }
+}
@@ -1,4 +1,16 @@
 pub mod units;
 pub mod weather;
 pub mod location;
+pub mod provider;
+pub mod error;
+pub mod client;
+pub mod air_quality;
+pub mod pollen;
 
+pub mod marine;
+pub mod history;
+pub mod map;
+pub mod aviation;
+pub mod models;
+pub mod station;
+pub mod geoclue;
@@ -0,0 +1,137 @@
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use super::error::WeatherError;
+use super::client::HttpClient;
+
+const AIR_POLLUTION_API_URL: &str = "https://api.openweathermap.org/data";
+const AIR_POLLUTION_API_VERSION: &str = "2.5";
+
+/// Concentrations in μg/m3
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Pollutants {
+    pub pm2_5: f64,
+    pub pm10: f64,
+    pub o3: f64,
+    pub no2: f64,
+    pub so2: f64,
+    pub co: f64,
+}
+
+#[derive(Deserialize)]
+struct AirQualityIndex {
+    aqi: u32,
+}
+
+#[derive(Deserialize)]
+struct AirPollutionEntry {
+    dt: i64,
+    main: AirQualityIndex,
+    components: Pollutants,
+}
+
+#[derive(Deserialize)]
+struct AirPollutionResponse {
+    list: Vec<AirPollutionEntry>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct AirQuality {
+    pub dt: i64,
+    /// OpenWeather's air quality index, 1 (Good) to 5 (Very Poor)
+    pub aqi: u32,
+    pub pollutants: Pollutants,
+}
+
+pub struct AirQualityData {
+    pub current: AirQuality,
+    pub forecast: Vec<AirQuality>,
+}
+
+/// A pollutant, with the concentration at which OpenWeather rates it Very Poor
+pub struct Pollutant {
+    pub name: &'static str,
+    pub very_poor: f64,
+    pub value: fn(&Pollutants) -> f64,
+}
+
+pub const POLLUTANTS: &[Pollutant] = &[
+    Pollutant { name: "PM2.5", very_poor: 75.0, value: |p| p.pm2_5 },
+    Pollutant { name: "PM10", very_poor: 200.0, value: |p| p.pm10 },
+    Pollutant { name: "O₃", very_poor: 180.0, value: |p| p.o3 },
+    Pollutant { name: "NO₂", very_poor: 200.0, value: |p| p.no2 },
+    Pollutant { name: "SO₂", very_poor: 350.0, value: |p| p.so2 },
+    Pollutant { name: "CO", very_poor: 15400.0, value: |p| p.co },
+];
+
+impl From<AirPollutionEntry> for AirQuality {
+    fn from(entry: AirPollutionEntry) -> Self {
+        AirQuality {
+            dt: entry.dt,
+            aqi: entry.main.aqi,
+            pollutants: entry.components,
+        }
+    }
+}
+
+impl AirQuality {
+    pub fn category(&self) -> &str {
+        match self.aqi {
+            1 => "Good",
+            2 => "Fair",
+            3 => "Moderate",
+            4 => "Poor",
+            5 => "Very Poor",
+            _ => "Unknown",
+        }
+    }
+
+    /// Guidance for sensitive groups (asthma, heart or lung conditions, children and older adults)
+    pub fn sensitive_advice(&self) -> &str {
+        match self.aqi {
+            1 => "Air quality is good, no precautions are needed.",
+            2 => "Unusually sensitive people should consider reducing prolonged or heavy exertion outdoors.",
+            3 => "Sensitive groups should reduce prolonged or heavy exertion outdoors and keep reliever medication to hand.",
+            4 => "Sensitive groups should avoid prolonged or heavy exertion outdoors, and consider moving activities indoors.",
+            5 => "Sensitive groups should avoid all physical activity outdoors and stay indoors where possible.",
+            _ => "No guidance is available.",
+        }
+    }
+}
+
+fn base_url() -> String {
+    format!("{}/{}", 
+        AIR_POLLUTION_API_URL, 
+        AIR_POLLUTION_API_VERSION)
+}
+
+async fn get_air_pollution(endpoint: &str, api_key: &str, lat: f64, lon: f64) -> Result<Vec<AirQuality>, WeatherError> {
+    let url = format!("{}/{}?lat={}&lon={}&appid={}",
+        base_url(),
+        endpoint,
+        lat, lon,
+        api_key.trim());
+    let response: AirPollutionResponse = HttpClient::shared().get_json(&url, &[]).await?;
+
+    Ok(response.list.into_iter()
+        .map(AirQuality::from)
+        .collect())
+}
+
+pub async fn get_air_quality(api_key: Option<&str>, lat: f64, lon: f64) -> Result<AirQualityData, WeatherError> {
+    let api_key = api_key.ok_or(WeatherError::MissingApiKey)?;
+    let current = get_air_pollution("air_pollution", api_key, lat, lon).await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| WeatherError::Decode {
+            path: String::from("list"),
+            message: String::from("no current air quality"),
+        })?;
+    let forecast = get_air_pollution("air_pollution/forecast", api_key, lat, lon).await?;
+
+    Ok(AirQualityData {
+        current,
+        forecast,
+    })
+}
@@ -0,0 +1,759 @@
+use std::fs::read_to_string;
+use super::error::{
+    WeatherError,
+    check_status,
+};
+use super::client::HttpClient;
+use super::provider::user_agent;
+
+pub const DEFAULT_METAR_URL: &str = "https://aviationweather.gov/api/data/metar?ids={icao}&format=raw";
+pub const DEFAULT_TAF_URL: &str = "https://aviationweather.gov/api/data/taf?ids={icao}&format=raw";
+
+const METRES_PER_STATUTE_MILE: f64 = 1609.344;
+const KNOTS_PER_MPS: f64 = 1.943_844;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum FlightCategory {
+    Vfr,
+    Mvfr,
+    Ifr,
+    Lifr,
+}
+
+impl std::fmt::Display for FlightCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            FlightCategory::Vfr => write!(f, "VFR"),
+            FlightCategory::Mvfr => write!(f, "MVFR"),
+            FlightCategory::Ifr => write!(f, "IFR"),
+            FlightCategory::Lifr => write!(f, "LIFR"),
+        }
+    }
+}
+
+impl FlightCategory {
+    /// FAA categories from the ceiling (feet above ground) and visibility (statute miles),
+    /// the worse of the two deciding
+    pub fn classify(ceiling: Option<u32>, visibility: Option<f64>) -> Option<FlightCategory> {
+        if ceiling.is_none() && visibility.is_none() {
+            return None;
+        }
+        let ceiling = ceiling.unwrap_or(u32::MAX);
+        let visibility = visibility.unwrap_or(f64::MAX);
+
+        Some(if ceiling < 500 || visibility < 1.0 {
+            FlightCategory::Lifr
+        } else if ceiling < 1000 || visibility < 3.0 {
+            FlightCategory::Ifr
+        } else if ceiling <= 3000 || visibility <= 5.0 {
+            FlightCategory::Mvfr
+        } else {
+            FlightCategory::Vfr
+        })
+    }
+}
+
+/// Wind in knots, direction is None when variable
+#[derive(Clone, PartialEq)]
+pub struct Wind {
+    pub direction: Option<u32>,
+    pub speed: f64,
+    pub gust: Option<f64>,
+    pub variable_between: Option<(u32, u32)>,
+}
+
+impl std::fmt::Display for Wind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if self.speed == 0.0 {
+            return write!(f, "Calm");
+        }
+        match self.direction {
+            Some(direction) => write!(f, "{:03}° at {:.0} kt", direction, self.speed)?,
+            None => write!(f, "Variable at {:.0} kt", self.speed)?,
+        }
+        if let Some(gust) = self.gust {
+            write!(f, " gusting {:.0} kt", gust)?;
+        }
+        if let Some((from, to)) = self.variable_between {
+            write!(f, " (varying {:03}°–{:03}°)", from, to)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct CloudLayer {
+    /// FEW, SCT, BKN, OVC or VV (vertical visibility into an obscured sky)
+    pub cover: String,
+    /// Feet above ground
+    pub base: u32,
+    /// CB or TCU
+    pub convective: Option<String>,
+}
+
+impl CloudLayer {
+    fn is_ceiling(&self) -> bool {
+        self.cover == "BKN" || self.cover == "OVC" || self.cover == "VV"
+    }
+}
+
+/// The conditions shared by METARs and each TAF change group
+#[derive(Clone, Default)]
+pub struct Conditions {
+    pub wind: Option<Wind>,
+    /// Statute miles
+    pub visibility: Option<f64>,
+    pub weather: Vec<String>,
+    pub clouds: Vec<CloudLayer>,
+    /// Sky clear, e.g. from SKC, CLR, NSC or CAVOK
+    pub clear: bool,
+}
+
+impl Conditions {
+    /// Lowest broken, overcast or obscured layer in feet
+    pub fn ceiling(&self) -> Option<u32> {
+        self.clouds.iter()
+            .filter(|c| c.is_ceiling())
+            .map(|c| c.base)
+            .min()
+    }
+
+    pub fn flight_category(&self) -> Option<FlightCategory> {
+        FlightCategory::classify(self.ceiling(), self.visibility)
+    }
+
+    pub fn visibility_text(&self) -> Option<String> {
+        self.visibility.map(|v| if v >= 6.0 {
+            String::from("6+ SM")
+        } else {
+            format!("{:.1} SM", v)
+        })
+    }
+
+    pub fn clouds_text(&self) -> String {
+        if self.clouds.is_empty() {
+            return String::from(if self.clear { "Clear" } else { "--" });
+        }
+        self.clouds.iter()
+            .map(|c| format!("{} {} ft{}",
+                c.cover,
+                c.base,
+                c.convective.as_ref().map(|t| format!(" {}", t)).unwrap_or_default()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Decodes a token into the conditions, returning false if it is not a condition
+    fn decode(&mut self, token: &str, previous: Option<&str>) -> bool {
+        if !token.is_ascii() {
+            return false;
+        }
+        if let Some(wind) = parse_wind(token) {
+            self.wind = Some(wind);
+        } else if let Some(range) = parse_wind_variation(token) {
+            if let Some(wind) = &mut self.wind {
+                wind.variable_between = Some(range);
+            }
+        } else if let Some(visibility) = parse_visibility(token, previous) {
+            self.visibility = Some(visibility);
+        } else if token == "CAVOK" {
+            self.visibility = Some(10.0);
+            self.clear = true;
+        } else if token == "SKC" || token == "CLR" || token == "NSC" || token == "NCD" {
+            self.clear = true;
+        } else if let Some(layer) = parse_cloud(token) {
+            self.clouds.push(layer);
+        } else if is_weather(token) {
+            self.weather.push(String::from(token));
+        } else {
+            return false;
+        }
+        true
+    }
+}
+
+pub struct Metar {
+    pub raw: String,
+    pub station: String,
+    /// Observation time as DDHHMMZ
+    pub time: Option<String>,
+    pub conditions: Conditions,
+    /// Celsius
+    pub temperature: Option<i32>,
+    pub dew_point: Option<i32>,
+    /// Hectopascals
+    pub pressure: Option<f64>,
+}
+
+pub struct TafGroup {
+    /// FM, BECMG, TEMPO, PROB30 or PROB40, empty for the base forecast
+    pub change: String,
+    /// Validity as given, e.g. 1218/1224 or 121800
+    pub period: Option<String>,
+    pub conditions: Conditions,
+}
+
+pub struct Taf {
+    pub raw: String,
+    pub station: String,
+    pub groups: Vec<TafGroup>,
+}
+
+pub struct AviationReport {
+    pub metar: Metar,
+    pub taf: Option<Taf>,
+}
+
+fn parse_number<T: std::str::FromStr>(text: &str) -> Option<T> {
+    if text.is_empty() || !text.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    text.parse().ok()
+}
+
+/// dddssKT, dddssGggKT, VRBssKT, or the same in MPS
+fn parse_wind(token: &str) -> Option<Wind> {
+    let (body, factor) = if let Some(body) = token.strip_suffix("KT") {
+        (body, 1.0)
+    } else if let Some(body) = token.strip_suffix("MPS") {
+        (body, KNOTS_PER_MPS)
+    } else {
+        return None;
+    };
+    if body.len() < 5 {
+        return None;
+    }
+    let direction = match &body[..3] {
+        "VRB" => None,
+        direction => Some(parse_number::<u32>(direction)?),
+    };
+    let rest = &body[3..];
+    let (speed, gust) = match rest.split_once('G') {
+        Some((speed, gust)) => (speed, Some(parse_number::<f64>(gust)? * factor)),
+        None => (rest, None),
+    };
+
+    Some(Wind {
+        direction,
+        speed: parse_number::<f64>(speed)? * factor,
+        gust,
+        variable_between: None,
+    })
+}
+
+/// dddVddd
+fn parse_wind_variation(token: &str) -> Option<(u32, u32)> {
+    if token.len() != 7 {
+        return None;
+    }
+    let (from, to) = token.split_once('V')?;
+
+    Some((parse_number(from)?, parse_number(to)?))
+}
+
+fn parse_fraction(text: &str) -> Option<f64> {
+    match text.split_once('/') {
+        Some((numerator, denominator)) => {
+            let denominator = parse_number::<f64>(denominator)?;
+            if denominator == 0.0 {
+                return None;
+            }
+            Some(parse_number::<f64>(numerator)? / denominator)
+        },
+        None => parse_number(text),
+    }
+}
+
+/// Statute miles (10SM, 1/2SM, P6SM, M1/4SM, with a whole number in the previous
+/// token for 1 1/2SM) or four digit metres (9999 meaning 10km or more)
+fn parse_visibility(token: &str, previous: Option<&str>) -> Option<f64> {
+    if let Some(miles) = token.strip_suffix("SM") {
+        let miles = miles.trim_start_matches(|c| c == 'P' || c == 'M');
+        let mut value = parse_fraction(miles)?;
+        if miles.contains('/') {
+            if let Some(whole) = previous.and_then(parse_number::<f64>) {
+                value += whole;
+            }
+        }
+        return Some(value);
+    }
+    if token.len() == 4 {
+        let metres = parse_number::<f64>(token)?;
+        return Some(if metres >= 9999.0 {
+            10.0
+        } else {
+            metres / METRES_PER_STATUTE_MILE
+        });
+    }
+    None
+}
+
+/// FEW020, SCT035CB, BKN100TCU, OVC008, VV002
+fn parse_cloud(token: &str) -> Option<CloudLayer> {
+    let cover = ["FEW", "SCT", "BKN", "OVC", "VV"].iter()
+        .find(|cover| token.starts_with(*cover))?;
+    let rest = &token[cover.len()..];
+    if rest.len() < 3 {
+        return None;
+    }
+    let base = parse_number::<u32>(&rest[..3])? * 100;
+    let convective = match &rest[3..] {
+        "" => None,
+        "CB" | "TCU" => Some(String::from(&rest[3..])),
+        _ => return None,
+    };
+
+    Some(CloudLayer {
+        cover: String::from(*cover),
+        base,
+        convective,
+    })
+}
+
+const WEATHER_CODES: &[&str] = &[
+    "MI", "PR", "BC", "DR", "BL", "SH", "TS", "FZ",
+    "DZ", "RA", "SN", "SG", "IC", "PL", "GR", "GS", "UP",
+    "BR", "FG", "FU", "VA", "DU", "SA", "HZ", "PY",
+    "PO", "SQ", "FC", "SS", "DS",
+];
+
+/// Present weather such as -RA, +TSRA, VCSH or FZFG
+fn is_weather(token: &str) -> bool {
+    let codes = token.trim_start_matches(|c| c == '-' || c == '+');
+    let codes = codes.strip_prefix("VC").unwrap_or(codes);
+    if codes.is_empty() || codes.len() % 2 != 0 {
+        return false;
+    }
+
+    (0..codes.len()).step_by(2)
+        .all(|i| WEATHER_CODES.contains(&&codes[i..i + 2]))
+}
+
+/// M05 for -5
+fn parse_temperature(text: &str) -> Option<i32> {
+    match text.strip_prefix('M') {
+        Some(negative) => parse_number::<i32>(negative).map(|t| -t),
+        None => parse_number(text),
+    }
+}
+
+fn decode_error(raw: &str, message: &str) -> WeatherError {
+    WeatherError::Decode {
+        path: String::from(raw.split_whitespace().next().unwrap_or("")),
+        message: String::from(message),
+    }
+}
+
+/// Strips the optional METAR/SPECI/TAF prefix and amendment markers, returning the station
+fn station<'a>(tokens: &mut std::iter::Peekable<impl Iterator<Item = &'a str>>) -> Option<String> {
+    while let Some(token) = tokens.peek() {
+        match *token {
+            "METAR" | "SPECI" | "TAF" | "AMD" | "COR" => {
+                tokens.next();
+            },
+            _ => break,
+        }
+    }
+    let station = tokens.next()?;
+    if station.len() == 4 && station.chars().all(|c| c.is_ascii_alphanumeric()) {
+        Some(String::from(station))
+    } else {
+        None
+    }
+}
+
+pub fn parse_metar(raw: &str) -> Result<Metar, WeatherError> {
+    let raw = raw.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut tokens = raw.split_whitespace().peekable();
+    let station = station(&mut tokens)
+        .ok_or_else(|| decode_error(&raw, "expected a four letter station identifier"))?;
+
+    let mut metar = Metar {
+        raw: raw.clone(),
+        station,
+        time: None,
+        conditions: Conditions::default(),
+        temperature: None,
+        dew_point: None,
+        pressure: None,
+    };
+
+    let mut previous = None;
+    for token in tokens {
+        if token == "RMK" {
+            break;
+        }
+        if token.len() == 7 && token.ends_with('Z') && parse_number::<u32>(&token[..6]).is_some() {
+            metar.time = Some(String::from(token));
+        } else if metar.conditions.decode(token, previous) {
+            // decoded into the conditions
+        } else if let Some(pressure) = token.strip_prefix('Q').and_then(parse_number::<f64>) {
+            metar.pressure = Some(pressure);
+        } else if let Some(inches) = token.strip_prefix('A').and_then(parse_number::<f64>) {
+            metar.pressure = Some(inches / 100.0 * 33.8639);
+        } else if let Some((temperature, dew_point)) = token.split_once('/') {
+            if let Some(temperature) = parse_temperature(temperature) {
+                metar.temperature = Some(temperature);
+                metar.dew_point = parse_temperature(dew_point);
+            }
+        }
+        previous = Some(token);
+    }
+
+    Ok(metar)
+}
+
+fn is_change(token: &str) -> bool {
+    token.starts_with("FM") && parse_number::<u32>(&token[2..]).is_some()
+        || token == "BECMG"
+        || token == "TEMPO"
+        || token == "PROB30"
+        || token == "PROB40"
+}
+
+fn is_period(token: &str) -> bool {
+    match token.split_once('/') {
+        Some((from, to)) => parse_number::<u32>(from).is_some() && parse_number::<u32>(to).is_some(),
+        None => false,
+    }
+}
+
+pub fn parse_taf(raw: &str) -> Result<Taf, WeatherError> {
+    let raw = raw.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut tokens = raw.split_whitespace().peekable();
+    let station = station(&mut tokens)
+        .ok_or_else(|| decode_error(&raw, "expected a four letter station identifier"))?;
+
+    let mut groups = vec![TafGroup {
+        change: String::new(),
+        period: None,
+        conditions: Conditions::default(),
+    }];
+    let mut previous = None;
+    for token in tokens {
+        if token == "RMK" {
+            break;
+        }
+        let group = groups.last_mut().unwrap();
+        if token.starts_with("FM") && is_change(token) {
+            groups.push(TafGroup {
+                change: String::from("FM"),
+                period: Some(String::from(&token[2..])),
+                conditions: Conditions::default(),
+            });
+        } else if is_change(token) {
+            // TEMPO following PROB30 belongs to the same group
+            if group.change.starts_with("PROB") && token == "TEMPO" && group.period.is_none() {
+                group.change += " TEMPO";
+            } else {
+                groups.push(TafGroup {
+                    change: String::from(token),
+                    period: None,
+                    conditions: Conditions::default(),
+                });
+            }
+        } else if is_period(token) {
+            if group.period.is_none() {
+                group.period = Some(String::from(token));
+            }
+        } else {
+            group.conditions.decode(token, previous);
+        }
+        previous = Some(token);
+    }
+
+    Ok(Taf {
+        raw,
+        station,
+        groups,
+    })
+}
+
+/// Reads a report from a URL template with {icao}, or from a local file (file:// or a path)
+async fn get_report(source: &str, icao: &str) -> Result<String, WeatherError> {
+    let source = source.replace("{icao}", icao);
+    if !source.starts_with("http://") && !source.starts_with("https://") {
+        let path = source.strip_prefix("file://").unwrap_or(&source);
+        return Ok(read_to_string(path)?);
+    }
+
+    let user_agent = user_agent();
+    let response = HttpClient::shared().get(&source, &[("User-Agent", &user_agent)]).await?;
+    check_status(&response)?;
+
+    Ok(response.into_body())
+}
+
+/// Reports may hold several stations, keep the lines for the one asked for
+fn station_report(text: &str, icao: &str) -> Option<String> {
+    let mut report: Vec<&str> = Vec::new();
+    for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        let starts_report = line.split_whitespace()
+            .find(|t| !["METAR", "SPECI", "TAF", "AMD", "COR"].contains(t))
+            .map(|t| t.len() == 4 && t.chars().all(|c| c.is_ascii_uppercase()))
+            .unwrap_or(false);
+        if starts_report && !report.is_empty() {
+            break;
+        }
+        let is_station = line.split_whitespace().any(|t| t.eq_ignore_ascii_case(icao));
+        if !report.is_empty() || (starts_report && is_station) {
+            report.push(line);
+        }
+    }
+
+    if report.is_empty() {
+        None
+    } else {
+        Some(report.join(" "))
+    }
+}
+
+/// The latest METAR and TAF for an aerodrome, a missing or unreadable TAF is not an error
+/// as smaller aerodromes do not issue one
+pub async fn get_aviation(metar_source: &str, taf_source: &str, icao: &str) -> Result<AviationReport, WeatherError> {
+    let icao = icao.trim().to_uppercase();
+    let text = get_report(metar_source, &icao).await?;
+    let metar = station_report(&text, &icao)
+        .ok_or_else(|| WeatherError::Decode {
+            path: icao.clone(),
+            message: String::from("no METAR for this station"),
+        })?;
+    let metar = parse_metar(&metar)?;
+
+    let taf = match get_report(taf_source, &icao).await {
+        Ok(text) => match station_report(&text, &icao) {
+            // The METAR is still worth showing when the TAF can not be read
+            Some(taf) => match parse_taf(&taf) {
+                Ok(taf) => Some(taf),
+                Err(err) => {
+                    println!("Unable to read TAF for {}: {}", icao, err);
+                    None
+                },
+            },
+            None => None,
+        },
+        Err(err) => {
+            println!("Unable to get TAF for {}: {}", icao, err);
+            None
+        },
+    };
+
+    Ok(AviationReport {
+        metar,
+        taf,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    #[test]
+    fn parses_us_metar() {
+        let metar = parse_metar("METAR KJFK 121851Z 31015G25KT 280V350 10SM FEW050 BKN250 M02/M12 A3012 RMK AO2 SLP201").unwrap();
+
+        assert_eq!(metar.station, "KJFK");
+        assert_eq!(metar.time.as_deref(), Some("121851Z"));
+        let wind = metar.conditions.wind.as_ref().unwrap();
+        assert_eq!(wind.direction, Some(310));
+        assert!(close(wind.speed, 15.0));
+        assert_eq!(wind.gust, Some(25.0));
+        assert_eq!(wind.variable_between, Some((280, 350)));
+        assert_eq!(metar.conditions.visibility, Some(10.0));
+        assert_eq!(metar.conditions.clouds.len(), 2);
+        assert_eq!(metar.conditions.ceiling(), Some(25000));
+        assert_eq!(metar.temperature, Some(-2));
+        assert_eq!(metar.dew_point, Some(-12));
+        assert!(close(metar.pressure.unwrap(), 30.12 * 33.8639));
+        assert!(metar.conditions.flight_category() == Some(FlightCategory::Vfr));
+    }
+
+    #[test]
+    fn parses_metar_with_mixed_fraction_visibility() {
+        let metar = parse_metar("KORD 121851Z 27008KT 1 1/2SM -SN BR OVC008 M03/M04 A2992").unwrap();
+
+        assert_eq!(metar.conditions.visibility, Some(1.5));
+        assert_eq!(metar.conditions.weather, vec!["-SN", "BR"]);
+        assert_eq!(metar.conditions.ceiling(), Some(800));
+        assert!(metar.conditions.flight_category() == Some(FlightCategory::Ifr));
+    }
+
+    #[test]
+    fn parses_metric_metar() {
+        let metar = parse_metar("EGLL 121850Z AUTO 24012KT 9999 SCT035CB 12/08 Q1013 NOSIG").unwrap();
+
+        assert_eq!(metar.station, "EGLL");
+        assert_eq!(metar.conditions.visibility, Some(10.0));
+        let cloud = &metar.conditions.clouds[0];
+        assert_eq!(cloud.cover, "SCT");
+        assert_eq!(cloud.base, 3500);
+        assert_eq!(cloud.convective.as_deref(), Some("CB"));
+        assert_eq!(metar.conditions.ceiling(), None);
+        assert_eq!(metar.temperature, Some(12));
+        assert_eq!(metar.pressure, Some(1013.0));
+    }
+
+    #[test]
+    fn parses_cavok_metar() {
+        let metar = parse_metar("LFPG 121830Z 05005MPS CAVOK 18/06 Q1021").unwrap();
+
+        let wind = metar.conditions.wind.as_ref().unwrap();
+        assert!(close(wind.speed, 5.0 * KNOTS_PER_MPS));
+        assert!(metar.conditions.clear);
+        assert_eq!(metar.conditions.clouds_text(), "Clear");
+        assert!(metar.conditions.flight_category() == Some(FlightCategory::Vfr));
+    }
+
+    #[test]
+    fn rejects_metar_without_station() {
+        assert!(matches!(parse_metar("121851Z 31015KT 10SM"), Err(WeatherError::Decode { .. })));
+        assert!(matches!(parse_metar(""), Err(WeatherError::Decode { .. })));
+    }
+
+    #[test]
+    fn parses_taf_groups() {
+        let taf = parse_taf("TAF KJFK 121720Z 1218/1324 31012G22KT P6SM SCT050 \
+            FM130000 32008KT P6SM SKC \
+            TEMPO 1306/1310 3SM BR \
+            PROB40 TEMPO 1312/1316 -SHRA BKN015").unwrap();
+
+        assert_eq!(taf.station, "KJFK");
+        let groups = taf.groups.iter()
+            .map(|g| (g.change.as_str(), g.period.as_deref()))
+            .collect::<Vec<_>>();
+        assert_eq!(groups, vec![
+            ("", Some("1218/1324")),
+            ("FM", Some("130000")),
+            ("TEMPO", Some("1306/1310")),
+            ("PROB40 TEMPO", Some("1312/1316")),
+        ]);
+
+        let base = &taf.groups[0].conditions;
+        assert_eq!(base.wind.as_ref().unwrap().gust, Some(22.0));
+        assert_eq!(base.visibility, Some(6.0));
+        assert!(taf.groups[1].conditions.clear);
+        assert_eq!(taf.groups[2].conditions.visibility, Some(3.0));
+        assert_eq!(taf.groups[2].conditions.weather, vec!["BR"]);
+        assert_eq!(taf.groups[3].conditions.ceiling(), Some(1500));
+        assert!(taf.groups[3].conditions.flight_category() == Some(FlightCategory::Mvfr));
+    }
+
+    #[test]
+    fn rejects_taf_without_station() {
+        assert!(matches!(parse_taf("TAF 121720Z 1218/1324 31012KT"), Err(WeatherError::Decode { .. })));
+    }
+
+    #[test]
+    fn parses_visibility() {
+        let cases = [
+            ("10SM", None, Some(10.0)),
+            ("P6SM", None, Some(6.0)),
+            ("1/2SM", None, Some(0.5)),
+            ("1/2SM", Some("1"), Some(1.5)),
+            ("M1/4SM", None, Some(0.25)),
+            ("3SM", Some("2"), Some(3.0)),
+            ("9999", None, Some(10.0)),
+            ("0800", None, Some(800.0 / METRES_PER_STATUTE_MILE)),
+            ("1/0SM", None, None),
+            ("999", None, None),
+            ("KJFK", None, None),
+        ];
+        for (token, previous, expected) in cases.iter() {
+            let visibility = parse_visibility(token, *previous);
+            let matches = match (visibility, expected) {
+                (Some(visibility), Some(expected)) => close(visibility, *expected),
+                (None, None) => true,
+                _ => false,
+            };
+            assert!(matches, "{} after {:?} gave {:?}", token, previous, visibility);
+        }
+    }
+
+    #[test]
+    fn parses_wind() {
+        let wind = parse_wind("VRB03KT").unwrap();
+        assert_eq!(wind.direction, None);
+        assert_eq!(wind.speed, 3.0);
+        assert_eq!(wind.to_string(), "Variable at 3 kt");
+
+        let wind = parse_wind("00000KT").unwrap();
+        assert_eq!(wind.to_string(), "Calm");
+
+        let wind = parse_wind("090105G130KT").unwrap();
+        assert_eq!(wind.direction, Some(90));
+        assert_eq!(wind.speed, 105.0);
+        assert_eq!(wind.gust, Some(130.0));
+        assert_eq!(wind.to_string(), "090° at 105 kt gusting 130 kt");
+
+        for token in ["31015", "3101KT", "ABC15KT", "31015GKT", "10SM"].iter() {
+            assert!(parse_wind(token).is_none(), "{}", token);
+        }
+    }
+
+    #[test]
+    fn parses_cloud() {
+        let cloud = parse_cloud("BKN100TCU").unwrap();
+        assert_eq!(cloud.cover, "BKN");
+        assert_eq!(cloud.base, 10000);
+        assert_eq!(cloud.convective.as_deref(), Some("TCU"));
+        assert!(cloud.is_ceiling());
+
+        let cloud = parse_cloud("VV002").unwrap();
+        assert_eq!(cloud.base, 200);
+        assert!(cloud.is_ceiling());
+
+        assert!(!parse_cloud("FEW020").unwrap().is_ceiling());
+        for token in ["OVC", "SCT03", "BKN020XX", "SKC"].iter() {
+            assert!(parse_cloud(token).is_none(), "{}", token);
+        }
+    }
+
+    #[test]
+    fn classifies_flight_category() {
+        let cases = [
+            (None, None, None),
+            (Some(3500), Some(10.0), Some(FlightCategory::Vfr)),
+            (None, Some(5.5), Some(FlightCategory::Vfr)),
+            (Some(3000), None, Some(FlightCategory::Mvfr)),
+            (None, Some(5.0), Some(FlightCategory::Mvfr)),
+            (Some(1000), Some(3.0), Some(FlightCategory::Mvfr)),
+            (Some(999), Some(10.0), Some(FlightCategory::Ifr)),
+            (Some(5000), Some(2.9), Some(FlightCategory::Ifr)),
+            (Some(500), Some(1.0), Some(FlightCategory::Ifr)),
+            (Some(499), None, Some(FlightCategory::Lifr)),
+            (None, Some(0.75), Some(FlightCategory::Lifr)),
+            // The worse of ceiling and visibility decides
+            (Some(5000), Some(0.5), Some(FlightCategory::Lifr)),
+        ];
+        for (ceiling, visibility, expected) in cases.iter() {
+            let category = FlightCategory::classify(*ceiling, *visibility);
+            assert!(category == *expected, "{:?} ft, {:?} SM gave {:?}",
+                ceiling, visibility, category.map(|c| c.to_string()));
+        }
+    }
+
+    #[test]
+    fn finds_station_report() {
+        let text = "KJFK 121851Z 31015KT 10SM FEW050 M02/M12 A3012\n\
+            KLGA 121851Z 30012KT 10SM SCT045 M01/M11 A3011\n";
+        assert_eq!(station_report(text, "KLGA").as_deref(),
+            Some("KLGA 121851Z 30012KT 10SM SCT045 M01/M11 A3011"));
+        assert_eq!(station_report(text, "klga").as_deref(),
+            Some("KLGA 121851Z 30012KT 10SM SCT045 M01/M11 A3011"));
+        assert_eq!(station_report(text, "KEWR"), None);
+    }
+
+    #[test]
+    fn joins_taf_continuation_lines() {
+        let text = "TAF KJFK 121720Z 1218/1324 31012KT P6SM SCT050\n\
+              FM130000 32008KT P6SM SKC\n\
+              TEMPO 1306/1310 3SM BR\n\
+            TAF KLGA 121720Z 1218/1324 30010KT P6SM FEW050\n";
+        assert_eq!(station_report(text, "KJFK").as_deref(),
+            Some("TAF KJFK 121720Z 1218/1324 31012KT P6SM SCT050 FM130000 32008KT P6SM SKC TEMPO 1306/1310 3SM BR"));
+    }
+}
@@ -0,0 +1,275 @@
+use std::time::Duration;
+use once_cell::sync::OnceCell;
+use isahc::prelude::*;
+use isahc::config::RedirectPolicy;
+use isahc::Request;
+use http::{
+    Response,
+    StatusCode,
+    header::RETRY_AFTER,
+};
+use serde::de::DeserializeOwned;
+use super::error::{
+    WeatherError,
+    check_status,
+    decode,
+};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_RETRIES: u32 = 3;
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+static SHARED_CLIENT: OnceCell<HttpClient> = OnceCell::new();
+
+/// HTTP client shared by the weather and location APIs, applying timeouts
+/// and retrying failed GET requests with jittered exponential backoff
+pub struct HttpClient {
+    client: isahc::HttpClient,
+    max_retries: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl HttpClient {
+    pub fn new(connect_timeout: Duration, timeout: Duration, max_retries: u32, initial_backoff: Duration) -> Result<Self, WeatherError> {
+        let client = isahc::HttpClient::builder()
+            .connect_timeout(connect_timeout)
+            .timeout(timeout)
+            .redirect_policy(RedirectPolicy::Limit(5))
+            .build()?;
+
+        Ok(HttpClient {
+            client,
+            max_retries,
+            initial_backoff,
+            max_backoff: MAX_BACKOFF,
+        })
+    }
+
+    pub fn shared() -> &'static HttpClient {
+        SHARED_CLIENT.get_or_init(|| {
+            HttpClient::new(CONNECT_TIMEOUT, REQUEST_TIMEOUT, MAX_RETRIES, INITIAL_BACKOFF)
+                .expect("Unable to create HTTP client")
+        })
+    }
+
+    /// Backoff before the given retry (starting at 1), half fixed and half random
+    /// so that many clients failing together do not retry together
+    fn backoff(&self, retry: u32) -> Duration {
+        let exponential = self.initial_backoff
+            .checked_mul(1 << (retry - 1).min(16))
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff);
+        let half = exponential.as_millis() as u64 / 2;
+
+        Duration::from_millis(half + fastrand::u64(0..=half))
+    }
+
+    /// Retry-After is given in seconds, HTTP dates are treated as unknown
+    fn retry_after<B>(response: &Response<B>) -> Option<Duration> {
+        response.headers().get(RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .map(Duration::from_secs)
+    }
+
+    fn is_retryable(status: StatusCode) -> bool {
+        match status {
+            StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::INTERNAL_SERVER_ERROR
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT => true,
+            _ => false,
+        }
+    }
+
+    async fn send(&self, url: &str, headers: &[(&str, &str)]) -> Result<Response<Vec<u8>>, WeatherError> {
+        let mut request = Request::get(url);
+        for (name, value) in headers.iter() {
+            request = request.header(*name, *value);
+        }
+        let mut response = self.client.send_async(request.body(())?).await?;
+        let bytes = response.bytes().await?;
+        let (parts, _) = response.into_parts();
+
+        Ok(Response::from_parts(parts, bytes))
+    }
+
+    /// GETs the url, retrying on network failures, timeouts, 5xx responses and
+    /// 429 (waiting for Retry-After when given). The final response is returned
+    /// whatever its status, see get_json for checking it
+    pub async fn get(&self, url: &str, headers: &[(&str, &str)]) -> Result<Response<String>, WeatherError> {
+        let response = self.get_bytes(url, headers).await?;
+        let (parts, bytes) = response.into_parts();
+        let text = String::from_utf8_lossy(&bytes).into_owned();
+
+        Ok(Response::from_parts(parts, text))
+    }
+
+    /// As get, keeping the body as raw bytes for images
+    pub async fn get_bytes(&self, url: &str, headers: &[(&str, &str)]) -> Result<Response<Vec<u8>>, WeatherError> {
+        let mut retry = 0;
+        loop {
+            let result = self.send(url, headers).await;
+            if retry >= self.max_retries {
+                return result;
+            }
+            retry += 1;
+
+            let delay = match &result {
+                Ok(response) if Self::is_retryable(response.status()) => {
+                    let retry_after = if response.status() == StatusCode::TOO_MANY_REQUESTS {
+                        Self::retry_after(response)
+                    } else {
+                        None
+                    };
+                    match retry_after {
+                        // Not worth keeping the user waiting, report it as rate limited
+                        Some(retry_after) if retry_after > self.max_backoff => return result,
+                        Some(retry_after) => retry_after,
+                        None => self.backoff(retry),
+                    }
+                },
+                Err(err) if err.is_offline() => self.backoff(retry),
+                _ => return result,
+            };
+
+            gtk::glib::timeout_future(delay).await;
+        }
+    }
+
+    pub async fn get_json<T: DeserializeOwned>(&self, url: &str, headers: &[(&str, &str)]) -> Result<T, WeatherError> {
+        let response = self.get(url, headers).await?;
+        check_status(&response)?;
+
+        decode(response.body())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{
+        Read,
+        Write,
+    };
+    use std::net::TcpListener;
+    use std::sync::Arc;
+    use std::sync::atomic::{
+        AtomicUsize,
+        Ordering,
+    };
+    use std::thread;
+    use std::time::Instant;
+
+    /// Answers each connection with the next canned response, counting the requests made
+    fn serve(responses: Vec<&'static str>, delay: Duration) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/forecast", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+
+        thread::spawn(move || {
+            for response in responses {
+                let (mut stream, _) = match listener.accept() {
+                    Ok(connection) => connection,
+                    Err(_) => return,
+                };
+                let mut request = Vec::new();
+                let mut buffer = [0; 1024];
+                while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                    match stream.read(&mut buffer) {
+                        Ok(0) | Err(_) => break,
+                        Ok(read) => request.extend_from_slice(&buffer[..read]),
+                    }
+                }
+                counter.fetch_add(1, Ordering::SeqCst);
+                thread::sleep(delay);
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+
+        (url, requests)
+    }
+
+    fn client(timeout: Duration, max_retries: u32) -> HttpClient {
+        HttpClient::new(Duration::from_secs(1), timeout, max_retries, Duration::from_millis(10)).unwrap()
+    }
+
+    /// Runs on a context of the test's own, made the thread default so the backoff timers attach to it
+    fn run<F: std::future::Future>(future: F) -> F::Output {
+        let context = gtk::glib::MainContext::new();
+        context.with_thread_default(|| context.block_on(future))
+    }
+
+    const OK: &str = "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{}";
+    const UNAVAILABLE: &str = "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+    const RATE_LIMITED: &str = "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 1\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+    const NOT_FOUND: &str = "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+
+    #[test]
+    fn retries_server_errors() {
+        let (url, requests) = serve(vec![UNAVAILABLE, UNAVAILABLE, OK], Duration::ZERO);
+
+        let response = run(client(Duration::from_secs(5), 3).get(&url, &[])).unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body(), "{}");
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn returns_the_last_response_once_out_of_retries() {
+        let (url, requests) = serve(vec![UNAVAILABLE, UNAVAILABLE, OK], Duration::ZERO);
+
+        let result = run(client(Duration::from_secs(5), 1).get_json::<serde_json::Value>(&url, &[]));
+
+        assert!(matches!(result, Err(WeatherError::HttpStatus(503))));
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn waits_for_retry_after_when_rate_limited() {
+        let (url, requests) = serve(vec![RATE_LIMITED, OK], Duration::ZERO);
+
+        let started = Instant::now();
+        let response = run(client(Duration::from_secs(5), 3).get(&url, &[])).unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(started.elapsed() >= Duration::from_secs(1));
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn reports_rate_limiting_without_retries_left() {
+        let (url, _) = serve(vec![RATE_LIMITED], Duration::ZERO);
+
+        let result = run(client(Duration::from_secs(5), 0).get_json::<serde_json::Value>(&url, &[]));
+
+        assert!(matches!(result, Err(WeatherError::RateLimited(Some(1)))));
+    }
+
+    #[test]
+    fn does_not_retry_client_errors() {
+        let (url, requests) = serve(vec![NOT_FOUND, OK], Duration::ZERO);
+
+        let result = run(client(Duration::from_secs(5), 3).get_json::<serde_json::Value>(&url, &[]));
+
+        assert!(matches!(result, Err(WeatherError::HttpStatus(404))));
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn times_out_slow_responses() {
+        let (url, _) = serve(vec![OK, OK], Duration::from_secs(5));
+
+        let started = Instant::now();
+        let result = run(client(Duration::from_millis(200), 1).get(&url, &[]));
+
+        assert!(matches!(result, Err(WeatherError::Timeout)));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
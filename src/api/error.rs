@@ -0,0 +1,109 @@
+use serde::de::DeserializeOwned;
+use isahc::error::ErrorKind;
+
+#[derive(Debug, Clone)]
+pub enum WeatherError {
+    /// The service could not be reached
+    Network(String),
+    /// The service did not respond in time
+    Timeout,
+    /// The service responded with an unexpected HTTP status
+    HttpStatus(u16),
+    /// The provider needs an API key and none was configured
+    MissingApiKey,
+    /// The provider rejected the configured API key (HTTP 401)
+    InvalidApiKey,
+    /// Too many requests were made (HTTP 429), retry after the given seconds when known
+    RateLimited(Option<u64>),
+    /// The response could not be read, path is where in the document it failed
+    Decode {
+        path: String,
+        message: String,
+    },
+    /// A timestamp from the service could not be converted to a date & time
+    InvalidTimestamp(i64),
+    /// The device's position could not be found
+    Location(String),
+}
+
+impl std::fmt::Display for WeatherError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            WeatherError::Network(err) => write!(f, "Unable to connect: {}", err),
+            WeatherError::Timeout => write!(f, "The request timed out"),
+            WeatherError::HttpStatus(status) => write!(f, "Unexpected response (HTTP {})", status),
+            WeatherError::MissingApiKey => write!(f, "An API key is required"),
+            WeatherError::InvalidApiKey => write!(f, "The API key was rejected"),
+            WeatherError::RateLimited(Some(seconds)) => write!(f, "Too many requests, try again in {}s", seconds),
+            WeatherError::RateLimited(None) => write!(f, "Too many requests, try again later"),
+            WeatherError::Decode { path, message } => write!(f, "Unable to read data at {}: {}", path, message),
+            WeatherError::InvalidTimestamp(dt) => write!(f, "Invalid timestamp {}", dt),
+            WeatherError::Location(err) => write!(f, "Unable to find your location: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for WeatherError {}
+
+impl WeatherError {
+    /// Whether the service could not be reached at all, rather than refusing the request
+    pub fn is_offline(&self) -> bool {
+        match self {
+            WeatherError::Network(_) | WeatherError::Timeout => true,
+            _ => false,
+        }
+    }
+}
+
+impl From<isahc::Error> for WeatherError {
+    fn from(err: isahc::Error) -> Self {
+        match err.kind() {
+            ErrorKind::Timeout => WeatherError::Timeout,
+            _ => WeatherError::Network(err.to_string()),
+        }
+    }
+}
+
+impl From<std::io::Error> for WeatherError {
+    fn from(err: std::io::Error) -> Self {
+        match err.kind() {
+            std::io::ErrorKind::TimedOut => WeatherError::Timeout,
+            _ => WeatherError::Network(err.to_string()),
+        }
+    }
+}
+
+impl From<http::Error> for WeatherError {
+    fn from(err: http::Error) -> Self {
+        WeatherError::Network(err.to_string())
+    }
+}
+
+/// Maps unsuccessful HTTP responses onto their WeatherError
+pub fn check_status<T>(response: &http::Response<T>) -> Result<(), WeatherError> {
+    let status = response.status();
+    if status.is_success() || status == http::StatusCode::NOT_MODIFIED {
+        return Ok(());
+    }
+
+    match status {
+        http::StatusCode::UNAUTHORIZED => Err(WeatherError::InvalidApiKey),
+        http::StatusCode::TOO_MANY_REQUESTS => {
+            let retry_after = response.headers().get(http::header::RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.trim().parse::<u64>().ok());
+            Err(WeatherError::RateLimited(retry_after))
+        },
+        _ => Err(WeatherError::HttpStatus(status.as_u16())),
+    }
+}
+
+/// Deserializes JSON, reporting where in the document it did not match
+pub fn decode<T: DeserializeOwned>(text: &str) -> Result<T, WeatherError> {
+    let deserializer = &mut serde_json::Deserializer::from_str(text);
+    serde_path_to_error::deserialize(deserializer)
+        .map_err(|err| WeatherError::Decode {
+            path: err.path().to_string(),
+            message: err.inner().to_string(),
+        })
+}
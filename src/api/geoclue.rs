@@ -0,0 +1,355 @@
+use std::time::Duration;
+use gtk::gio;
+use gtk::glib;
+use gtk::glib::ToVariant;
+use gio::{
+    BusType,
+    DBusCallFlags,
+    DBusConnection,
+};
+use super::error::WeatherError;
+
+const GEOCLUE_NAME: &str = "org.freedesktop.GeoClue2";
+const MANAGER_PATH: &str = "/org/freedesktop/GeoClue2/Manager";
+const MANAGER_INTERFACE: &str = "org.freedesktop.GeoClue2.Manager";
+const CLIENT_INTERFACE: &str = "org.freedesktop.GeoClue2.Client";
+const LOCATION_INTERFACE: &str = "org.freedesktop.GeoClue2.Location";
+const PROPERTIES_INTERFACE: &str = "org.freedesktop.DBus.Properties";
+
+/// Name of the installed desktop file (extra/Weather Mobile.desktop), GeoClue checks it before allowing access
+const DESKTOP_ID: &str = "Weather Mobile";
+/// GCLUE_ACCURACY_LEVEL_CITY, close enough for a forecast without needing GPS
+const ACCURACY_LEVEL_CITY: u32 = 4;
+
+/// Set to "session" to ask a GeoClue service on the session bus instead
+const BUS_VARIABLE: &str = "WEATHER_GEOCLUE_BUS";
+
+const CALL_TIMEOUT_MS: i32 = 10_000;
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// How long to wait for the first fix before giving up
+const MAX_POLLS: u32 = 60;
+
+/// The system bus GeoClue runs on, unless overridden
+fn bus_type() -> BusType {
+    match std::env::var(BUS_VARIABLE).as_deref() {
+        Ok("session") => BusType::Session,
+        _ => BusType::System,
+    }
+}
+
+fn location_error(err: glib::Error) -> WeatherError {
+    WeatherError::Location(err.to_string())
+}
+
+async fn call(
+    connection: &DBusConnection,
+    path: &str,
+    interface: &str,
+    method: &str,
+    parameters: Option<&glib::Variant>,
+) -> Result<glib::Variant, WeatherError> {
+    connection.call_future(
+        Some(GEOCLUE_NAME),
+        path,
+        interface,
+        method,
+        parameters,
+        None,
+        DBusCallFlags::NONE,
+        CALL_TIMEOUT_MS)
+        .await
+        .map_err(location_error)
+}
+
+async fn get_property(connection: &DBusConnection, path: &str, interface: &str, property: &str) -> Result<glib::Variant, WeatherError> {
+    let reply = call(connection, path, PROPERTIES_INTERFACE, "Get",
+        Some(&(interface, property).to_variant())).await?;
+
+    reply.child_value(0).as_variant()
+        .ok_or_else(|| WeatherError::Location(format!("{} is not a property value", property)))
+}
+
+async fn set_property(connection: &DBusConnection, path: &str, interface: &str, property: &str, value: glib::Variant) -> Result<(), WeatherError> {
+    call(connection, path, PROPERTIES_INTERFACE, "Set",
+        Some(&(interface, property, value).to_variant())).await?;
+
+    Ok(())
+}
+
+fn object_path(value: &glib::Variant) -> Option<String> {
+    value.str()
+        .filter(|path| *path != "/")
+        .map(String::from)
+}
+
+async fn get_coordinate(connection: &DBusConnection, path: &str, property: &str) -> Result<f64, WeatherError> {
+    get_property(connection, path, LOCATION_INTERFACE, property).await?
+        .get::<f64>()
+        .ok_or_else(|| WeatherError::Location(format!("{} is not a number", property)))
+}
+
+/// Waits for the client's first location fix, returning its object path
+async fn wait_for_location(connection: &DBusConnection, client: &str) -> Result<String, WeatherError> {
+    for _ in 0..MAX_POLLS {
+        let location = get_property(connection, client, CLIENT_INTERFACE, "Location").await?;
+        if let Some(location) = object_path(&location) {
+            return Ok(location);
+        }
+        glib::timeout_future(POLL_INTERVAL).await;
+    }
+
+    Err(WeatherError::Location(String::from("no position was found in time")))
+}
+
+/// Asks GeoClue for the device's position, returned as latitude and longitude
+pub async fn get_device_location() -> Result<(f64, f64), WeatherError> {
+    let connection = gio::bus_get_future(bus_type()).await
+        .map_err(location_error)?;
+
+    device_location(&connection).await
+}
+
+async fn device_location(connection: &DBusConnection) -> Result<(f64, f64), WeatherError> {
+    let reply = call(connection, MANAGER_PATH, MANAGER_INTERFACE, "GetClient", None).await?;
+    let client = object_path(&reply.child_value(0))
+        .ok_or_else(|| WeatherError::Location(String::from("GeoClue did not create a client")))?;
+
+    set_property(connection, &client, CLIENT_INTERFACE, "DesktopId", DESKTOP_ID.to_variant()).await?;
+    set_property(connection, &client, CLIENT_INTERFACE, "RequestedAccuracyLevel", ACCURACY_LEVEL_CITY.to_variant()).await?;
+    call(connection, &client, CLIENT_INTERFACE, "Start", None).await?;
+
+    let position = match wait_for_location(connection, &client).await {
+        Ok(location) => {
+            let lat = get_coordinate(connection, &location, "Latitude").await;
+            let lon = get_coordinate(connection, &location, "Longitude").await;
+            lat.and_then(|lat| lon.map(|lon| (lat, lon)))
+        },
+        Err(err) => Err(err),
+    };
+
+    // Stop the client either way so GeoClue can turn off location services,
+    // a position already found is still worth showing if that fails
+    if let Err(err) = call(connection, &client, CLIENT_INTERFACE, "Stop", None).await {
+        println!("Unable to stop the GeoClue client: {}", err);
+    }
+
+    position
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{
+        BufRead,
+        BufReader,
+    };
+    use std::process::{
+        Child,
+        Command,
+        Stdio,
+    };
+    use std::sync::{
+        Arc,
+        Mutex,
+    };
+    use std::sync::atomic::{
+        AtomicBool,
+        Ordering,
+    };
+    use gio::{
+        DBusConnectionFlags,
+        DBusNodeInfo,
+    };
+
+    const CLIENT_PATH: &str = "/org/freedesktop/GeoClue2/Client/1";
+    const LOCATION_PATH: &str = "/org/freedesktop/GeoClue2/Client/1/Location/1";
+
+    /// The parts of GeoClue used. Paths are given as strings as the glib bindings
+    /// can not build object paths, the client reads both the same way
+    const MOCK_XML: &str = r#"<node>
+  <interface name="org.freedesktop.GeoClue2.Manager">
+    <method name="GetClient">
+      <arg name="client" type="s" direction="out"/>
+    </method>
+  </interface>
+  <interface name="org.freedesktop.GeoClue2.Client">
+    <method name="Start"/>
+    <method name="Stop"/>
+    <property name="Location" type="s" access="read"/>
+    <property name="DesktopId" type="s" access="readwrite"/>
+    <property name="RequestedAccuracyLevel" type="u" access="readwrite"/>
+  </interface>
+  <interface name="org.freedesktop.GeoClue2.Location">
+    <property name="Latitude" type="d" access="read"/>
+    <property name="Longitude" type="d" access="read"/>
+  </interface>
+</node>"#;
+
+    /// A session bus of its own for each test, stopped when dropped
+    struct PrivateBus {
+        daemon: Child,
+        address: String,
+    }
+
+    impl PrivateBus {
+        /// None when dbus-daemon is not installed
+        fn start() -> Option<PrivateBus> {
+            let mut daemon = Command::new("dbus-daemon")
+                .args(&["--session", "--nofork", "--print-address"])
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn()
+                .ok()?;
+            let mut address = String::new();
+            if let Some(stdout) = daemon.stdout.take() {
+                let _ = BufReader::new(stdout).read_line(&mut address);
+            }
+            let bus = PrivateBus {
+                daemon,
+                address: address.trim().to_string(),
+            };
+
+            if bus.address.is_empty() {
+                None
+            } else {
+                Some(bus)
+            }
+        }
+
+        fn connect(&self) -> DBusConnection {
+            DBusConnection::for_address_sync(
+                &self.address,
+                DBusConnectionFlags::AUTHENTICATION_CLIENT | DBusConnectionFlags::MESSAGE_BUS_CONNECTION,
+                None,
+                gio::NONE_CANCELLABLE)
+                .expect("private bus should accept connections")
+        }
+    }
+
+    impl Drop for PrivateBus {
+        fn drop(&mut self) {
+            let _ = self.daemon.kill();
+            let _ = self.daemon.wait();
+        }
+    }
+
+    /// What the client asked of the mock
+    #[derive(Default)]
+    struct MockState {
+        started: AtomicBool,
+        stopped: AtomicBool,
+        desktop_id: Mutex<Option<String>>,
+    }
+
+    /// Serves a GeoClue reporting the position once started, failing Stop when asked to
+    async fn serve_mock(connection: &DBusConnection, lat: f64, lon: f64, fail_stop: bool) -> Arc<MockState> {
+        let node = DBusNodeInfo::for_xml(MOCK_XML).unwrap();
+        let interface = |name: &str| node.lookup_interface(name).unwrap();
+        let state = Arc::new(MockState::default());
+
+        connection.register_object(MANAGER_PATH, &interface(MANAGER_INTERFACE),
+            |_, _, _, _, _, _, invocation| invocation.return_value(Some(&(CLIENT_PATH,).to_variant())),
+            // The manager has no properties of its own
+            |_, _, _, _, _| false.to_variant(),
+            |_, _, _, _, _, _| false)
+            .unwrap();
+
+        let method_state = state.clone();
+        let get_state = state.clone();
+        let set_state = state.clone();
+        connection.register_object(CLIENT_PATH, &interface(CLIENT_INTERFACE),
+            move |_, _, _, _, method, _, invocation| {
+                match method {
+                    "Start" => method_state.started.store(true, Ordering::SeqCst),
+                    _ => method_state.stopped.store(true, Ordering::SeqCst),
+                }
+                if method == "Stop" && fail_stop {
+                    invocation.return_dbus_error("org.freedesktop.DBus.Error.Failed", "Unable to stop");
+                } else {
+                    invocation.return_value(None);
+                }
+            },
+            move |_, _, _, _, property| match property {
+                // GeoClue reports "/" until it has a fix
+                "Location" if get_state.started.load(Ordering::SeqCst) => LOCATION_PATH.to_variant(),
+                "Location" => "/".to_variant(),
+                "RequestedAccuracyLevel" => ACCURACY_LEVEL_CITY.to_variant(),
+                _ => get_state.desktop_id.lock().unwrap().clone().unwrap_or_default().to_variant(),
+            },
+            move |_, _, _, _, property, value| {
+                if property == "DesktopId" {
+                    *set_state.desktop_id.lock().unwrap() = value.get::<String>();
+                }
+                true
+            })
+            .unwrap();
+
+        connection.register_object(LOCATION_PATH, &interface(LOCATION_INTERFACE),
+            |_, _, _, _, _, _, invocation| invocation.return_value(None),
+            move |_, _, _, _, property| match property {
+                "Latitude" => lat.to_variant(),
+                _ => lon.to_variant(),
+            },
+            |_, _, _, _, _, _| false)
+            .unwrap();
+
+        // DBUS_NAME_FLAG_DO_NOT_QUEUE, the bus is private so the name is free
+        connection.call_future(
+            Some("org.freedesktop.DBus"),
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus",
+            "RequestName",
+            Some(&(GEOCLUE_NAME, 4u32).to_variant()),
+            None,
+            DBusCallFlags::NONE,
+            CALL_TIMEOUT_MS)
+            .await
+            .unwrap();
+
+        state
+    }
+
+    fn locate_with_mock(fail_stop: bool) -> Option<(Result<(f64, f64), WeatherError>, Arc<MockState>)> {
+        let bus = match PrivateBus::start() {
+            Some(bus) => bus,
+            None => {
+                println!("Skipping GeoClue test, dbus-daemon is not available");
+                return None;
+            },
+        };
+
+        let context = glib::MainContext::new();
+        let result = context.with_thread_default(|| context.block_on(async {
+            let service = bus.connect();
+            let state = serve_mock(&service, 52.52, 13.41, fail_stop).await;
+            let position = device_location(&bus.connect()).await;
+            (position, state)
+        }));
+
+        Some(result)
+    }
+
+    #[test]
+    fn finds_position_from_geoclue() {
+        let (position, state) = match locate_with_mock(false) {
+            Some(result) => result,
+            None => return,
+        };
+
+        assert_eq!(position.unwrap(), (52.52, 13.41));
+        assert!(state.started.load(Ordering::SeqCst));
+        assert!(state.stopped.load(Ordering::SeqCst));
+        assert_eq!(state.desktop_id.lock().unwrap().as_deref(), Some(DESKTOP_ID));
+    }
+
+    #[test]
+    fn keeps_position_when_stop_fails() {
+        let (position, state) = match locate_with_mock(true) {
+            Some(result) => result,
+            None => return,
+        };
+
+        assert_eq!(position.unwrap(), (52.52, 13.41));
+        assert!(state.stopped.load(Ordering::SeqCst));
+    }
+}
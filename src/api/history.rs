@@ -0,0 +1,168 @@
+use serde::Deserialize;
+use time::{
+    Date,
+    Duration,
+    OffsetDateTime,
+};
+use super::units::Units;
+use super::weather::{
+    HourlyWeather,
+    Weather,
+};
+use super::provider::open_meteo::{
+    snowfall_water,
+    weather_code_status,
+};
+use super::error::WeatherError;
+use super::client::HttpClient;
+
+const OPEN_METEO_ARCHIVE_API_URL: &str = "https://archive-api.open-meteo.com/v1/archive";
+const OPEN_METEO_FORECAST_API_URL: &str = "https://api.open-meteo.com/v1/forecast";
+
+const HISTORY_VARIABLES: &str = "temperature_2m,apparent_temperature,relative_humidity_2m,dew_point_2m,\
+pressure_msl,cloud_cover,wind_speed_10m,wind_direction_10m,wind_gusts_10m,\
+rain,snowfall,weather_code,is_day";
+
+/// The archive lags a few days behind, more recent days are read from the forecast API
+const ARCHIVE_DELAY_DAYS: i64 = 5;
+
+#[derive(Deserialize)]
+struct HistoryHourly {
+    time: Vec<i64>,
+    #[serde(default)]
+    temperature_2m: Vec<Option<f64>>,
+    #[serde(default)]
+    apparent_temperature: Vec<Option<f64>>,
+    #[serde(default)]
+    relative_humidity_2m: Vec<Option<f64>>,
+    #[serde(default)]
+    dew_point_2m: Vec<Option<f64>>,
+    #[serde(default)]
+    pressure_msl: Vec<Option<f64>>,
+    #[serde(default)]
+    cloud_cover: Vec<Option<f64>>,
+    #[serde(default)]
+    wind_speed_10m: Vec<Option<f64>>,
+    #[serde(default)]
+    wind_direction_10m: Vec<Option<f64>>,
+    #[serde(default)]
+    wind_gusts_10m: Vec<Option<f64>>,
+    #[serde(default)]
+    rain: Vec<Option<f64>>,
+    #[serde(default)]
+    snowfall: Vec<Option<f64>>,
+    #[serde(default)]
+    weather_code: Vec<Option<u32>>,
+    #[serde(default)]
+    is_day: Vec<Option<u8>>,
+}
+
+#[derive(Deserialize)]
+struct HistoryResponse {
+    hourly: HistoryHourly,
+}
+
+/// Observed conditions through one past day at a location
+pub struct HistoricalDay {
+    pub date: Date,
+    pub hourly: Vec<HourlyWeather>,
+}
+
+fn at<T: Copy + Default>(values: &[Option<T>], index: usize) -> T {
+    values.get(index).copied().flatten().unwrap_or_default()
+}
+
+fn at_opt<T: Copy>(values: &[Option<T>], index: usize) -> Option<T> {
+    values.get(index).copied().flatten()
+}
+
+impl HistoryHourly {
+    fn weather(&self, index: usize) -> HourlyWeather {
+        Weather {
+            dt: self.time[index],
+            sunrise: None,
+            sunset: None,
+            temp: at(&self.temperature_2m, index),
+            feels_like: at(&self.apparent_temperature, index),
+            pressure: at(&self.pressure_msl, index) as u32,
+            humidity: at(&self.relative_humidity_2m, index) as u32,
+            dew_point: at(&self.dew_point_2m, index),
+            uvi: 0.0,
+            clouds: at(&self.cloud_cover, index),
+            visibility: None,
+            wind_speed: at(&self.wind_speed_10m, index),
+            wind_deg: at(&self.wind_direction_10m, index) as u32,
+            wind_gust: at_opt(&self.wind_gusts_10m, index),
+            status: vec![weather_code_status(
+                at(&self.weather_code, index),
+                at(&self.is_day, index) == 1)],
+            pop: 0.0,
+            rain: at_opt(&self.rain, index).filter(|v| *v > 0.0),
+            snow: snowfall_water(at_opt(&self.snowfall, index)).filter(|v| *v > 0.0),
+        }
+    }
+}
+
+impl HistoricalDay {
+    pub fn temp_max(&self) -> Option<f64> {
+        self.hourly.iter().map(|h| h.temp).reduce(f64::max)
+    }
+
+    pub fn temp_min(&self) -> Option<f64> {
+        self.hourly.iter().map(|h| h.temp).reduce(f64::min)
+    }
+
+    pub fn precipitation(&self) -> f64 {
+        self.hourly.iter()
+            .map(|h| h.rain.unwrap_or(0.0) + h.snow.unwrap_or(0.0))
+            .sum()
+    }
+
+    /// The hour closest to a timestamp, if the day covers it
+    pub fn at(&self, dt: i64) -> Option<&HourlyWeather> {
+        self.hourly.iter()
+            .filter(|h| (h.dt - dt).abs() <= 30 * 60)
+            .min_by_key(|h| (h.dt - dt).abs())
+    }
+}
+
+fn iso_date(date: Date) -> String {
+    format!("{}-{:02}-{:02}", date.year(), date.month() as u8, date.day())
+}
+
+/// Hourly conditions for a past day (in the location's time zone) from the Open-Meteo archive
+pub async fn get_history(units: Units, lat: f64, lon: f64, date: Date) -> Result<HistoricalDay, WeatherError> {
+    let (temperature_unit, wind_speed_unit) = match units {
+        Units::Metric => ("celsius", "ms"),
+        Units::Imperial => ("fahrenheit", "mph"),
+    };
+    let archived_until = OffsetDateTime::now_utc().date() - Duration::days(ARCHIVE_DELAY_DAYS);
+    let base_url = if date < archived_until {
+        OPEN_METEO_ARCHIVE_API_URL
+    } else {
+        OPEN_METEO_FORECAST_API_URL
+    };
+    let url = format!("{}?latitude={}&longitude={}\
+&start_date={}&end_date={}&hourly={}\
+&temperature_unit={}&wind_speed_unit={}&precipitation_unit=mm\
+&timeformat=unixtime&timezone=auto",
+        base_url,
+        lat, lon,
+        iso_date(date),
+        iso_date(date),
+        HISTORY_VARIABLES,
+        temperature_unit,
+        wind_speed_unit);
+
+    let response: HistoryResponse = HttpClient::shared().get_json(&url, &[]).await?;
+    let hourly = &response.hourly;
+    let hourly = (0..hourly.time.len())
+        .filter(|i| hourly.temperature_2m.get(*i).copied().flatten().is_some())
+        .map(|i| hourly.weather(i))
+        .collect();
+
+    Ok(HistoricalDay {
+        date,
+        hourly,
+    })
+}
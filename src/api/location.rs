@@ -1,79 +1,277 @@
-use serde::Deserialize;
-use isahc::prelude::*;
+pub mod geocode_api;
+pub mod nominatim;
+pub mod open_meteo;
 
-const GEOCODE_API_KEY: &str = "dcaab990-5a96-11eb-a3dc-95ccdabef212";
-const GEOCODE_API_URL: &str = "https://app.geocodeapi.io/api";
-const GEOCODE_API_VERSION: &str = "v1";
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use super::error::WeatherError;
+use geocode_api::GeocodeApiGeocoder;
+use nominatim::NominatimGeocoder;
+use open_meteo::OpenMeteoGeocoder;
 
-#[derive(Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct LocationPoint {
     pub lat: f64,
     pub lon: f64,
     pub location: String,
 }
 
-pub async fn search_locations(search: &str) -> Option<Vec<LocationPoint>> {
-    if let Some(data) = get_location_data(search).await {
-        let locations = parse_to_location_points(data);
-        Some(locations)
-    } else {
-        None
+impl LocationPoint {
+    /// Whether both points are the same place, within the precision forecasts are cached at
+    pub fn is_same_place(&self, other: &LocationPoint) -> bool {
+        (self.lat - other.lat).abs() < 0.0001 && (self.lon - other.lon).abs() < 0.0001
     }
 }
 
-fn base_url() -> String {
-    return format!("{}/{}", 
-        GEOCODE_API_URL, 
-        GEOCODE_API_VERSION);
+pub type LocationFuture<'a> = Pin<Box<dyn Future<Output = Result<Vec<LocationPoint>, WeatherError>> + 'a>>;
+pub type LabelFuture<'a> = Pin<Box<dyn Future<Output = Result<Option<String>, WeatherError>> + 'a>>;
+
+/// A place name search service, mapping its own results onto LocationPoints
+pub trait Geocoder {
+    fn name(&self) -> &str;
+    fn search<'a>(&'a self, query: &'a str) -> LocationFuture<'a>;
+    /// Name of the place at the coordinates, None when there is nothing there (e.g. at sea)
+    fn reverse<'a>(&'a self, lat: f64, lon: f64) -> LabelFuture<'a>;
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum GeocoderKind {
+    GeocodeApi,
+    Nominatim,
+    OpenMeteo,
+}
+
+/// Open-Meteo needs no API key and has no per-second limit, so searching works for everyone
+impl Default for GeocoderKind {
+    fn default() -> Self {
+        GeocoderKind::OpenMeteo
+    }
+}
+
+impl std::fmt::Display for GeocoderKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            GeocoderKind::GeocodeApi => write!(f, "geocodeapi.io"),
+            GeocoderKind::Nominatim => write!(f, "OpenStreetMap Nominatim"),
+            GeocoderKind::OpenMeteo => write!(f, "Open-Meteo"),
+        }
+    }
+}
+
+impl GeocoderKind {
+    pub const ALL: &'static [GeocoderKind] = &[
+        GeocoderKind::GeocodeApi,
+        GeocoderKind::Nominatim,
+        GeocoderKind::OpenMeteo,
+    ];
+
+    pub fn id(&self) -> &str {
+        match *self {
+            GeocoderKind::GeocodeApi => "geocodeapi",
+            GeocoderKind::Nominatim => "nominatim",
+            GeocoderKind::OpenMeteo => "open-meteo",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<GeocoderKind> {
+        Self::ALL.iter()
+            .find(|kind| kind.id() == id)
+            .copied()
+    }
+
+    pub fn create(&self) -> Arc<dyn Geocoder> {
+        match *self {
+            GeocoderKind::GeocodeApi => Arc::new(GeocodeApiGeocoder::new()),
+            GeocoderKind::Nominatim => Arc::new(NominatimGeocoder::new()),
+            GeocoderKind::OpenMeteo => Arc::new(OpenMeteoGeocoder::new()),
+        }
+    }
 }
 
-fn parse_feature_to_location_point(feature: serde_json::Value) -> LocationPoint {
-    let properties = &feature["properties"];
-    let coords = &feature["geometry"]["coordinates"].as_array()
-        .expect("Unable to find feature coordinates");
-    let lon = coords[0].as_f64().unwrap();
-    let lat = coords[1].as_f64().unwrap();
-    let location = String::from(properties["label"].as_str().unwrap());
+pub async fn search_locations(geocoder: &dyn Geocoder, search: &str) -> Result<Vec<LocationPoint>, WeatherError> {
+    geocoder.search(search.trim()).await
+}
+
+/// Resolves coordinates to a LocationPoint named after the place there,
+/// or after the coordinates themselves when there is no place to name
+pub async fn reverse_geocode(geocoder: &dyn Geocoder, lat: f64, lon: f64) -> Result<LocationPoint, WeatherError> {
+    let location = geocoder.reverse(lat, lon).await?
+        .unwrap_or_else(|| coordinates_label(lat, lon));
 
-    LocationPoint {
+    Ok(LocationPoint {
         lat,
         lon,
         location,
+    })
+}
+
+/// e.g. 51.5074° N, 0.1278° W
+pub fn coordinates_label(lat: f64, lon: f64) -> String {
+    format!("{:.4}° {}, {:.4}° {}",
+        lat.abs(),
+        if lat < 0.0 { "S" } else { "N" },
+        lon.abs(),
+        if lon < 0.0 { "W" } else { "E" })
+}
+
+/// Applies a hemisphere letter to a value, which is then written without a sign
+fn signed(value: f64, hemisphere: Option<char>, positive: char, negative: char) -> Option<f64> {
+    match hemisphere {
+        None => Some(value),
+        Some(_) if value.is_sign_negative() => None,
+        Some(h) if h == positive => Some(value),
+        Some(h) if h == negative => Some(-value),
+        Some(_) => None,
     }
 }
 
-fn parse_to_location_points(value: serde_json::Value) -> Vec<LocationPoint> {
-    let features = value["features"].as_array()
-        .expect("Location data did not contain features");
-    
-    let locations = features.iter()
-            .map(|f| parse_feature_to_location_point(f.clone()))
-            .collect::<Vec<_>>();
+/// Reads a typed latitude and longitude pair, e.g. "51.5074, -0.1278" or "51.5074° N, 0.1278° W"
+pub fn parse_coordinates(text: &str) -> Option<(f64, f64)> {
+    // Each value with the hemisphere it was given in, if any
+    let mut values: Vec<(f64, Option<char>)> = Vec::new();
+    for part in text.split(|c: char| c == ',' || c.is_whitespace()).filter(|part| !part.is_empty()) {
+        let hemisphere = part.chars().last()
+            .map(|c| c.to_ascii_uppercase())
+            .filter(|c| "NSEW".contains(*c));
+        let number = if hemisphere.is_some() {
+            &part[..part.len() - 1]
+        } else {
+            part
+        };
+        let number = number.trim_end_matches('°');
+
+        if !number.is_empty() {
+            values.push((number.parse::<f64>().ok()?, hemisphere));
+        } else if hemisphere.is_some() {
+            // Written apart from its value, as in 51.5074° N
+            let last = values.last_mut().filter(|last| last.1.is_none())?;
+            last.1 = hemisphere;
+        }
+    }
 
-    locations
+    let (lat, lon) = match values.as_slice() {
+        [(lat, lat_hemisphere), (lon, lon_hemisphere)] => (
+            signed(*lat, *lat_hemisphere, 'N', 'S')?,
+            signed(*lon, *lon_hemisphere, 'E', 'W')?,
+        ),
+        _ => return None,
+    };
+    if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+        return None;
+    }
+
+    Some((lat, lon))
 }
 
-async fn get_location_data(search: &str) -> Option<serde_json::Value> {
-    let url = format!("{}/search?apikey={}&text={}",
-        base_url(),
-        GEOCODE_API_KEY,
-        search);
+/// Percent encodes a search for use in a query string
+pub fn encode_query(query: &str) -> String {
+    query.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (byte as char).to_string(),
+            b' ' => String::from("+"),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
 
-    let response = isahc::get_async(url).await;
+fn missing(path: String, message: &str) -> WeatherError {
+    WeatherError::Decode {
+        path,
+        message: String::from(message),
+    }
+}
 
-    if let Some(mut body) = response.ok() {
-        let text = body.text().await;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        if let Ok(text) = text {
-            let data: serde_json::Value = serde_json::from_str(&text)
-                    .expect("Unable to deserialize location data into json");
-            Some(data)
-        } else {
-            panic!("Unable to get text data from 200 response");
+    #[test]
+    fn encodes_queries() {
+        assert_eq!(encode_query("New York"), "New+York");
+        assert_eq!(encode_query("São Paulo"), "S%C3%A3o+Paulo");
+        assert_eq!(encode_query("a&b=c/d?"), "a%26b%3Dc%2Fd%3F");
+        assert_eq!(encode_query("A-z_0.9~"), "A-z_0.9~");
+        assert_eq!(encode_query(""), "");
+    }
+
+    #[test]
+    fn parses_signed_coordinates() {
+        let cases = [
+            ("51.5074, -0.1278", (51.5074, -0.1278)),
+            ("51.5074 -0.1278", (51.5074, -0.1278)),
+            ("  -33.8688,151.2093 ", (-33.8688, 151.2093)),
+            ("+40.7128, -74.0060", (40.7128, -74.006)),
+            ("90, 180", (90.0, 180.0)),
+            ("-90, -180", (-90.0, -180.0)),
+        ];
+        for (text, expected) in cases.iter() {
+            assert_eq!(parse_coordinates(text), Some(*expected), "{}", text);
         }
-    } else {
-        println!("get_location_data: did not get ok response!");
-        None
     }
-}
 
+    #[test]
+    fn parses_hemispheres() {
+        let cases = [
+            ("51.5074° N, 0.1278° W", (51.5074, -0.1278)),
+            ("33.8688S 151.2093E", (-33.8688, 151.2093)),
+            ("33.8688°S, 151.2093°E", (-33.8688, 151.2093)),
+            ("51.5n 0.1w", (51.5, -0.1)),
+            // A hemisphere on one value only
+            ("22.9068 S, -43.1729", (-22.9068, -43.1729)),
+        ];
+        for (text, expected) in cases.iter() {
+            assert_eq!(parse_coordinates(text), Some(*expected), "{}", text);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_coordinates() {
+        let cases = [
+            "",
+            "51.5074",
+            "1, 2, 3",
+            "91, 0",
+            "-90.0001, 0",
+            "0, 180.5",
+            "nan, 0",
+            "inf, 0",
+            "Essen",
+            "New York",
+            // Signed and given a hemisphere, or hemispheres the wrong way around
+            "-51.5 N, 0.1 W",
+            "51.5 E, 0.1 N",
+            "51.5 N N, 0.1",
+            // Decimal commas can not be told apart from the separator
+            "51,5074, -0,1278",
+            "51,5 -0,1",
+        ];
+        for text in cases.iter() {
+            assert_eq!(parse_coordinates(text), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn labels_coordinates() {
+        assert_eq!(coordinates_label(51.5074, -0.1278), "51.5074° N, 0.1278° W");
+        assert_eq!(coordinates_label(-33.8688, 151.2093), "33.8688° S, 151.2093° E");
+        assert_eq!(coordinates_label(0.0, 0.0), "0.0000° N, 0.0000° E");
+
+        // Labels can be typed back in
+        for (lat, lon) in [(51.5074, -0.1278), (-33.8688, 151.2093), (-22.9068, -43.1729)].iter() {
+            assert_eq!(parse_coordinates(&coordinates_label(*lat, *lon)), Some((*lat, *lon)));
+        }
+    }
+
+    #[test]
+    fn defaults_to_keyless_geocoder() {
+        assert!(GeocoderKind::default() == GeocoderKind::OpenMeteo);
+        for kind in GeocoderKind::ALL.iter() {
+            assert!(GeocoderKind::from_id(kind.id()) == Some(*kind));
+        }
+    }
+}
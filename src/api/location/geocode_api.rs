@@ -0,0 +1,139 @@
+use crate::api::error::WeatherError;
+use crate::api::client::HttpClient;
+use super::{
+    Geocoder,
+    LabelFuture,
+    LocationFuture,
+    LocationPoint,
+    encode_query,
+    missing,
+};
+
+const GEOCODE_API_KEY: &str = "dcaab990-5a96-11eb-a3dc-95ccdabef212";
+const GEOCODE_API_URL: &str = "https://app.geocodeapi.io/api";
+const GEOCODE_API_VERSION: &str = "v1";
+
+fn base_url() -> String {
+    format!("{}/{}", 
+        GEOCODE_API_URL, 
+        GEOCODE_API_VERSION)
+}
+
+fn parse_feature_to_location_point(index: usize, feature: &serde_json::Value) -> Result<LocationPoint, WeatherError> {
+    let properties = &feature["properties"];
+    let coords = feature["geometry"]["coordinates"].as_array()
+        .ok_or_else(|| missing(format!("features[{}].geometry.coordinates", index), "expected an array"))?;
+    let lon = coords.get(0).and_then(|c| c.as_f64())
+        .ok_or_else(|| missing(format!("features[{}].geometry.coordinates[0]", index), "expected a longitude"))?;
+    let lat = coords.get(1).and_then(|c| c.as_f64())
+        .ok_or_else(|| missing(format!("features[{}].geometry.coordinates[1]", index), "expected a latitude"))?;
+    let location = properties["label"].as_str()
+        .ok_or_else(|| missing(format!("features[{}].properties.label", index), "expected a string"))?;
+
+    Ok(LocationPoint {
+        lat,
+        lon,
+        location: String::from(location),
+    })
+}
+
+fn parse_to_location_points(value: serde_json::Value) -> Result<Vec<LocationPoint>, WeatherError> {
+    let features = value["features"].as_array()
+        .ok_or_else(|| missing(String::from("features"), "expected an array"))?;
+    
+    features.iter()
+        .enumerate()
+        .map(|(i, f)| parse_feature_to_location_point(i, f))
+        .collect()
+}
+
+async fn get_location_data(search: &str) -> Result<serde_json::Value, WeatherError> {
+    let url = format!("{}/search?apikey={}&text={}",
+        base_url(),
+        GEOCODE_API_KEY,
+        encode_query(search));
+
+    HttpClient::shared().get_json(&url, &[]).await
+}
+
+async fn get_reverse_data(lat: f64, lon: f64) -> Result<serde_json::Value, WeatherError> {
+    let url = format!("{}/reverse?apikey={}&point.lat={}&point.lon={}&size=1",
+        base_url(),
+        GEOCODE_API_KEY,
+        lat, lon);
+
+    HttpClient::shared().get_json(&url, &[]).await
+}
+
+/// geocodeapi.io, using the application's shared API key
+#[derive(Default)]
+pub struct GeocodeApiGeocoder;
+
+impl GeocodeApiGeocoder {
+    pub fn new() -> Self {
+        GeocodeApiGeocoder
+    }
+
+    async fn search_locations(&self, search: &str) -> Result<Vec<LocationPoint>, WeatherError> {
+        let data = get_location_data(search).await?;
+        parse_to_location_points(data)
+    }
+
+    async fn reverse_label(&self, lat: f64, lon: f64) -> Result<Option<String>, WeatherError> {
+        let data = get_reverse_data(lat, lon).await?;
+        let nearest = parse_to_location_points(data)?.into_iter().next();
+
+        Ok(nearest.map(|point| point.location))
+    }
+}
+
+impl Geocoder for GeocodeApiGeocoder {
+    fn name(&self) -> &str {
+        "geocodeapi.io"
+    }
+
+    fn search<'a>(&'a self, query: &'a str) -> LocationFuture<'a> {
+        Box::pin(self.search_locations(query))
+    }
+
+    fn reverse<'a>(&'a self, lat: f64, lon: f64) -> LabelFuture<'a> {
+        Box::pin(self.reverse_label(lat, lon))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_features() {
+        let value = serde_json::json!({
+            "features": [{
+                "geometry": {"type": "Point", "coordinates": [-74.006, 40.7143]},
+                "properties": {"label": "New York, NY, USA"}
+            }]
+        });
+        let points = parse_to_location_points(value).unwrap();
+
+        assert_eq!(points.len(), 1);
+        assert_eq!(points[0].lat, 40.7143);
+        assert_eq!(points[0].lon, -74.006);
+        assert_eq!(points[0].location, "New York, NY, USA");
+    }
+
+    #[test]
+    fn rejects_features_without_label() {
+        let value = serde_json::json!({
+            "features": [{
+                "geometry": {"coordinates": [-74.006, 40.7143]},
+                "properties": {}
+            }]
+        });
+
+        match parse_to_location_points(value) {
+            Err(WeatherError::Decode { path, .. }) => assert_eq!(path, "features[0].properties.label"),
+            _ => panic!("expected a decode error"),
+        }
+        assert!(parse_to_location_points(serde_json::json!({})).is_err());
+    }
+}
@@ -0,0 +1,170 @@
+use std::sync::Mutex;
+use std::time::{
+    Duration,
+    Instant,
+};
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use crate::api::error::WeatherError;
+use crate::api::client::HttpClient;
+use crate::api::provider::user_agent;
+use super::{
+    Geocoder,
+    LabelFuture,
+    LocationFuture,
+    LocationPoint,
+    encode_query,
+    missing,
+};
+
+const NOMINATIM_API_URL: &str = "https://nominatim.openstreetmap.org";
+const SEARCH_LIMIT: usize = 10;
+
+/// Nominatim's usage policy allows at most one request a second
+const MIN_REQUEST_INTERVAL: Duration = Duration::from_secs(1);
+
+/// When the last request was (or is due to be) made, shared by every NominatimGeocoder
+static NEXT_REQUEST: Lazy<Mutex<Option<Instant>>> = Lazy::new(|| Mutex::new(None));
+
+#[derive(Deserialize)]
+struct NominatimPlace {
+    /// Coordinates are given as strings
+    lat: String,
+    lon: String,
+    display_name: String,
+}
+
+#[derive(Deserialize)]
+struct NominatimReverse {
+    /// Missing along with an error message when there is nothing at the coordinates
+    #[serde(default)]
+    display_name: Option<String>,
+}
+
+/// Waits until the next request is allowed, reserving its slot so overlapping searches queue up
+async fn wait_turn() {
+    let wait = {
+        let mut next = NEXT_REQUEST.lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let now = Instant::now();
+        let start = next.filter(|n| *n > now).unwrap_or(now);
+        *next = Some(start + MIN_REQUEST_INTERVAL);
+
+        start - now
+    };
+    if wait > Duration::from_millis(0) {
+        gtk::glib::timeout_future(wait).await;
+    }
+}
+
+fn to_location_point(index: usize, place: NominatimPlace) -> Result<LocationPoint, WeatherError> {
+    let lat = place.lat.parse::<f64>()
+        .map_err(|_| missing(format!("[{}].lat", index), "expected a latitude"))?;
+    let lon = place.lon.parse::<f64>()
+        .map_err(|_| missing(format!("[{}].lon", index), "expected a longitude"))?;
+
+    Ok(LocationPoint {
+        lat,
+        lon,
+        location: place.display_name,
+    })
+}
+
+/// OpenStreetMap's Nominatim, identified by the application's User-Agent as its policy requires
+#[derive(Default)]
+pub struct NominatimGeocoder;
+
+impl NominatimGeocoder {
+    pub fn new() -> Self {
+        NominatimGeocoder
+    }
+
+    async fn search_locations(&self, search: &str) -> Result<Vec<LocationPoint>, WeatherError> {
+        let url = format!("{}/search?q={}&format=jsonv2&limit={}",
+            NOMINATIM_API_URL,
+            encode_query(search),
+            SEARCH_LIMIT);
+        let user_agent = user_agent();
+
+        wait_turn().await;
+        let places: Vec<NominatimPlace> = HttpClient::shared()
+            .get_json(&url, &[("User-Agent", user_agent.as_str())])
+            .await?;
+
+        places.into_iter()
+            .enumerate()
+            .map(|(i, place)| to_location_point(i, place))
+            .collect()
+    }
+
+    pub(super) async fn reverse_label(&self, lat: f64, lon: f64) -> Result<Option<String>, WeatherError> {
+        let url = format!("{}/reverse?lat={}&lon={}&format=jsonv2&zoom=14",
+            NOMINATIM_API_URL,
+            lat, lon);
+        let user_agent = user_agent();
+
+        wait_turn().await;
+        let place: NominatimReverse = HttpClient::shared()
+            .get_json(&url, &[("User-Agent", user_agent.as_str())])
+            .await?;
+
+        Ok(place.display_name)
+    }
+}
+
+impl Geocoder for NominatimGeocoder {
+    fn name(&self) -> &str {
+        "Nominatim"
+    }
+
+    fn search<'a>(&'a self, query: &'a str) -> LocationFuture<'a> {
+        Box::pin(self.search_locations(query))
+    }
+
+    fn reverse<'a>(&'a self, lat: f64, lon: f64) -> LabelFuture<'a> {
+        Box::pin(self.reverse_label(lat, lon))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_places() {
+        let places: Vec<NominatimPlace> = serde_json::from_str(r#"[
+            {"place_id": 88066702, "lat": "51.5073219", "lon": "-0.1276474",
+                "display_name": "London, Greater London, England, United Kingdom"}
+        ]"#).unwrap();
+        let point = places.into_iter()
+            .enumerate()
+            .map(|(i, place)| to_location_point(i, place))
+            .next()
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(point.lat, 51.5073219);
+        assert_eq!(point.lon, -0.1276474);
+        assert_eq!(point.location, "London, Greater London, England, United Kingdom");
+    }
+
+    #[test]
+    fn rejects_unreadable_coordinates() {
+        let place = NominatimPlace {
+            lat: String::from("north"),
+            lon: String::from("0"),
+            display_name: String::from("Nowhere"),
+        };
+
+        match to_location_point(3, place) {
+            Err(WeatherError::Decode { path, .. }) => assert_eq!(path, "[3].lat"),
+            _ => panic!("expected a decode error"),
+        }
+    }
+
+    #[test]
+    fn reads_reverse_at_sea() {
+        let place: NominatimReverse = serde_json::from_str(r#"{"error": "Unable to geocode"}"#).unwrap();
+        assert_eq!(place.display_name, None);
+    }
+}
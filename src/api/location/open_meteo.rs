@@ -0,0 +1,128 @@
+use serde::Deserialize;
+use crate::api::error::WeatherError;
+use crate::api::client::HttpClient;
+use super::{
+    Geocoder,
+    LabelFuture,
+    LocationFuture,
+    LocationPoint,
+    encode_query,
+};
+use super::nominatim::NominatimGeocoder;
+
+const OPEN_METEO_GEOCODING_URL: &str = "https://geocoding-api.open-meteo.com";
+const OPEN_METEO_GEOCODING_VERSION: &str = "v1";
+const SEARCH_LIMIT: usize = 10;
+
+#[derive(Deserialize)]
+struct OpenMeteoPlace {
+    name: String,
+    latitude: f64,
+    longitude: f64,
+    #[serde(default)]
+    admin1: Option<String>,
+    #[serde(default)]
+    country: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct OpenMeteoSearch {
+    /// Left out entirely when nothing matches
+    #[serde(default)]
+    results: Vec<OpenMeteoPlace>,
+}
+
+fn base_url() -> String {
+    format!("{}/{}",
+        OPEN_METEO_GEOCODING_URL,
+        OPEN_METEO_GEOCODING_VERSION)
+}
+
+/// e.g. Paris, Île-de-France, France
+fn label(place: &OpenMeteoPlace) -> String {
+    let mut parts = vec![place.name.as_str()];
+    for part in [&place.admin1, &place.country].iter().copied().flatten() {
+        if !parts.contains(&part.as_str()) {
+            parts.push(part);
+        }
+    }
+
+    parts.join(", ")
+}
+
+/// Open-Meteo's geocoding API, built on GeoNames
+#[derive(Default)]
+pub struct OpenMeteoGeocoder;
+
+impl OpenMeteoGeocoder {
+    pub fn new() -> Self {
+        OpenMeteoGeocoder
+    }
+
+    async fn search_locations(&self, search: &str) -> Result<Vec<LocationPoint>, WeatherError> {
+        let url = format!("{}/search?name={}&count={}&format=json",
+            base_url(),
+            encode_query(search),
+            SEARCH_LIMIT);
+        let response: OpenMeteoSearch = HttpClient::shared().get_json(&url, &[]).await?;
+
+        Ok(response.results.iter()
+            .map(|place| LocationPoint {
+                lat: place.latitude,
+                lon: place.longitude,
+                location: label(place),
+            })
+            .collect())
+    }
+}
+
+impl Geocoder for OpenMeteoGeocoder {
+    fn name(&self) -> &str {
+        "Open-Meteo"
+    }
+
+    fn search<'a>(&'a self, query: &'a str) -> LocationFuture<'a> {
+        Box::pin(self.search_locations(query))
+    }
+
+    /// Open-Meteo only searches by name, so places are named by Nominatim
+    fn reverse<'a>(&'a self, lat: f64, lon: f64) -> LabelFuture<'a> {
+        Box::pin(async move {
+            NominatimGeocoder::new().reverse_label(lat, lon).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(json: &str) -> Vec<String> {
+        let search: OpenMeteoSearch = serde_json::from_str(json).unwrap();
+        search.results.iter().map(label).collect()
+    }
+
+    #[test]
+    fn labels_places() {
+        let names = labels(r#"{"results": [
+            {"id": 2988507, "name": "Paris", "latitude": 48.85341, "longitude": 2.3488,
+                "country_code": "FR", "admin1": "Île-de-France", "country": "France"},
+            {"id": 2950159, "name": "Berlin", "latitude": 52.52437, "longitude": 13.41053,
+                "admin1": "Berlin", "country": "Germany"},
+            {"id": 1, "name": "Null Island", "latitude": 0.0, "longitude": 0.0}
+        ], "generationtime_ms": 0.5}"#);
+
+        assert_eq!(names, vec![
+            "Paris, Île-de-France, France",
+            // A state sharing the city's name is not repeated
+            "Berlin, Germany",
+            "Null Island",
+        ]);
+    }
+
+    #[test]
+    fn reads_empty_search() {
+        // Open-Meteo leaves results out when nothing matches
+        assert!(labels(r#"{"generationtime_ms": 0.3}"#).is_empty());
+    }
+}
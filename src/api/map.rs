@@ -0,0 +1,289 @@
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::fs::{
+    create_dir_all,
+    metadata,
+    write,
+};
+use std::time::{
+    Duration,
+    SystemTime,
+};
+use dirs::cache_dir;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use super::error::{
+    WeatherError,
+    check_status,
+};
+use super::client::HttpClient;
+use super::provider::user_agent;
+
+pub const DEFAULT_BASE_TILE_URL: &str = "https://tile.openstreetmap.org/{z}/{x}/{y}.png";
+pub const DEFAULT_LAYER_TILE_URL: &str = "https://tile.openweathermap.org/map/{layer}/{z}/{x}/{y}.png?appid={api_key}";
+
+const TILE_CACHE_DIR: &str = "weather-mobile/tiles";
+
+/// Base map tiles hardly change, weather layers are refreshed every few minutes
+const BASE_TILE_MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+const LAYER_TILE_MAX_AGE: Duration = Duration::from_secs(30 * 60);
+
+pub const MIN_ZOOM: u8 = 2;
+pub const MAX_ZOOM: u8 = 12;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum MapLayer {
+    Precipitation,
+    Clouds,
+    Temperature,
+    Wind,
+}
+
+impl std::fmt::Display for MapLayer {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            MapLayer::Precipitation => write!(f, "Precipitation"),
+            MapLayer::Clouds => write!(f, "Clouds"),
+            MapLayer::Temperature => write!(f, "Temperature"),
+            MapLayer::Wind => write!(f, "Wind"),
+        }
+    }
+}
+
+impl Default for MapLayer {
+    fn default() -> Self {
+        MapLayer::Precipitation
+    }
+}
+
+impl MapLayer {
+    pub const ALL: &'static [MapLayer] = &[
+        MapLayer::Precipitation,
+        MapLayer::Clouds,
+        MapLayer::Temperature,
+        MapLayer::Wind,
+    ];
+
+    /// OpenWeather's layer name, substituted for {layer} in the tile URL
+    pub fn id(&self) -> &'static str {
+        match *self {
+            MapLayer::Precipitation => "precipitation_new",
+            MapLayer::Clouds => "clouds_new",
+            MapLayer::Temperature => "temp_new",
+            MapLayer::Wind => "wind_new",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<MapLayer> {
+        MapLayer::ALL.iter()
+            .find(|layer| layer.id() == id)
+            .copied()
+    }
+}
+
+/// A slippy map tile, x and y wrap around at each zoom level
+#[derive(Clone, Copy, PartialEq)]
+pub struct Tile {
+    pub z: u8,
+    pub x: i64,
+    pub y: i64,
+}
+
+/// Position of a coordinate in tiles at a zoom level, the fraction being where it falls in the tile
+pub fn tile_position(lat: f64, lon: f64, zoom: u8) -> (f64, f64) {
+    let n = (1u64 << zoom) as f64;
+    let lat = lat.max(-85.0511).min(85.0511).to_radians();
+    let x = (lon + 180.0) / 360.0 * n;
+    let y = (1.0 - (lat.tan() + 1.0 / lat.cos()).ln() / std::f64::consts::PI) / 2.0 * n;
+
+    (x, y)
+}
+
+/// Coordinate at a position in tiles, the inverse of tile_position with the longitude wrapped around
+pub fn tile_coordinates(x: f64, y: f64, zoom: u8) -> (f64, f64) {
+    let n = (1u64 << zoom) as f64;
+    let lon = (x / n * 360.0).rem_euclid(360.0) - 180.0;
+    let lat = (std::f64::consts::PI * (1.0 - 2.0 * y / n)).sinh().atan().to_degrees();
+
+    (lat, lon)
+}
+
+impl Tile {
+    pub fn is_valid(&self) -> bool {
+        self.y >= 0 && self.y < (1i64 << self.z)
+    }
+
+    fn wrapped_x(&self) -> i64 {
+        self.x.rem_euclid(1i64 << self.z)
+    }
+
+    /// Fills in {z}, {x}, {y}, {layer} and {api_key} in a tile URL template
+    pub fn url(&self, template: &str, layer: Option<MapLayer>, api_key: Option<&str>) -> String {
+        template
+            .replace("{z}", &self.z.to_string())
+            .replace("{x}", &self.wrapped_x().to_string())
+            .replace("{y}", &self.y.to_string())
+            .replace("{layer}", layer.map(|l| l.id()).unwrap_or(""))
+            .replace("{api_key}", api_key.unwrap_or("").trim())
+    }
+
+    /// Cached tiles are kept per template, so pointing at another tile server starts afresh
+    fn cache_path(&self, cache_root: &Path, template: &str, layer: Option<MapLayer>) -> PathBuf {
+        let mut dir = cache_root.to_path_buf();
+        dir.push(format!("{:016x}", stable_hash(template)));
+        dir.push(layer.map(|l| l.id()).unwrap_or("base"));
+        dir.push(self.z.to_string());
+        dir.push(self.wrapped_x().to_string());
+        dir.push(format!("{}.png", self.y));
+
+        dir
+    }
+}
+
+/// FNV-1a, which unlike DefaultHasher gives the same hash with every Rust version,
+/// so tiles cached before an upgrade are still found
+fn stable_hash(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// Where tiles are cached on disk
+pub fn tile_cache_dir() -> Option<PathBuf> {
+    let mut dir = cache_dir()?;
+    dir.push(TILE_CACHE_DIR);
+
+    Some(dir)
+}
+
+fn is_fresh(path: &Path, max_age: Duration) -> bool {
+    metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .map(|age| age < max_age)
+        .unwrap_or(false)
+}
+
+/// Path to the tile image, downloading it unless a fresh copy is cached on disk.
+/// A stale copy is still used when the tile server can not be reached
+pub async fn get_tile(cache_root: &Path, tile: Tile, template: &str, layer: Option<MapLayer>, api_key: Option<&str>) -> Result<PathBuf, WeatherError> {
+    let path = tile.cache_path(cache_root, template, layer);
+    let max_age = if layer.is_some() {
+        LAYER_TILE_MAX_AGE
+    } else {
+        BASE_TILE_MAX_AGE
+    };
+    if is_fresh(&path, max_age) {
+        return Ok(path);
+    }
+
+    let url = tile.url(template, layer, api_key);
+    let user_agent = user_agent();
+    let response = HttpClient::shared().get_bytes(&url, &[("User-Agent", &user_agent)]).await
+        .and_then(|response| check_status(&response).map(|_| response));
+    let response = match response {
+        Ok(response) => response,
+        Err(err) if err.is_offline() && path.exists() => return Ok(path),
+        Err(err) => return Err(err),
+    };
+
+    if let Some(dir) = path.parent() {
+        create_dir_all(dir)?;
+    }
+    write(&path, response.body())?;
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{
+        Read,
+        Write,
+    };
+    use std::net::TcpListener;
+    use std::sync::Arc;
+    use std::sync::atomic::{
+        AtomicUsize,
+        Ordering,
+    };
+    use std::thread;
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\ntile";
+
+    /// Serves a single tile, counting the requests made
+    fn serve_tile() -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let template = format!("http://{}/{{z}}/{{x}}/{{y}}.png", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0; 1024];
+            while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                match stream.read(&mut buffer) {
+                    Ok(0) | Err(_) => break,
+                    Ok(read) => request.extend_from_slice(&buffer[..read]),
+                }
+            }
+            counter.fetch_add(1, Ordering::SeqCst);
+            let head = format!("HTTP/1.1 200 OK\r\nContent-Type: image/png\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                PNG.len());
+            stream.write_all(head.as_bytes()).unwrap();
+            stream.write_all(PNG).unwrap();
+        });
+
+        (template, requests)
+    }
+
+    fn run<F: std::future::Future>(future: F) -> F::Output {
+        let context = gtk::glib::MainContext::new();
+        context.with_thread_default(|| context.block_on(future))
+    }
+
+    #[test]
+    fn converts_tile_positions_back() {
+        for (lat, lon) in [(51.5074, -0.1278), (-33.8688, 151.2093), (0.0, 0.0), (64.1466, -21.9426)].iter() {
+            let (x, y) = tile_position(*lat, *lon, 7);
+            let (back_lat, back_lon) = tile_coordinates(x, y, 7);
+            assert!((back_lat - lat).abs() < 1e-9 && (back_lon - lon).abs() < 1e-9, "{}, {}", lat, lon);
+        }
+        // Past the antimeridian the longitude wraps around
+        let (_, lon) = tile_coordinates(128.0 + 32.0, 64.0, 7);
+        assert!((lon + 90.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn hashes_templates_stably() {
+        assert_eq!(stable_hash(""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(stable_hash("a"), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[test]
+    fn caches_downloaded_tiles() {
+        let cache = std::env::temp_dir().join(format!("weather-mobile-tiles-{}", std::process::id()));
+        let (template, requests) = serve_tile();
+        let tile = Tile { z: 3, x: 9, y: 2 };
+
+        let path = run(get_tile(&cache, tile, &template, None, None)).unwrap();
+        assert!(path.starts_with(&cache));
+        assert!(path.ends_with("3/1/2.png"));
+        assert_eq!(std::fs::read(&path).unwrap(), PNG);
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+        // The server only answers once, the second request must come from the cache
+        let cached = run(get_tile(&cache, tile, &template, None, None)).unwrap();
+        assert_eq!(cached, path);
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+        let _ = std::fs::remove_dir_all(&cache);
+    }
+}
@@ -0,0 +1,141 @@
+use serde::Deserialize;
+use super::units::Units;
+use super::error::WeatherError;
+use super::client::HttpClient;
+
+const OPEN_METEO_MARINE_API_URL: &str = "https://marine-api.open-meteo.com";
+const OPEN_METEO_MARINE_API_VERSION: &str = "v1";
+
+const MARINE_VARIABLES: &str = "wave_height,wave_direction,wave_period,\
+swell_wave_height,swell_wave_direction,swell_wave_period,sea_surface_temperature";
+
+/// Hours of marine forecast kept for the outlook
+const MARINE_FORECAST_HOURS: usize = 24;
+
+/// Sea state at a point in time, heights in metres or feet depending on the units
+#[derive(Deserialize, Clone)]
+pub struct MarineConditions {
+    #[serde(rename = "time")]
+    pub dt: i64,
+    #[serde(default)]
+    pub wave_height: Option<f64>,
+    #[serde(default)]
+    pub wave_direction: Option<f64>,
+    #[serde(default)]
+    pub wave_period: Option<f64>,
+    #[serde(default)]
+    pub swell_wave_height: Option<f64>,
+    #[serde(default)]
+    pub swell_wave_direction: Option<f64>,
+    #[serde(default)]
+    pub swell_wave_period: Option<f64>,
+    #[serde(default)]
+    pub sea_surface_temperature: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct MarineHourly {
+    time: Vec<i64>,
+    wave_height: Vec<Option<f64>>,
+    wave_direction: Vec<Option<f64>>,
+    wave_period: Vec<Option<f64>>,
+    swell_wave_height: Vec<Option<f64>>,
+    swell_wave_direction: Vec<Option<f64>>,
+    swell_wave_period: Vec<Option<f64>>,
+    sea_surface_temperature: Vec<Option<f64>>,
+}
+
+#[derive(Deserialize)]
+struct MarineResponse {
+    current: MarineConditions,
+    hourly: MarineHourly,
+}
+
+pub struct MarineData {
+    pub current: MarineConditions,
+    pub hourly: Vec<MarineConditions>,
+}
+
+fn at(values: &[Option<f64>], index: usize) -> Option<f64> {
+    values.get(index).copied().flatten()
+}
+
+impl MarineHourly {
+    fn conditions(&self, index: usize) -> MarineConditions {
+        MarineConditions {
+            dt: self.time[index],
+            wave_height: at(&self.wave_height, index),
+            wave_direction: at(&self.wave_direction, index),
+            wave_period: at(&self.wave_period, index),
+            swell_wave_height: at(&self.swell_wave_height, index),
+            swell_wave_direction: at(&self.swell_wave_direction, index),
+            swell_wave_period: at(&self.swell_wave_period, index),
+            sea_surface_temperature: at(&self.sea_surface_temperature, index),
+        }
+    }
+}
+
+impl MarineConditions {
+    /// Inland grid cells come back with every value missing
+    pub fn is_empty(&self) -> bool {
+        self.wave_height.is_none()
+            && self.swell_wave_height.is_none()
+            && self.sea_surface_temperature.is_none()
+    }
+}
+
+/// The 16 point compass direction for a bearing in degrees, e.g. "WSW"
+pub fn compass_point(degrees: f64) -> &'static str {
+    const POINTS: [&str; 16] = [
+        "N", "NNE", "NE", "ENE", "E", "ESE", "SE", "SSE",
+        "S", "SSW", "SW", "WSW", "W", "WNW", "NW", "NNW",
+    ];
+    let index = (degrees.rem_euclid(360.0) / 22.5).round() as usize % POINTS.len();
+
+    POINTS[index]
+}
+
+fn base_url() -> String {
+    format!("{}/{}",
+        OPEN_METEO_MARINE_API_URL,
+        OPEN_METEO_MARINE_API_VERSION)
+}
+
+/// Waves, swell and sea temperature, or `None` when the location has no marine data
+pub async fn get_marine(units: Units, lat: f64, lon: f64) -> Result<Option<MarineData>, WeatherError> {
+    let (length_unit, temperature_unit) = match units {
+        Units::Metric => ("metric", "celsius"),
+        Units::Imperial => ("imperial", "fahrenheit"),
+    };
+    let url = format!("{}/marine?latitude={}&longitude={}\
+&current={}&hourly={}&forecast_days=2\
+&length_unit={}&temperature_unit={}&timeformat=unixtime&timezone=auto",
+        base_url(),
+        lat, lon,
+        MARINE_VARIABLES,
+        MARINE_VARIABLES,
+        length_unit,
+        temperature_unit);
+
+    let response: MarineResponse = match HttpClient::shared().get_json(&url, &[]).await {
+        Ok(response) => response,
+        // Points far from the sea are rejected outright rather than returned empty
+        Err(WeatherError::HttpStatus(400)) => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    if response.current.is_empty() {
+        return Ok(None);
+    }
+
+    let hourly = &response.hourly;
+    let hourly = (0..hourly.time.len())
+        .map(|i| hourly.conditions(i))
+        .filter(|c| c.dt >= response.current.dt && !c.is_empty())
+        .take(MARINE_FORECAST_HOURS)
+        .collect();
+
+    Ok(Some(MarineData {
+        current: response.current,
+        hourly,
+    }))
+}
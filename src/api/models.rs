@@ -0,0 +1,327 @@
+use std::collections::HashMap;
+use serde::Deserialize;
+use super::units::Units;
+use super::error::WeatherError;
+use super::client::HttpClient;
+
+const OPEN_METEO_API_URL: &str = "https://api.open-meteo.com";
+const OPEN_METEO_API_VERSION: &str = "v1";
+
+/// Hours compared between the models
+const COMPARISON_HOURS: usize = 48;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ForecastModel {
+    Ecmwf,
+    Gfs,
+    Icon,
+    Gem,
+    MeteoFrance,
+    Jma,
+}
+
+impl std::fmt::Display for ForecastModel {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            ForecastModel::Ecmwf => write!(f, "ECMWF"),
+            ForecastModel::Gfs => write!(f, "GFS"),
+            ForecastModel::Icon => write!(f, "ICON"),
+            ForecastModel::Gem => write!(f, "GEM"),
+            ForecastModel::MeteoFrance => write!(f, "Météo-France"),
+            ForecastModel::Jma => write!(f, "JMA"),
+        }
+    }
+}
+
+impl ForecastModel {
+    pub const ALL: &'static [ForecastModel] = &[
+        ForecastModel::Ecmwf,
+        ForecastModel::Gfs,
+        ForecastModel::Icon,
+        ForecastModel::Gem,
+        ForecastModel::MeteoFrance,
+        ForecastModel::Jma,
+    ];
+
+    /// Open-Meteo's model name, also the suffix of each hourly variable
+    pub fn id(&self) -> &'static str {
+        match *self {
+            ForecastModel::Ecmwf => "ecmwf_ifs025",
+            ForecastModel::Gfs => "gfs_seamless",
+            ForecastModel::Icon => "icon_seamless",
+            ForecastModel::Gem => "gem_seamless",
+            ForecastModel::MeteoFrance => "meteofrance_seamless",
+            ForecastModel::Jma => "jma_seamless",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ModelsResponse {
+    /// Keyed by variable and model, e.g. temperature_2m_gfs_seamless, plus time
+    hourly: HashMap<String, Vec<Option<f64>>>,
+}
+
+/// Hourly temperature and precipitation (mm) from one model
+pub struct ModelSeries {
+    pub model: ForecastModel,
+    pub temperature: Vec<Option<f64>>,
+    pub precipitation: Vec<Option<f64>>,
+}
+
+pub struct ModelComparison {
+    pub units: Units,
+    pub hours: Vec<i64>,
+    pub series: Vec<ModelSeries>,
+}
+
+/// How closely the models agree
+#[derive(Clone, Copy, PartialEq)]
+pub enum Confidence {
+    High,
+    Moderate,
+    Low,
+}
+
+impl std::fmt::Display for Confidence {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            Confidence::High => write!(f, "High"),
+            Confidence::Moderate => write!(f, "Moderate"),
+            Confidence::Low => write!(f, "Low"),
+        }
+    }
+}
+
+impl ModelComparison {
+    fn values_at(&self, index: usize, values: fn(&ModelSeries) -> &[Option<f64>]) -> Vec<f64> {
+        self.series.iter()
+            .filter_map(|s| values(s).get(index).copied().flatten())
+            .collect()
+    }
+
+    /// Lowest and highest temperature forecast by any model for the hour
+    pub fn temperature_range(&self, index: usize) -> Option<(f64, f64)> {
+        let values = self.values_at(index, |s| &s.temperature);
+        let min = values.iter().copied().reduce(f64::min)?;
+        let max = values.iter().copied().reduce(f64::max)?;
+
+        Some((min, max))
+    }
+
+    /// Mean spread in temperature between the models
+    pub fn temperature_spread(&self) -> Option<f64> {
+        let spreads = (0..self.hours.len())
+            .filter_map(|i| self.temperature_range(i))
+            .map(|(min, max)| max - min)
+            .collect::<Vec<_>>();
+        if spreads.is_empty() {
+            return None;
+        }
+
+        Some(spreads.iter().sum::<f64>() / spreads.len() as f64)
+    }
+
+    /// Models expecting any precipitation over the period
+    pub fn models_with_precipitation(&self) -> usize {
+        self.series.iter()
+            .filter(|s| s.precipitation.iter().flatten().any(|p| *p >= 0.1))
+            .count()
+    }
+
+    pub fn confidence(&self) -> Option<Confidence> {
+        // Thresholds are in °C, scaled for Fahrenheit
+        let scale = match self.units {
+            Units::Metric => 1.0,
+            Units::Imperial => 1.8,
+        };
+        let spread = self.temperature_spread()? / scale;
+        let wet = self.models_with_precipitation();
+        let split = wet > 0 && wet < self.series.len();
+
+        Some(if spread < 1.5 && !split {
+            Confidence::High
+        } else if spread < 3.0 {
+            Confidence::Moderate
+        } else {
+            Confidence::Low
+        })
+    }
+}
+
+fn base_url() -> String {
+    format!("{}/{}",
+        OPEN_METEO_API_URL,
+        OPEN_METEO_API_VERSION)
+}
+
+/// Hourly temperature and precipitation from each model, leaving out models with no data for the location
+pub async fn get_model_comparison(units: Units, lat: f64, lon: f64) -> Result<ModelComparison, WeatherError> {
+    let temperature_unit = match units {
+        Units::Metric => "celsius",
+        Units::Imperial => "fahrenheit",
+    };
+    let models = ForecastModel::ALL.iter()
+        .map(|m| m.id())
+        .collect::<Vec<_>>()
+        .join(",");
+    let url = format!("{}/forecast?latitude={}&longitude={}\
+&hourly=temperature_2m,precipitation&models={}&forecast_hours={}\
+&temperature_unit={}&precipitation_unit=mm&timeformat=unixtime&timezone=auto",
+        base_url(),
+        lat, lon,
+        models,
+        COMPARISON_HOURS,
+        temperature_unit);
+
+    let response: ModelsResponse = HttpClient::shared().get_json(&url, &[]).await?;
+
+    comparison_from(units, response)
+}
+
+fn comparison_from(units: Units, mut response: ModelsResponse) -> Result<ModelComparison, WeatherError> {
+    let hours = response.hourly.remove("time")
+        .ok_or_else(|| WeatherError::Decode {
+            path: String::from("hourly.time"),
+            message: String::from("missing field `time`"),
+        })?
+        .into_iter()
+        .map(|t| t.unwrap_or_default() as i64)
+        .collect();
+
+    let series = ForecastModel::ALL.iter()
+        .filter_map(|model| {
+            let temperature = response.hourly.remove(&format!("temperature_2m_{}", model.id()))
+                .filter(|temperature| temperature.iter().any(Option::is_some));
+            let temperature = if let Some(temperature) = temperature {
+                temperature
+            } else {
+                println!("Leaving {} out of the model comparison, it has no forecast for this location", model);
+                return None;
+            };
+            let precipitation = response.hourly.remove(&format!("precipitation_{}", model.id()))
+                .unwrap_or_default();
+
+            Some(ModelSeries {
+                model: *model,
+                temperature,
+                precipitation,
+            })
+        })
+        .collect();
+
+    Ok(ModelComparison {
+        units,
+        hours,
+        series,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn comparison(units: Units, temperatures: &[&[Option<f64>]], precipitation: &[&[Option<f64>]]) -> ModelComparison {
+        ModelComparison {
+            units,
+            hours: (0..temperatures[0].len() as i64).map(|h| h * 3600).collect(),
+            series: temperatures.iter()
+                .zip(precipitation.iter())
+                .zip(ForecastModel::ALL.iter())
+                .map(|((temperature, precipitation), model)| ModelSeries {
+                    model: *model,
+                    temperature: temperature.to_vec(),
+                    precipitation: precipitation.to_vec(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn finds_temperature_range_and_spread() {
+        let models = comparison(Units::Metric, &[
+            &[Some(10.0), Some(14.0), None],
+            &[Some(12.0), Some(10.0), None],
+            &[None, Some(12.0), None],
+        ], &[&[], &[], &[]]);
+
+        assert_eq!(models.temperature_range(0), Some((10.0, 12.0)));
+        assert_eq!(models.temperature_range(1), Some((10.0, 14.0)));
+        // No model has the hour
+        assert_eq!(models.temperature_range(2), None);
+        assert_eq!(models.temperature_range(3), None);
+        assert_eq!(models.temperature_spread(), Some(3.0));
+
+        let empty = comparison(Units::Metric, &[&[None]], &[&[]]);
+        assert_eq!(empty.temperature_spread(), None);
+        assert!(empty.confidence().is_none());
+    }
+
+    #[test]
+    fn counts_models_with_precipitation() {
+        let models = comparison(Units::Metric, &[&[Some(1.0)], &[Some(1.0)], &[Some(1.0)]], &[
+            &[Some(0.0), Some(0.05), None],
+            &[Some(0.0), Some(0.1)],
+            &[],
+        ]);
+
+        assert_eq!(models.models_with_precipitation(), 1);
+    }
+
+    #[test]
+    fn rates_confidence() {
+        let dry: &[Option<f64>] = &[Some(0.0)];
+        let wet: &[Option<f64>] = &[Some(2.0)];
+        let cases = [
+            (Units::Metric, 1.0, wet, wet, Confidence::High),
+            (Units::Metric, 1.0, dry, dry, Confidence::High),
+            // Models disagreeing on precipitation
+            (Units::Metric, 1.0, dry, wet, Confidence::Moderate),
+            (Units::Metric, 2.5, dry, dry, Confidence::Moderate),
+            (Units::Metric, 2.5, dry, wet, Confidence::Moderate),
+            (Units::Metric, 3.5, dry, dry, Confidence::Low),
+            // The same spreads in Fahrenheit
+            (Units::Imperial, 2.5, dry, dry, Confidence::High),
+            (Units::Imperial, 4.5, dry, dry, Confidence::Moderate),
+            (Units::Imperial, 6.0, dry, dry, Confidence::Low),
+        ];
+        for (units, spread, first, second, expected) in cases.iter() {
+            let models = comparison(*units, &[&[Some(50.0)], &[Some(50.0 + spread)]], &[*first, *second]);
+            let confidence = models.confidence();
+            assert!(confidence == Some(*expected), "{} spread of {} gave {:?}",
+                units, spread, confidence.map(|c| c.to_string()));
+        }
+    }
+
+    #[test]
+    fn reads_models_with_forecasts() {
+        let response: ModelsResponse = serde_json::from_str(r#"{
+            "hourly": {
+                "time": [1717200000, 1717203600],
+                "temperature_2m_ecmwf_ifs025": [14.2, 13.8],
+                "precipitation_ecmwf_ifs025": [0.0, 0.4],
+                "temperature_2m_gfs_seamless": [null, null],
+                "precipitation_gfs_seamless": [null, null],
+                "temperature_2m_icon_seamless": [null, 13.1]
+            }
+        }"#).unwrap();
+        let models = comparison_from(Units::Metric, response).unwrap();
+
+        assert_eq!(models.hours, vec![1_717_200_000, 1_717_203_600]);
+        // GFS has only nulls and the others are missing
+        let names = models.series.iter().map(|s| s.model.to_string()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["ECMWF", "ICON"]);
+        assert_eq!(models.series[0].precipitation, vec![Some(0.0), Some(0.4)]);
+        assert!(models.series[1].precipitation.is_empty());
+        assert_eq!(models.temperature_range(1), Some((13.1, 13.8)));
+    }
+
+    #[test]
+    fn requires_times() {
+        let response: ModelsResponse = serde_json::from_str(r#"{
+            "hourly": {"temperature_2m_ecmwf_ifs025": [14.2]}
+        }"#).unwrap();
+
+        assert!(matches!(comparison_from(Units::Metric, response), Err(WeatherError::Decode { .. })));
+    }
+}
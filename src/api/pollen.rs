@@ -0,0 +1,179 @@
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use time::{
+    Date,
+    OffsetDateTime,
+    UtcOffset,
+};
+use super::error::WeatherError;
+use super::client::HttpClient;
+
+const OPEN_METEO_AIR_QUALITY_API_URL: &str = "https://air-quality-api.open-meteo.com";
+const OPEN_METEO_AIR_QUALITY_API_VERSION: &str = "v1";
+const POLLEN_FORECAST_DAYS: u32 = 4;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum Allergen {
+    Grass,
+    Birch,
+    Alder,
+    Ragweed,
+    Mugwort,
+    Olive,
+}
+
+impl std::fmt::Display for Allergen {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            Allergen::Grass => write!(f, "Grass"),
+            Allergen::Birch => write!(f, "Birch"),
+            Allergen::Alder => write!(f, "Alder"),
+            Allergen::Ragweed => write!(f, "Ragweed"),
+            Allergen::Mugwort => write!(f, "Mugwort"),
+            Allergen::Olive => write!(f, "Olive"),
+        }
+    }
+}
+
+impl Allergen {
+    pub const ALL: &'static [Allergen] = &[
+        Allergen::Grass,
+        Allergen::Birch,
+        Allergen::Alder,
+        Allergen::Ragweed,
+        Allergen::Mugwort,
+        Allergen::Olive,
+    ];
+
+    /// Approximate grains/m³ at which the count becomes moderate, high and very high
+    fn thresholds(&self) -> (f64, f64, f64) {
+        match *self {
+            Allergen::Grass => (30.0, 50.0, 150.0),
+            Allergen::Birch => (40.0, 80.0, 200.0),
+            Allergen::Alder => (40.0, 80.0, 200.0),
+            Allergen::Ragweed => (10.0, 50.0, 100.0),
+            Allergen::Mugwort => (10.0, 30.0, 50.0),
+            Allergen::Olive => (50.0, 200.0, 400.0),
+        }
+    }
+
+    pub fn level(&self, grains: f64) -> &str {
+        let (moderate, high, very_high) = self.thresholds();
+        if grains >= very_high {
+            "Very high"
+        } else if grains >= high {
+            "High"
+        } else if grains >= moderate {
+            "Moderate"
+        } else if grains > 0.0 {
+            "Low"
+        } else {
+            "None"
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct PollenHourly {
+    time: Vec<i64>,
+    #[serde(default)]
+    grass_pollen: Vec<Option<f64>>,
+    #[serde(default)]
+    birch_pollen: Vec<Option<f64>>,
+    #[serde(default)]
+    alder_pollen: Vec<Option<f64>>,
+    #[serde(default)]
+    ragweed_pollen: Vec<Option<f64>>,
+    #[serde(default)]
+    mugwort_pollen: Vec<Option<f64>>,
+    #[serde(default)]
+    olive_pollen: Vec<Option<f64>>,
+}
+
+#[derive(Deserialize)]
+struct PollenResponse {
+    utc_offset_seconds: i32,
+    hourly: PollenHourly,
+}
+
+impl PollenHourly {
+    fn values(&self, allergen: Allergen) -> &[Option<f64>] {
+        match allergen {
+            Allergen::Grass => &self.grass_pollen,
+            Allergen::Birch => &self.birch_pollen,
+            Allergen::Alder => &self.alder_pollen,
+            Allergen::Ragweed => &self.ragweed_pollen,
+            Allergen::Mugwort => &self.mugwort_pollen,
+            Allergen::Olive => &self.olive_pollen,
+        }
+    }
+}
+
+/// Peak pollen counts (grains/m³) for a day, local to the location
+pub struct PollenDay {
+    pub date: Date,
+    /// The location's offset from UTC the date is in
+    pub utc_offset: UtcOffset,
+    pub levels: Vec<(Allergen, f64)>,
+}
+
+fn pollen_days(response: PollenResponse) -> Vec<PollenDay> {
+    let offset = UtcOffset::from_whole_seconds(response.utc_offset_seconds)
+        .unwrap_or(UtcOffset::UTC);
+    let hourly = &response.hourly;
+    let dates = hourly.time.iter()
+        .map(|t| OffsetDateTime::from_unix_timestamp(*t)
+            .map(|d| d.to_offset(offset).date())
+            .ok())
+        .collect::<Vec<_>>();
+
+    let mut days: Vec<PollenDay> = Vec::new();
+    for (i, date) in dates.iter().enumerate() {
+        let date = if let Some(date) = date {
+            *date
+        } else {
+            continue;
+        };
+        if days.last().map(|d| d.date != date).unwrap_or(true) {
+            days.push(PollenDay {
+                date,
+                utc_offset: offset,
+                levels: Vec::new(),
+            });
+        }
+        let day = days.last_mut().unwrap();
+
+        for allergen in Allergen::ALL.iter() {
+            let value = hourly.values(*allergen).get(i).copied().flatten();
+            if let Some(value) = value {
+                match day.levels.iter_mut().find(|(a, _)| a == allergen) {
+                    Some((_, peak)) => *peak = peak.max(value),
+                    None => day.levels.push((*allergen, value)),
+                }
+            }
+        }
+    }
+
+    days
+}
+
+fn base_url() -> String {
+    format!("{}/{}", 
+        OPEN_METEO_AIR_QUALITY_API_URL, 
+        OPEN_METEO_AIR_QUALITY_API_VERSION)
+}
+
+/// Daily pollen outlook, empty outside of the areas Open-Meteo covers (mostly Europe)
+pub async fn get_pollen(lat: f64, lon: f64) -> Result<Vec<PollenDay>, WeatherError> {
+    let url = format!("{}/air-quality?latitude={}&longitude={}\
+&hourly=grass_pollen,birch_pollen,alder_pollen,ragweed_pollen,mugwort_pollen,olive_pollen\
+&forecast_days={}&timeformat=unixtime&timezone=auto",
+        base_url(),
+        lat, lon,
+        POLLEN_FORECAST_DAYS);
+    let response: PollenResponse = HttpClient::shared().get_json(&url, &[]).await?;
+
+    Ok(pollen_days(response))
+}
@@ -0,0 +1,164 @@
+pub mod openweather;
+pub mod open_meteo;
+pub mod met_norway;
+pub mod nws;
+pub mod fallback;
+pub mod consensus;
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use super::units::Units;
+use super::weather::WeatherData;
+use super::error::WeatherError;
+use openweather::OpenWeatherProvider;
+use open_meteo::OpenMeteoProvider;
+use met_norway::MetNorwayProvider;
+use nws::NwsProvider;
+use consensus::ConsensusProvider;
+use fallback::{
+    FallbackProvider,
+    ProviderHealth,
+};
+
+pub type WeatherFuture<'a> = Pin<Box<dyn Future<Output = Result<WeatherData, WeatherError>> + 'a>>;
+
+/// A source of forecast data, responsible for mapping its own response
+/// format onto the provider neutral WeatherData
+pub trait WeatherProvider {
+    fn name(&self) -> &str;
+    fn fetch<'a>(&'a self, units: Units, lat: f64, lon: f64) -> WeatherFuture<'a>;
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProviderKind {
+    OpenWeather,
+    OpenMeteo,
+    MetNorway,
+    Nws,
+    /// Virtual provider blending all the others
+    Consensus,
+}
+
+/// Open-Meteo needs no API key, so a fresh install shows a forecast straight away
+impl Default for ProviderKind {
+    fn default() -> Self {
+        ProviderKind::OpenMeteo
+    }
+}
+
+impl std::fmt::Display for ProviderKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            ProviderKind::OpenWeather => write!(f, "OpenWeather"),
+            ProviderKind::OpenMeteo => write!(f, "Open-Meteo"),
+            ProviderKind::MetNorway => write!(f, "MET Norway"),
+            ProviderKind::Nws => write!(f, "US National Weather Service"),
+            ProviderKind::Consensus => write!(f, "Consensus of all providers"),
+        }
+    }
+}
+
+impl ProviderKind {
+    pub const ALL: &'static [ProviderKind] = &[
+        ProviderKind::OpenWeather,
+        ProviderKind::OpenMeteo,
+        ProviderKind::MetNorway,
+        ProviderKind::Nws,
+        ProviderKind::Consensus,
+    ];
+
+    /// Providers with a forecast of their own
+    pub const SOURCES: &'static [ProviderKind] = &[
+        ProviderKind::OpenWeather,
+        ProviderKind::OpenMeteo,
+        ProviderKind::MetNorway,
+        ProviderKind::Nws,
+    ];
+
+    pub fn id(&self) -> &str {
+        match *self {
+            ProviderKind::OpenWeather => "openweather",
+            ProviderKind::OpenMeteo => "open-meteo",
+            ProviderKind::MetNorway => "met-norway",
+            ProviderKind::Nws => "nws",
+            ProviderKind::Consensus => "consensus",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<ProviderKind> {
+        Self::ALL.iter()
+            .find(|kind| kind.id() == id)
+            .copied()
+    }
+
+    pub fn create(&self, open_weather_api_key: Option<String>) -> Arc<dyn WeatherProvider> {
+        match *self {
+            ProviderKind::OpenWeather => Arc::new(OpenWeatherProvider::new(open_weather_api_key)),
+            ProviderKind::OpenMeteo => Arc::new(OpenMeteoProvider::new()),
+            ProviderKind::MetNorway => Arc::new(MetNorwayProvider::new()),
+            ProviderKind::Nws => Arc::new(NwsProvider::new()),
+            ProviderKind::Consensus => {
+                // OpenWeather is only blended in once it has an API key
+                let providers = Self::SOURCES.iter()
+                    .filter(|kind| **kind != ProviderKind::OpenWeather || open_weather_api_key.is_some())
+                    .map(|kind| kind.create(open_weather_api_key.clone()))
+                    .collect();
+                Arc::new(ConsensusProvider::new(providers))
+            },
+        }
+    }
+}
+
+/// Estimates a feels like temperature for providers that do not forecast one,
+/// using the Australian apparent temperature (humidity and wind adjusted)
+pub fn apparent_temperature(celsius: f64, humidity: f64, metres_per_second: f64) -> f64 {
+    let vapour_pressure = humidity / 100.0 * 6.105
+        * ((17.27 * celsius) / (237.7 + celsius)).exp();
+
+    celsius + 0.33 * vapour_pressure - 0.70 * metres_per_second - 4.00
+}
+
+pub fn temperature_in(units: Units, celsius: f64) -> f64 {
+    match units {
+        Units::Metric => celsius,
+        Units::Imperial => celsius * 9.0 / 5.0 + 32.0,
+    }
+}
+
+pub fn speed_in(units: Units, metres_per_second: f64) -> f64 {
+    match units {
+        Units::Metric => metres_per_second,
+        Units::Imperial => metres_per_second * 2.236_936,
+    }
+}
+
+/// User-Agent for services (MET Norway, NWS) that require one identifying the application
+pub fn user_agent() -> String {
+    format!("{}/{} https://github.com/tiggilyboo/weather-mobile",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION"))
+}
+
+/// Creates the primary provider followed by its fallbacks, in order and without repeats
+pub fn create_chain(primary: ProviderKind, fallbacks: &[ProviderKind], open_weather_api_key: Option<String>, health: Arc<ProviderHealth>) -> Arc<dyn WeatherProvider> {
+    let mut kinds = vec![primary];
+    for kind in fallbacks.iter() {
+        if !kinds.contains(kind) {
+            kinds.push(*kind);
+        }
+    }
+    let providers = kinds.into_iter()
+        .map(|kind| (kind, kind.create(open_weather_api_key.clone())))
+        .collect();
+
+    Arc::new(FallbackProvider::new(providers, health))
+}
+
+pub fn default_provider() -> Arc<dyn WeatherProvider> {
+    ProviderKind::default().create(None)
+}
@@ -0,0 +1,385 @@
+use std::sync::Arc;
+use futures::future::join_all;
+use time::UtcOffset;
+use crate::api::units::Units;
+use crate::api::error::WeatherError;
+use crate::api::weather::{
+    FieldProvenance,
+    Weather,
+    WeatherAlert,
+    WeatherData,
+    WeatherDayTemps,
+    date_at,
+    estimated_offset,
+};
+use super::{
+    WeatherProvider,
+    WeatherFuture,
+};
+
+/// Combines the values several providers forecast for the same field
+trait Blend: Clone {
+    fn median(values: &[&Self]) -> Self;
+}
+
+fn median(values: &mut Vec<f64>) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let middle = values.len() / 2;
+
+    Some(if values.len() % 2 == 0 {
+        (values[middle - 1] + values[middle]) / 2.0
+    } else {
+        values[middle]
+    })
+}
+
+fn median_of<T>(values: &[&T], value: fn(&T) -> f64) -> f64 {
+    median(&mut values.iter().map(|v| value(v)).collect()).unwrap_or_default()
+}
+
+fn max_of<T>(values: &[&T], value: fn(&T) -> Option<f64>) -> Option<f64> {
+    values.iter()
+        .filter_map(|v| value(v))
+        .reduce(f64::max)
+}
+
+impl Blend for f64 {
+    fn median(values: &[&Self]) -> Self {
+        median_of(values, |v| *v)
+    }
+}
+
+impl Blend for WeatherDayTemps {
+    fn median(values: &[&Self]) -> Self {
+        WeatherDayTemps {
+            day: median_of(values, |t| t.day),
+            night: median_of(values, |t| t.night),
+            eve: median_of(values, |t| t.eve),
+            morn: median_of(values, |t| t.morn),
+        }
+    }
+}
+
+/// Medians for most fields, the highest chance and amount of precipitation and
+/// strongest gusts so nothing a provider warns of is lost, and the lowest visibility.
+/// Times, sun and conditions come from the first forecast
+fn blend_weather<T: Blend>(forecasts: &[&Weather<T>]) -> Weather<T> {
+    let first = forecasts[0];
+    let temps = forecasts.iter().map(|f| &f.temp).collect::<Vec<_>>();
+    let feels_like = forecasts.iter().map(|f| &f.feels_like).collect::<Vec<_>>();
+
+    Weather {
+        dt: first.dt,
+        sunrise: first.sunrise,
+        sunset: first.sunset,
+        temp: T::median(&temps),
+        feels_like: T::median(&feels_like),
+        pressure: median_of(forecasts, |f| f.pressure as f64).round() as u32,
+        humidity: median_of(forecasts, |f| f.humidity as f64).round() as u32,
+        dew_point: median_of(forecasts, |f| f.dew_point),
+        uvi: median_of(forecasts, |f| f.uvi),
+        clouds: median_of(forecasts, |f| f.clouds),
+        visibility: forecasts.iter().filter_map(|f| f.visibility).min(),
+        wind_speed: median_of(forecasts, |f| f.wind_speed),
+        wind_deg: first.wind_deg,
+        wind_gust: max_of(forecasts, |f| f.wind_gust),
+        status: first.status.clone(),
+        pop: max_of(forecasts, |f| Some(f.pop)).unwrap_or_default(),
+        rain: max_of(forecasts, |f| f.rain),
+        snow: max_of(forecasts, |f| f.snow),
+    }
+}
+
+/// Blends each entry of the first series with those of the others that match it
+fn blend_series<T: Blend, K: PartialEq>(sources: &[&[Weather<T>]], key: impl Fn(&Weather<T>) -> Option<K>) -> Vec<Weather<T>> {
+    sources[0].iter()
+        .map(|entry| {
+            let entry_key = key(entry);
+            let mut matching = vec![entry];
+            for other in sources[1..].iter() {
+                if let Some(found) = other.iter().find(|o| entry_key.is_some() && key(o) == entry_key) {
+                    matching.push(found);
+                }
+            }
+            blend_weather(&matching)
+        })
+        .collect()
+}
+
+/// The provider giving the highest value of a field, for provenance
+fn highest(sources: &[(String, WeatherData)], value: fn(&WeatherData) -> Option<f64>) -> Option<String> {
+    sources.iter()
+        .filter_map(|(name, data)| value(data).map(|v| (name, v)))
+        .fold(None, |best: Option<(&String, f64)>, (name, v)| match best {
+            Some((_, b)) if b >= v => best,
+            _ => Some((name, v)),
+        })
+        .map(|(name, _)| name.clone())
+}
+
+fn provenance(field: &str, source: String) -> FieldProvenance {
+    FieldProvenance {
+        field: String::from(field),
+        source,
+    }
+}
+
+/// Offset of the location's time zone, from the first provider reporting it or else estimated
+fn location_offset(sources: &[(String, WeatherData)], lon: f64) -> UtcOffset {
+    sources.iter()
+        .find_map(|(_, data)| data.utc_offset)
+        .and_then(|seconds| UtcOffset::from_whole_seconds(seconds).ok())
+        .unwrap_or_else(|| estimated_offset(lon))
+}
+
+fn blend(sources: Vec<(String, WeatherData)>, lon: f64) -> WeatherData {
+    let names = sources.iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let first = &sources[0].1;
+    // Providers stamp each day at a different hour, from local midnight to midday,
+    // so days are matched on the location's calendar rather than the device's
+    let offset = location_offset(&sources, lon);
+
+    let currents = sources.iter().map(|(_, d)| &d.current).collect::<Vec<_>>();
+    let hourly = sources.iter().map(|(_, d)| d.hourly.as_slice()).collect::<Vec<_>>();
+    let daily = sources.iter().map(|(_, d)| d.daily.as_slice()).collect::<Vec<_>>();
+
+    let minutely_source = sources.iter().find(|(_, d)| !d.minutely.is_empty());
+    let mut alerts: Vec<WeatherAlert> = Vec::new();
+    for (_, data) in sources.iter() {
+        for alert in data.alerts.iter() {
+            if !alerts.iter().any(|a| a.event == alert.event && a.start == alert.start) {
+                alerts.push(alert.clone());
+            }
+        }
+    }
+
+    let median = format!("median of {}", names);
+    let mut fields = vec![
+        provenance("Temperature", median.clone()),
+        provenance("Feels like", median.clone()),
+        provenance("Humidity", median.clone()),
+        provenance("Pressure", median.clone()),
+        provenance("Wind speed", median.clone()),
+        provenance("Conditions", format!("from {}", sources[0].0)),
+        provenance("Hourly and weekly", median),
+    ];
+    if let Some(name) = highest(&sources, |d| Some(d.current.pop)) {
+        fields.push(provenance("Precipitation chance", format!("highest, from {}", name)));
+    }
+    if let Some(name) = highest(&sources, |d| d.current.wind_gust) {
+        fields.push(provenance("Wind gusts", format!("highest, from {}", name)));
+    }
+    if let Some((name, _)) = minutely_source {
+        fields.push(provenance("Next hour", format!("from {}", name)));
+    }
+
+    WeatherData {
+        current: blend_weather(&currents),
+        hourly: blend_series(&hourly, |h| Some(h.dt)),
+        minutely: minutely_source
+            .map(|(_, d)| d.minutely.clone())
+            .unwrap_or_default(),
+        daily: blend_series(&daily, |d| date_at(d.dt, offset).ok()),
+        alerts,
+        units: first.units,
+        provider: None,
+        provenance: fields,
+        utc_offset: sources.iter().find_map(|(_, d)| d.utc_offset),
+    }
+}
+
+/// A virtual provider blending the forecasts of several others, fetched in parallel
+pub struct ConsensusProvider {
+    providers: Vec<Arc<dyn WeatherProvider>>,
+}
+
+impl ConsensusProvider {
+    pub fn new(providers: Vec<Arc<dyn WeatherProvider>>) -> Self {
+        ConsensusProvider {
+            providers,
+        }
+    }
+
+    async fn fetch_all(&self, units: Units, lat: f64, lon: f64) -> Result<WeatherData, WeatherError> {
+        let results = join_all(self.providers.iter()
+            .map(|provider| provider.fetch(units, lat, lon)))
+            .await;
+
+        let mut first_err = None;
+        let mut sources = Vec::new();
+        for (provider, result) in self.providers.iter().zip(results) {
+            match result {
+                Ok(data) => sources.push((provider.name().to_string(), data)),
+                Err(err) => {
+                    println!("{} left out of the consensus: {}", provider.name(), err);
+                    if first_err.is_none() {
+                        first_err = Some(err);
+                    }
+                },
+            }
+        }
+
+        if sources.is_empty() {
+            return Err(first_err.expect("Consensus always blends at least one provider"));
+        }
+
+        Ok(blend(sources, lon))
+    }
+}
+
+impl WeatherProvider for ConsensusProvider {
+    fn name(&self) -> &str {
+        "Consensus"
+    }
+
+    fn fetch<'a>(&'a self, units: Units, lat: f64, lon: f64) -> WeatherFuture<'a> {
+        Box::pin(self.fetch_all(units, lat, lon))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn weather<T>(dt: i64, temp: T, feels_like: T, pop: f64, wind_gust: Option<f64>) -> Weather<T> {
+        Weather {
+            dt,
+            sunrise: None,
+            sunset: None,
+            temp,
+            feels_like,
+            pressure: 1010,
+            humidity: 50,
+            dew_point: 5.0,
+            uvi: 1.0,
+            clouds: 20.0,
+            visibility: Some(10000),
+            wind_speed: 3.0,
+            wind_deg: 180,
+            wind_gust,
+            status: Vec::new(),
+            pop,
+            rain: None,
+            snow: None,
+        }
+    }
+
+    fn temps(day: f64) -> WeatherDayTemps {
+        WeatherDayTemps {
+            day,
+            night: day - 10.0,
+            eve: day - 2.0,
+            morn: day - 6.0,
+        }
+    }
+
+    fn data(current: Weather<f64>, daily: Vec<Weather<WeatherDayTemps>>, utc_offset: Option<i32>) -> WeatherData {
+        WeatherData {
+            hourly: vec![current.clone()],
+            current,
+            minutely: Vec::new(),
+            daily,
+            alerts: Vec::new(),
+            units: Some(Units::Metric),
+            provider: None,
+            provenance: Vec::new(),
+            utc_offset,
+        }
+    }
+
+    #[test]
+    fn takes_medians() {
+        assert_eq!(median(&mut vec![]), None);
+        assert_eq!(median(&mut vec![4.0]), Some(4.0));
+        assert_eq!(median(&mut vec![9.0, 1.0, 5.0]), Some(5.0));
+        assert_eq!(median(&mut vec![8.0, 2.0, 4.0, 6.0]), Some(5.0));
+    }
+
+    #[test]
+    fn blends_weather() {
+        let a = weather(0, 10.0, 8.0, 0.1, None);
+        let mut b = weather(0, 12.0, 11.0, 0.6, Some(9.0));
+        b.visibility = Some(4000);
+        b.wind_deg = 90;
+        let c = weather(0, 20.0, 18.0, 0.3, Some(15.0));
+
+        let blended = blend_weather(&[&a, &b, &c]);
+        assert_eq!(blended.temp, 12.0);
+        assert_eq!(blended.feels_like, 11.0);
+        // Nothing a provider warns of is lost
+        assert_eq!(blended.pop, 0.6);
+        assert_eq!(blended.wind_gust, Some(15.0));
+        assert_eq!(blended.visibility, Some(4000));
+        // Directions are not averaged, they come from the first forecast
+        assert_eq!(blended.wind_deg, 180);
+    }
+
+    #[test]
+    fn matches_days_on_location_calendar() {
+        // Sydney at UTC+10, one provider stamps days at local midnight (the previous UTC date)
+        // and the other at local midday
+        let offset = 10 * 3600;
+        let midnight = 1_717_200_000 - offset as i64;
+        let midday = midnight + 12 * 3600;
+        let first = data(weather(midnight, 10.0, 9.0, 0.0, None), vec![
+            weather(midnight, temps(16.0), temps(15.0), 0.2, None),
+            weather(midnight + 86400, temps(18.0), temps(17.0), 0.1, None),
+        ], Some(offset));
+        let second = data(weather(midnight, 12.0, 11.0, 0.0, None), vec![
+            weather(midday, temps(20.0), temps(19.0), 0.5, None),
+            weather(midday + 86400, temps(22.0), temps(21.0), 0.3, None),
+        ], None);
+
+        let blended = blend(vec![
+            (String::from("Open-Meteo"), first),
+            (String::from("OpenWeather"), second),
+        ], 151.2);
+        assert_eq!(blended.daily.len(), 2);
+        assert_eq!(blended.daily[0].dt, midnight);
+        assert_eq!(blended.daily[0].temp.day, 18.0);
+        assert_eq!(blended.daily[0].pop, 0.5);
+        assert_eq!(blended.daily[1].temp.day, 20.0);
+        assert_eq!(blended.daily[1].pop, 0.3);
+        assert_eq!(blended.utc_offset, Some(offset));
+    }
+
+    #[test]
+    fn estimates_offset_when_unreported() {
+        let sources = vec![(String::from("MET Norway"), data(weather(0, 0.0, 0.0, 0.0, None), Vec::new(), None))];
+        assert_eq!(location_offset(&sources, -74.0).whole_hours(), -5);
+        assert_eq!(location_offset(&sources, 151.2).whole_hours(), 10);
+    }
+
+    #[test]
+    fn records_provenance() {
+        let mut windy = data(weather(0, 10.0, 9.0, 0.1, Some(20.0)), Vec::new(), None);
+        windy.minutely = vec![crate::api::weather::WeatherMinutely {
+            dt: 0,
+            precipitation: 0.0,
+        }];
+        let wet = data(weather(0, 12.0, 11.0, 0.7, Some(5.0)), Vec::new(), None);
+
+        let blended = blend(vec![
+            (String::from("OpenWeather"), wet),
+            (String::from("Open-Meteo"), windy),
+        ], 0.0);
+        let source = |field: &str| blended.provenance.iter()
+            .find(|p| p.field == field)
+            .map(|p| p.source.clone());
+
+        assert_eq!(source("Temperature").as_deref(), Some("median of OpenWeather, Open-Meteo"));
+        assert_eq!(source("Conditions").as_deref(), Some("from OpenWeather"));
+        assert_eq!(source("Precipitation chance").as_deref(), Some("highest, from OpenWeather"));
+        assert_eq!(source("Wind gusts").as_deref(), Some("highest, from Open-Meteo"));
+        assert_eq!(source("Next hour").as_deref(), Some("from Open-Meteo"));
+        assert_eq!(blended.minutely.len(), 1);
+        assert_eq!(blended.current.temp, 11.0);
+        assert!(blended.provider.is_none());
+    }
+}
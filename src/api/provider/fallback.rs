@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+use std::sync::{
+    Arc,
+    Mutex,
+};
+use std::time::{
+    Duration,
+    Instant,
+};
+use crate::api::units::Units;
+use crate::api::error::WeatherError;
+use crate::api::weather::WeatherData;
+use super::{
+    ProviderKind,
+    WeatherProvider,
+    WeatherFuture,
+};
+
+/// Number of recent requests used to judge a provider's failure rate
+const HEALTH_WINDOW: usize = 5;
+/// Failure rate over a full window at which a provider is skipped for the cool-down period
+const HEALTH_FAILURE_RATE: f64 = 0.5;
+/// Failures in a row that skip a provider before the window has filled
+const HEALTH_CONSECUTIVE_FAILURES: usize = 3;
+const HEALTH_COOL_DOWN: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Default)]
+struct Health {
+    recent: Vec<bool>,
+    cool_down_until: Option<Instant>,
+}
+
+impl Health {
+    fn failure_rate(&self) -> f64 {
+        if self.recent.is_empty() {
+            return 0.0;
+        }
+        let failures = self.recent.iter().filter(|ok| !**ok).count();
+        failures as f64 / self.recent.len() as f64
+    }
+
+    fn consecutive_failures(&self) -> usize {
+        self.recent.iter().rev().take_while(|ok| !**ok).count()
+    }
+
+    /// A single failure is not enough, the provider must fail often over a full
+    /// window or several times in a row
+    fn is_unhealthy(&self) -> bool {
+        (self.recent.len() >= HEALTH_WINDOW && self.failure_rate() >= HEALTH_FAILURE_RATE)
+            || self.consecutive_failures() >= HEALTH_CONSECUTIVE_FAILURES
+    }
+}
+
+/// Recent success and failure of each provider, shared between the provider
+/// chains created as preferences change so history is not lost
+#[derive(Default)]
+pub struct ProviderHealth {
+    providers: Mutex<HashMap<ProviderKind, Health>>,
+}
+
+impl ProviderHealth {
+    pub fn new() -> Self {
+        ProviderHealth::default()
+    }
+
+    pub fn is_cooling_down(&self, kind: ProviderKind) -> bool {
+        if let Ok(providers) = self.providers.lock() {
+            if let Some(until) = providers.get(&kind).and_then(|h| h.cool_down_until) {
+                return Instant::now() < until;
+            }
+        }
+        false
+    }
+
+    pub fn failure_rate(&self, kind: ProviderKind) -> f64 {
+        self.providers.lock()
+            .map(|providers| providers.get(&kind)
+                .map(|h| h.failure_rate())
+                .unwrap_or(0.0))
+            .unwrap_or(0.0)
+    }
+
+    fn record(&self, kind: ProviderKind, ok: bool) {
+        if let Ok(mut providers) = self.providers.lock() {
+            let health = providers.entry(kind).or_default();
+            health.recent.push(ok);
+            if health.recent.len() > HEALTH_WINDOW {
+                health.recent.remove(0);
+            }
+
+            if ok {
+                health.cool_down_until = None;
+            } else if health.is_unhealthy() {
+                println!("{} is failing, skipping it for {}s", kind, HEALTH_COOL_DOWN.as_secs());
+                health.cool_down_until = Some(Instant::now() + HEALTH_COOL_DOWN);
+            }
+        }
+    }
+}
+
+/// Tries each provider in preference order until one returns data, skipping
+/// those cooling down after repeated failures
+pub struct FallbackProvider {
+    providers: Vec<(ProviderKind, Arc<dyn WeatherProvider>)>,
+    health: Arc<ProviderHealth>,
+}
+
+impl FallbackProvider {
+    pub fn new(providers: Vec<(ProviderKind, Arc<dyn WeatherProvider>)>, health: Arc<ProviderHealth>) -> Self {
+        FallbackProvider {
+            providers,
+            health,
+        }
+    }
+
+    async fn fetch_first(&self, units: Units, lat: f64, lon: f64) -> Result<WeatherData, WeatherError> {
+        let mut first_err = None;
+        let available = self.providers.iter()
+            .filter(|(kind, _)| !self.health.is_cooling_down(*kind))
+            .collect::<Vec<_>>();
+        // Everything is cooling down, rather than give up try them all anyway
+        let candidates = if available.is_empty() {
+            self.providers.iter().collect()
+        } else {
+            available
+        };
+
+        for (kind, provider) in candidates {
+            match provider.fetch(units, lat, lon).await {
+                Ok(mut data) => {
+                    self.health.record(*kind, true);
+                    data.provider = Some(provider.name().to_string());
+                    return Ok(data);
+                },
+                Err(err) => {
+                    println!("{} failed: {}", provider.name(), err);
+                    self.health.record(*kind, false);
+                    if first_err.is_none() {
+                        first_err = Some(err);
+                    }
+                },
+            }
+        }
+
+        Err(first_err.expect("Provider chain always contains the primary provider"))
+    }
+}
+
+impl WeatherProvider for FallbackProvider {
+    fn name(&self) -> &str {
+        self.providers.first()
+            .map(|(_, provider)| provider.name())
+            .unwrap_or("None")
+    }
+
+    fn fetch<'a>(&'a self, units: Units, lat: f64, lon: f64) -> WeatherFuture<'a> {
+        Box::pin(self.fetch_first(units, lat, lon))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn health_after(results: &[bool]) -> ProviderHealth {
+        let health = ProviderHealth::new();
+        for ok in results {
+            health.record(ProviderKind::OpenMeteo, *ok);
+        }
+        health
+    }
+
+    #[test]
+    fn single_failure_does_not_cool_down() {
+        assert!(!health_after(&[true, false]).is_cooling_down(ProviderKind::OpenMeteo));
+        assert!(!health_after(&[false, true, false]).is_cooling_down(ProviderKind::OpenMeteo));
+    }
+
+    #[test]
+    fn consecutive_failures_cool_down() {
+        assert!(!health_after(&[false, false]).is_cooling_down(ProviderKind::OpenMeteo));
+        assert!(health_after(&[false, false, false]).is_cooling_down(ProviderKind::OpenMeteo));
+    }
+
+    #[test]
+    fn failing_over_a_full_window_cools_down() {
+        assert!(!health_after(&[true, false, true, false]).is_cooling_down(ProviderKind::OpenMeteo));
+        assert!(health_after(&[true, false, true, false, false]).is_cooling_down(ProviderKind::OpenMeteo));
+    }
+
+    #[test]
+    fn success_ends_cool_down() {
+        assert!(!health_after(&[false, false, false, true]).is_cooling_down(ProviderKind::OpenMeteo));
+    }
+}
@@ -0,0 +1,587 @@
+use std::sync::Mutex;
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use time::{
+    OffsetDateTime,
+    UtcOffset,
+    Date,
+};
+use time::format_description::well_known::Rfc3339;
+use crate::api::units::Units;
+use crate::api::weather::{
+    Weather,
+    WeatherData,
+    WeatherDayTemps,
+    WeatherStatus,
+    estimated_offset,
+};
+use crate::api::error::{
+    WeatherError,
+    check_status,
+    decode,
+};
+use crate::api::client::HttpClient;
+use super::{
+    WeatherProvider,
+    WeatherFuture,
+    apparent_temperature,
+    temperature_in,
+    speed_in,
+    user_agent,
+};
+
+const MET_API_URL: &str = "https://api.met.no/weatherapi/locationforecast";
+const MET_API_VERSION: &str = "2.0";
+
+/// Number of hours shown in the hourly view, matching OpenWeather's One Call
+const HOURLY_LIMIT: usize = 48;
+
+#[derive(Deserialize, Default)]
+struct MetInstantDetails {
+    air_temperature: Option<f64>,
+    air_pressure_at_sea_level: Option<f64>,
+    cloud_area_fraction: Option<f64>,
+    dew_point_temperature: Option<f64>,
+    relative_humidity: Option<f64>,
+    wind_from_direction: Option<f64>,
+    wind_speed: Option<f64>,
+    wind_speed_of_gust: Option<f64>,
+    ultraviolet_index_clear_sky: Option<f64>,
+}
+
+#[derive(Deserialize, Default)]
+struct MetInstant {
+    #[serde(default)]
+    details: MetInstantDetails,
+}
+
+#[derive(Deserialize)]
+struct MetSummary {
+    symbol_code: String,
+}
+
+#[derive(Deserialize, Default)]
+struct MetPeriodDetails {
+    precipitation_amount: Option<f64>,
+    probability_of_precipitation: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct MetPeriod {
+    summary: Option<MetSummary>,
+    #[serde(default)]
+    details: MetPeriodDetails,
+}
+
+#[derive(Deserialize)]
+struct MetTimeseriesData {
+    #[serde(default)]
+    instant: MetInstant,
+    next_1_hours: Option<MetPeriod>,
+    next_6_hours: Option<MetPeriod>,
+    next_12_hours: Option<MetPeriod>,
+}
+
+#[derive(Deserialize)]
+struct MetTimeseries {
+    time: String,
+    data: MetTimeseriesData,
+}
+
+#[derive(Deserialize)]
+struct MetProperties {
+    timeseries: Vec<MetTimeseries>,
+}
+
+#[derive(Deserialize)]
+struct MetForecast {
+    properties: MetProperties,
+}
+
+struct MetCachedForecast {
+    lat: String,
+    lon: String,
+    last_modified: Option<String>,
+    expires: Option<OffsetDateTime>,
+    body: String,
+}
+
+/// Maps MET symbol codes (e.g. lightrainshowers_day) onto the OpenWeather icon set in icons/
+pub fn symbol_code_status(symbol_code: &str) -> WeatherStatus {
+    let mut parts = symbol_code.splitn(2, '_');
+    let symbol = parts.next().unwrap_or("");
+    let variant = match parts.next() {
+        Some("night") => "n",
+        _ => "d",
+    };
+
+    let (icon, main) = if symbol.contains("thunder") {
+        ("11", "Thunderstorm")
+    } else if symbol.contains("snow") || symbol.contains("sleet") {
+        ("13", "Snow")
+    } else if symbol.contains("showers") {
+        ("09", "Rain")
+    } else if symbol.contains("rain") {
+        ("10", "Rain")
+    } else {
+        match symbol {
+            "clearsky" => ("01", "Clear"),
+            "fair" => ("02", "Clouds"),
+            "partlycloudy" => ("03", "Clouds"),
+            "cloudy" => ("04", "Clouds"),
+            "fog" => ("50", "Fog"),
+            _ => {
+                return WeatherStatus {
+                    main: String::from("Unknown"),
+                    description: String::from("unknown"),
+                    icon: String::from("unknown"),
+                };
+            }
+        }
+    };
+
+    WeatherStatus {
+        main: String::from(main),
+        description: describe_symbol(symbol),
+        icon: format!("{}{}", icon, variant),
+    }
+}
+
+/// Splits a compound symbol such as heavysleetshowersandthunder into words
+fn describe_symbol(symbol: &str) -> String {
+    const WORDS: &[(&str, &str)] = &[
+        ("clearsky", "clear sky"),
+        ("partlycloudy", "partly cloudy"),
+        ("cloudy", "cloudy"),
+        ("fair", "fair"),
+        ("fog", "fog"),
+        ("light", "light"),
+        ("heavy", "heavy"),
+        ("rain", "rain"),
+        ("sleet", "sleet"),
+        ("snow", "snow"),
+        ("showers", "showers"),
+        ("and", "and"),
+        ("thunder", "thunder"),
+    ];
+
+    let mut words = Vec::new();
+    let mut rest = symbol;
+    while !rest.is_empty() {
+        if let Some((token, word)) = WORDS.iter().find(|(token, _)| rest.starts_with(token)) {
+            words.push(*word);
+            rest = &rest[token.len()..];
+        } else {
+            words.push(rest);
+            break;
+        }
+    }
+
+    words.join(" ")
+}
+
+/// Parses HTTP dates such as Tue, 15 Nov 1994 08:12:31 GMT
+fn parse_http_date(value: &str) -> Option<OffsetDateTime> {
+    let format = time::format_description::parse(
+        "[weekday repr:short], [day] [month repr:short] [year] [hour]:[minute]:[second] GMT").ok()?;
+    time::PrimitiveDateTime::parse(value, &format)
+        .ok()
+        .map(|datetime| datetime.assume_utc())
+}
+
+struct MetStep {
+    dt: i64,
+    date: Date,
+    hour: u8,
+    weather: Weather<f64>,
+    precipitation: f64,
+    symbol: Option<String>,
+}
+
+impl MetTimeseries {
+    fn datetime(&self) -> Option<OffsetDateTime> {
+        OffsetDateTime::parse(&self.time, &Rfc3339).ok()
+    }
+
+    /// The shortest forecast period following this step, used for its status and precipitation
+    fn period(&self) -> Option<&MetPeriod> {
+        self.data.next_1_hours.as_ref()
+            .or(self.data.next_6_hours.as_ref())
+            .or(self.data.next_12_hours.as_ref())
+    }
+
+    fn step(&self, units: Units, offset: UtcOffset) -> Option<MetStep> {
+        let datetime = self.datetime()?.to_offset(offset);
+        let details = &self.data.instant.details;
+        let temperature = details.air_temperature?;
+        let humidity = details.relative_humidity.unwrap_or_default();
+        let wind_speed = details.wind_speed.unwrap_or_default();
+
+        let period = self.period();
+        let symbol = period
+            .and_then(|p| p.summary.as_ref())
+            .map(|s| s.symbol_code.clone());
+        let precipitation = period
+            .and_then(|p| p.details.precipitation_amount)
+            .unwrap_or_default();
+        let pop = period
+            .and_then(|p| p.details.probability_of_precipitation)
+            .unwrap_or_default() / 100.0;
+        let is_snow = symbol.as_ref()
+            .map(|s| s.contains("snow") || s.contains("sleet"))
+            .unwrap_or(false);
+        let volume = self.data.next_1_hours.as_ref()
+            .and_then(|p| p.details.precipitation_amount)
+            .filter(|v| *v > 0.0);
+
+        let weather = Weather {
+            dt: datetime.unix_timestamp(),
+            sunrise: None,
+            sunset: None,
+            temp: temperature_in(units, temperature),
+            feels_like: temperature_in(units, apparent_temperature(temperature, humidity, wind_speed)),
+            pressure: details.air_pressure_at_sea_level.unwrap_or_default() as u32,
+            humidity: humidity as u32,
+            dew_point: temperature_in(units, details.dew_point_temperature.unwrap_or_default()),
+            uvi: details.ultraviolet_index_clear_sky.unwrap_or_default(),
+            clouds: details.cloud_area_fraction.unwrap_or_default(),
+            visibility: None,
+            wind_speed: speed_in(units, wind_speed),
+            wind_deg: details.wind_from_direction.unwrap_or_default() as u32,
+            wind_gust: details.wind_speed_of_gust.map(|g| speed_in(units, g)),
+            status: symbol.iter().map(|s| symbol_code_status(s)).collect(),
+            pop,
+            rain: if is_snow { None } else { volume },
+            snow: if is_snow { volume } else { None },
+        };
+
+        Some(MetStep {
+            dt: weather.dt,
+            date: datetime.date(),
+            hour: datetime.hour(),
+            weather,
+            precipitation,
+            symbol,
+        })
+    }
+}
+
+fn daily_weather(steps: &[MetStep], offset: UtcOffset) -> Vec<Weather<WeatherDayTemps>> {
+    let mut dates = steps.iter().map(|s| s.date).collect::<Vec<_>>();
+    dates.dedup();
+
+    dates.into_iter()
+        .filter_map(|date| {
+            let day = steps.iter()
+                .filter(|s| s.date == date)
+                .collect::<Vec<_>>();
+            let midday = day.iter()
+                .min_by_key(|s| (s.hour as i32 - 12).abs())?;
+            let max = day.iter().map(|s| s.weather.temp).fold(f64::MIN, f64::max);
+            let min = day.iter().map(|s| s.weather.temp).fold(f64::MAX, f64::min);
+            let feels_max = day.iter().map(|s| s.weather.feels_like).fold(f64::MIN, f64::max);
+            let feels_min = day.iter().map(|s| s.weather.feels_like).fold(f64::MAX, f64::min);
+            let at_hour = |hour: u8| day.iter().find(|s| s.hour == hour);
+
+            // Hourly steps are followed by 6 hourly steps further out, only
+            // count each hour of precipitation once
+            let mut precipitation = 0.0;
+            let mut covered_until = i64::MIN;
+            for step in day.iter() {
+                if step.dt >= covered_until {
+                    precipitation += step.precipitation;
+                    let hours = steps.iter()
+                        .find(|s| s.dt > step.dt)
+                        .map(|s| s.dt - step.dt)
+                        .unwrap_or(3600);
+                    covered_until = step.dt + hours;
+                }
+            }
+            let is_snow = midday.symbol.as_ref()
+                .map(|s| s.contains("snow") || s.contains("sleet"))
+                .unwrap_or(false);
+            let volume = Some(precipitation).filter(|v| *v > 0.0);
+            let dt = date.midnight().assume_offset(offset).unix_timestamp();
+
+            Some(Weather {
+                dt,
+                sunrise: None,
+                sunset: None,
+                temp: WeatherDayTemps {
+                    day: max,
+                    night: min,
+                    eve: at_hour(18).map(|s| s.weather.temp).unwrap_or(max),
+                    morn: at_hour(6).map(|s| s.weather.temp).unwrap_or(min),
+                },
+                feels_like: WeatherDayTemps {
+                    day: feels_max,
+                    night: feels_min,
+                    eve: at_hour(18).map(|s| s.weather.feels_like).unwrap_or(feels_max),
+                    morn: at_hour(6).map(|s| s.weather.feels_like).unwrap_or(feels_min),
+                },
+                pressure: midday.weather.pressure,
+                humidity: midday.weather.humidity,
+                dew_point: midday.weather.dew_point,
+                uvi: day.iter().map(|s| s.weather.uvi).fold(0.0, f64::max),
+                clouds: midday.weather.clouds,
+                visibility: None,
+                wind_speed: day.iter().map(|s| s.weather.wind_speed).fold(0.0, f64::max),
+                wind_deg: midday.weather.wind_deg,
+                wind_gust: day.iter().filter_map(|s| s.weather.wind_gust).reduce(f64::max),
+                status: midday.weather.status.clone(),
+                pop: day.iter().map(|s| s.weather.pop).fold(0.0, f64::max),
+                rain: if is_snow { None } else { volume },
+                snow: if is_snow { volume } else { None },
+            })
+        })
+        .collect()
+}
+
+impl MetForecast {
+    /// Days are split at the location's midnight, estimated from the longitude as MET only gives UTC times
+    fn into_weather_data(self, units: Units, lon: f64) -> Result<WeatherData, WeatherError> {
+        let offset = estimated_offset(lon);
+        let steps = self.properties.timeseries.iter()
+            .filter_map(|t| t.step(units, offset))
+            .collect::<Vec<_>>();
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        let current = steps.iter()
+            .rev()
+            .find(|s| s.dt <= now)
+            .or(steps.first())
+            .ok_or_else(|| WeatherError::Decode {
+                path: String::from("properties.timeseries"),
+                message: String::from("no forecast steps with a temperature"),
+            })?
+            .weather.clone();
+        let hourly = steps.iter()
+            .filter(|s| s.dt + 3600 > now)
+            .take(HOURLY_LIMIT)
+            .map(|s| s.weather.clone())
+            .collect();
+        let daily = daily_weather(&steps, offset);
+
+        Ok(WeatherData {
+            current,
+            hourly,
+            minutely: Vec::new(),
+            daily,
+            alerts: Vec::new(),
+            units: None,
+            provider: None,
+            provenance: Vec::new(),
+            utc_offset: None,
+        })
+    }
+}
+
+/// Last forecast downloaded, shared by every MetNorwayProvider so recreating the provider
+/// when preferences change still revalidates with If-Modified-Since as MET asks
+static FORECAST_CACHE: Lazy<Mutex<Option<MetCachedForecast>>> = Lazy::new(|| Mutex::new(None));
+
+#[derive(Default)]
+pub struct MetNorwayProvider;
+
+impl MetNorwayProvider {
+    pub fn new() -> Self {
+        MetNorwayProvider
+    }
+
+    fn base_url(&self) -> String {
+        format!("{}/{}", 
+            MET_API_URL, 
+            MET_API_VERSION)
+    }
+
+    /// Returns the cached body if it is for the same location and has not expired yet,
+    /// otherwise the Last-Modified value to revalidate it with
+    fn cached(&self, lat: &str, lon: &str) -> (Option<String>, Option<String>) {
+        if let Ok(cache) = FORECAST_CACHE.lock() {
+            if let Some(cached) = cache.as_ref() {
+                if cached.lat == lat && cached.lon == lon {
+                    let fresh = cached.expires
+                        .map(|expires| expires > OffsetDateTime::now_utc())
+                        .unwrap_or(false);
+                    if fresh {
+                        return (Some(cached.body.clone()), None);
+                    }
+                    return (None, cached.last_modified.clone());
+                }
+            }
+        }
+        (None, None)
+    }
+
+    async fn get_body(&self, lat: &str, lon: &str) -> Result<String, WeatherError> {
+        let (fresh, last_modified) = self.cached(lat, lon);
+        if let Some(body) = fresh {
+            return Ok(body);
+        }
+
+        let url = format!("{}/complete?lat={}&lon={}",
+            self.base_url(),
+            lat, lon);
+        let user_agent = user_agent();
+        let mut headers = vec![("User-Agent", user_agent.as_str())];
+        if let Some(last_modified) = &last_modified {
+            headers.push(("If-Modified-Since", last_modified.as_str()));
+        }
+        let response = HttpClient::shared().get(&url, &headers).await?;
+        check_status(&response)?;
+
+        let expires = response.headers().get("Expires")
+            .and_then(|v| v.to_str().ok())
+            .and_then(parse_http_date);
+
+        if response.status() == http::StatusCode::NOT_MODIFIED {
+            if let Ok(mut cache) = FORECAST_CACHE.lock() {
+                if let Some(cached) = cache.as_mut() {
+                    cached.expires = expires;
+                    return Ok(cached.body.clone());
+                }
+            }
+            return Err(WeatherError::HttpStatus(response.status().as_u16()));
+        }
+
+        let last_modified = response.headers().get("Last-Modified")
+            .and_then(|v| v.to_str().ok())
+            .map(String::from);
+        let body = response.into_body();
+
+        if let Ok(mut cache) = FORECAST_CACHE.lock() {
+            *cache = Some(MetCachedForecast {
+                lat: String::from(lat),
+                lon: String::from(lon),
+                last_modified,
+                expires,
+                body: body.clone(),
+            });
+        }
+
+        Ok(body)
+    }
+
+    async fn get_forecast(&self, units: Units, lat: f64, lon: f64) -> Result<WeatherData, WeatherError> {
+        // MET rejects coordinates with more than 4 decimals
+        let body = self.get_body(&format!("{:.4}", lat), &format!("{:.4}", lon)).await?;
+        let forecast: MetForecast = decode(&body)?;
+
+        forecast.into_weather_data(units, lon)
+    }
+}
+
+impl WeatherProvider for MetNorwayProvider {
+    fn name(&self) -> &str {
+        "MET Norway"
+    }
+
+    fn fetch<'a>(&'a self, units: Units, lat: f64, lon: f64) -> WeatherFuture<'a> {
+        Box::pin(self.get_forecast(units, lat, lon))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_symbol_codes() {
+        let cases = [
+            ("clearsky_day", "01d", "Clear", "clear sky"),
+            ("clearsky_night", "01n", "Clear", "clear sky"),
+            ("clearsky_polartwilight", "01d", "Clear", "clear sky"),
+            ("fair_day", "02d", "Clouds", "fair"),
+            ("partlycloudy_night", "03n", "Clouds", "partly cloudy"),
+            ("cloudy", "04d", "Clouds", "cloudy"),
+            ("fog", "50d", "Fog", "fog"),
+            ("lightrainshowers_day", "09d", "Rain", "light rain showers"),
+            ("heavyrain", "10d", "Rain", "heavy rain"),
+            ("sleet", "13d", "Snow", "sleet"),
+            ("heavysnowshowers_night", "13n", "Snow", "heavy snow showers"),
+            ("heavysleetshowersandthunder_night", "11n", "Thunderstorm", "heavy sleet showers and thunder"),
+            ("rainandthunder", "11d", "Thunderstorm", "rain and thunder"),
+            ("somethingnew_day", "unknown", "Unknown", "unknown"),
+        ];
+        for (code, icon, main, description) in cases.iter() {
+            let status = symbol_code_status(code);
+            assert_eq!((status.icon.as_str(), status.main.as_str(), status.description.as_str()),
+                (*icon, *main, *description), "{}", code);
+        }
+    }
+
+    #[test]
+    fn describes_symbols() {
+        assert_eq!(describe_symbol("lightsleetshowersandthunder"), "light sleet showers and thunder");
+        assert_eq!(describe_symbol("partlycloudy"), "partly cloudy");
+        // Anything not recognised is kept as it is
+        assert_eq!(describe_symbol("heavyhail"), "heavy hail");
+        assert_eq!(describe_symbol(""), "");
+    }
+
+    fn steps(offset: UtcOffset) -> Vec<MetStep> {
+        let timeseries: Vec<MetTimeseries> = serde_json::from_str(r#"[
+            {"time": "2024-06-01T04:00:00Z", "data": {
+                "instant": {"details": {"air_temperature": 10.0, "wind_speed": 2.0}},
+                "next_1_hours": {"summary": {"symbol_code": "clearsky_day"},
+                    "details": {"precipitation_amount": 0.0, "probability_of_precipitation": 5.0}}}},
+            {"time": "2024-06-01T10:00:00Z", "data": {
+                "instant": {"details": {"air_temperature": 18.0, "wind_speed": 6.0, "wind_speed_of_gust": 11.0}},
+                "next_1_hours": {"summary": {"symbol_code": "partlycloudy_day"},
+                    "details": {"precipitation_amount": 0.5, "probability_of_precipitation": 40.0}}}},
+            {"time": "2024-06-01T13:00:00Z", "data": {"instant": {"details": {}}}},
+            {"time": "2024-06-01T16:00:00Z", "data": {
+                "instant": {"details": {"air_temperature": 15.0, "wind_speed": 4.0}},
+                "next_6_hours": {"summary": {"symbol_code": "rain_day"},
+                    "details": {"precipitation_amount": 2.0}}}},
+            {"time": "2024-06-01T22:00:00Z", "data": {
+                "instant": {"details": {"air_temperature": 1.0}},
+                "next_6_hours": {"summary": {"symbol_code": "lightsnow_night"},
+                    "details": {"precipitation_amount": 0.3}}}}
+        ]"#).unwrap();
+
+        timeseries.iter()
+            .filter_map(|t| t.step(Units::Metric, offset))
+            .collect()
+    }
+
+    #[test]
+    fn splits_days_at_location_midnight() {
+        let offset = UtcOffset::from_hms(2, 0, 0).unwrap();
+        let steps = steps(offset);
+        // The step without a temperature is left out
+        assert_eq!(steps.len(), 4);
+
+        let daily = daily_weather(&steps, offset);
+        assert_eq!(daily.len(), 2);
+
+        let first = &daily[0];
+        // Midnight on 1 June at UTC+2
+        assert_eq!(first.dt, 1_717_192_800);
+        assert_eq!(first.temp.day, 18.0);
+        assert_eq!(first.temp.night, 10.0);
+        assert_eq!(first.temp.morn, 10.0);
+        assert_eq!(first.temp.eve, 15.0);
+        assert_eq!(first.status[0].icon, "03d");
+        assert_eq!(first.rain, Some(2.5));
+        assert_eq!(first.snow, None);
+        assert_eq!(first.pop, 0.4);
+        assert_eq!(first.wind_speed, 6.0);
+        assert_eq!(first.wind_gust, Some(11.0));
+
+        // 22:00 UTC is already the next day
+        let second = &daily[1];
+        assert_eq!(second.dt, 1_717_192_800 + 86400);
+        assert_eq!(second.temp.day, 1.0);
+        assert_eq!(second.temp.morn, 1.0);
+        assert_eq!(second.rain, None);
+        assert_eq!(second.snow, Some(0.3));
+    }
+
+    #[test]
+    fn splits_days_in_utc() {
+        let daily = daily_weather(&steps(UtcOffset::UTC), UtcOffset::UTC);
+        assert_eq!(daily.len(), 1);
+        assert_eq!(daily[0].dt, 1_717_200_000);
+        assert_eq!(daily[0].temp.night, 1.0);
+    }
+}
@@ -0,0 +1,543 @@
+use std::sync::Mutex;
+use serde::Deserialize;
+use time::{
+    OffsetDateTime,
+    Date,
+};
+use time::format_description::well_known::Rfc3339;
+use crate::api::units::Units;
+use crate::api::weather::{
+    Weather,
+    WeatherData,
+    WeatherDayTemps,
+    WeatherStatus,
+    WeatherAlert,
+};
+use crate::api::error::WeatherError;
+use crate::api::client::HttpClient;
+use super::{
+    WeatherProvider,
+    WeatherFuture,
+    apparent_temperature,
+    temperature_in,
+    speed_in,
+    user_agent,
+};
+
+const NWS_API_URL: &str = "https://api.weather.gov";
+
+/// Number of hours shown in the hourly view, matching OpenWeather's One Call
+const HOURLY_LIMIT: usize = 48;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NwsPointProperties {
+    forecast: String,
+    forecast_hourly: String,
+}
+
+#[derive(Deserialize)]
+struct NwsPoint {
+    properties: NwsPointProperties,
+}
+
+#[derive(Deserialize, Default)]
+struct NwsValue {
+    value: Option<f64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NwsPeriod {
+    start_time: String,
+    is_daytime: bool,
+    temperature: f64,
+    #[serde(default)]
+    temperature_unit: Option<String>,
+    #[serde(default)]
+    wind_speed: Option<String>,
+    #[serde(default)]
+    wind_direction: Option<String>,
+    #[serde(default)]
+    icon: Option<String>,
+    short_forecast: String,
+    #[serde(default)]
+    probability_of_precipitation: NwsValue,
+    #[serde(default)]
+    dewpoint: NwsValue,
+    #[serde(default)]
+    relative_humidity: NwsValue,
+}
+
+#[derive(Deserialize)]
+struct NwsForecastProperties {
+    periods: Vec<NwsPeriod>,
+}
+
+#[derive(Deserialize)]
+struct NwsForecast {
+    properties: NwsForecastProperties,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NwsAlertProperties {
+    event: String,
+    #[serde(default)]
+    sender_name: Option<String>,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    instruction: Option<String>,
+    #[serde(default)]
+    severity: Option<String>,
+    #[serde(default)]
+    urgency: Option<String>,
+    #[serde(default)]
+    area_desc: Option<String>,
+    #[serde(default)]
+    onset: Option<String>,
+    #[serde(default)]
+    effective: Option<String>,
+    #[serde(default)]
+    ends: Option<String>,
+    #[serde(default)]
+    expires: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct NwsAlertFeature {
+    properties: NwsAlertProperties,
+}
+
+#[derive(Deserialize)]
+struct NwsAlerts {
+    features: Vec<NwsAlertFeature>,
+}
+
+struct NwsCachedPoint {
+    lat: String,
+    lon: String,
+    properties: NwsPointProperties,
+}
+
+fn parse_time(value: &str) -> Option<OffsetDateTime> {
+    OffsetDateTime::parse(value, &Rfc3339).ok()
+}
+
+/// Maps the condition in an NWS icon url (e.g. /icons/land/day/tsra_sct,40) onto the icon set in icons/
+pub fn icon_status(icon: Option<&str>, short_forecast: &str) -> WeatherStatus {
+    let path = icon
+        .map(|i| i.split('?').next().unwrap_or(""))
+        .unwrap_or("");
+    // Periods with two conditions (e.g. /icons/land/night/rain,30/tsra,60) show the later one
+    let condition = path.rsplit('/').next()
+        .and_then(|c| c.split(',').next())
+        .unwrap_or("");
+    let variant = if path.split('/').any(|s| s == "night") {
+        "n"
+    } else {
+        "d"
+    };
+
+    let (code, main) = match condition {
+        "skc" | "hot" | "cold" => ("01", "Clear"),
+        "few" | "wind_skc" | "wind_few" => ("02", "Clouds"),
+        "sct" | "wind_sct" => ("03", "Clouds"),
+        "bkn" | "ovc" | "wind_bkn" | "wind_ovc" => ("04", "Clouds"),
+        "rain_showers" | "rain_showers_hi" => ("09", "Rain"),
+        "rain" | "fzra" | "rain_fzra" => ("10", "Rain"),
+        "tsra" | "tsra_sct" | "tsra_hi" | "tornado" | "hurricane" | "tropical_storm" => ("11", "Thunderstorm"),
+        "snow" | "rain_snow" | "rain_sleet" | "snow_sleet" | "snow_fzra" | "sleet" | "blizzard" => ("13", "Snow"),
+        "fog" | "dust" | "smoke" | "haze" => ("50", "Fog"),
+        _ => {
+            return WeatherStatus {
+                main: String::from("Unknown"),
+                description: String::from(short_forecast),
+                icon: String::from("unknown"),
+            };
+        }
+    };
+
+    WeatherStatus {
+        main: String::from(main),
+        description: String::from(short_forecast),
+        icon: format!("{}{}", code, variant),
+    }
+}
+
+/// Wind speeds are reported as text such as "10 km/h" or "5 to 10 mph", take the upper value in km/h
+fn parse_wind_speed(value: Option<&str>) -> Option<f64> {
+    let value = value?;
+    let speed = value.split_whitespace()
+        .filter_map(|word| word.parse::<f64>().ok())
+        .reduce(f64::max)?;
+
+    // Forecasts are requested in SI units, but not every period honours it
+    Some(if value.trim_end().ends_with("mph") {
+        speed * 1.609_344
+    } else {
+        speed
+    })
+}
+
+fn compass_degrees(direction: Option<&str>) -> u32 {
+    const POINTS: &[&str] = &[
+        "N", "NNE", "NE", "ENE", "E", "ESE", "SE", "SSE",
+        "S", "SSW", "SW", "WSW", "W", "WNW", "NW", "NNW",
+    ];
+
+    direction
+        .and_then(|d| POINTS.iter().position(|p| *p == d))
+        .map(|i| (i as f64 * 22.5) as u32)
+        .unwrap_or(0)
+}
+
+struct NwsStep {
+    date: Date,
+    hour: u8,
+    is_daytime: bool,
+    weather: Weather<f64>,
+}
+
+impl NwsPeriod {
+    fn step(&self, units: Units) -> Option<NwsStep> {
+        let start = parse_time(&self.start_time)?;
+        // Forecasts are requested in SI units, though only temperatures say so
+        let celsius = match self.temperature_unit.as_deref() {
+            Some("F") => (self.temperature - 32.0) * 5.0 / 9.0,
+            _ => self.temperature,
+        };
+        let metres_per_second = parse_wind_speed(self.wind_speed.as_deref())
+            .map(|kmh| kmh / 3.6)
+            .unwrap_or_default();
+        let humidity = self.relative_humidity.value.unwrap_or_default();
+
+        let weather = Weather {
+            dt: start.unix_timestamp(),
+            sunrise: None,
+            sunset: None,
+            temp: temperature_in(units, celsius),
+            feels_like: temperature_in(units, apparent_temperature(celsius, humidity, metres_per_second)),
+            pressure: 0,
+            humidity: humidity as u32,
+            dew_point: temperature_in(units, self.dewpoint.value.unwrap_or_default()),
+            uvi: 0.0,
+            clouds: 0.0,
+            visibility: None,
+            wind_speed: speed_in(units, metres_per_second),
+            wind_deg: compass_degrees(self.wind_direction.as_deref()),
+            wind_gust: None,
+            status: vec![icon_status(self.icon.as_deref(), &self.short_forecast)],
+            pop: self.probability_of_precipitation.value.unwrap_or_default() / 100.0,
+            rain: None,
+            snow: None,
+        };
+
+        Some(NwsStep {
+            date: start.date(),
+            hour: start.hour(),
+            is_daytime: self.is_daytime,
+            weather,
+        })
+    }
+}
+
+/// Daily forecasts are split into day and night periods, pair them up per date
+fn daily_weather(periods: &[NwsStep], hourly: &[NwsStep]) -> Vec<Weather<WeatherDayTemps>> {
+    let mut dates = periods.iter().map(|p| p.date).collect::<Vec<_>>();
+    dates.dedup();
+
+    dates.into_iter()
+        .filter_map(|date| {
+            let day = periods.iter().find(|p| p.date == date && p.is_daytime);
+            let night = periods.iter().find(|p| p.date == date && !p.is_daytime);
+            let main = day.or(night)?;
+            let at_hour = |hour: u8| hourly.iter()
+                .find(|h| h.date == date && h.hour == hour)
+                .map(|h| &h.weather);
+
+            let day_temp = day.map(|d| d.weather.temp).unwrap_or(main.weather.temp);
+            let night_temp = night.map(|n| n.weather.temp).unwrap_or(main.weather.temp);
+            let day_feels = day.map(|d| d.weather.feels_like).unwrap_or(main.weather.feels_like);
+            let night_feels = night.map(|n| n.weather.feels_like).unwrap_or(main.weather.feels_like);
+            let pop = day.iter().chain(night.iter())
+                .map(|p| p.weather.pop)
+                .fold(0.0, f64::max);
+
+            Some(Weather {
+                dt: main.weather.dt,
+                sunrise: None,
+                sunset: None,
+                temp: WeatherDayTemps {
+                    day: day_temp,
+                    night: night_temp,
+                    eve: at_hour(18).map(|h| h.temp).unwrap_or(day_temp),
+                    morn: at_hour(6).map(|h| h.temp).unwrap_or(night_temp),
+                },
+                feels_like: WeatherDayTemps {
+                    day: day_feels,
+                    night: night_feels,
+                    eve: at_hour(18).map(|h| h.feels_like).unwrap_or(day_feels),
+                    morn: at_hour(6).map(|h| h.feels_like).unwrap_or(night_feels),
+                },
+                pressure: 0,
+                humidity: main.weather.humidity,
+                dew_point: main.weather.dew_point,
+                uvi: 0.0,
+                clouds: 0.0,
+                visibility: None,
+                wind_speed: main.weather.wind_speed,
+                wind_deg: main.weather.wind_deg,
+                wind_gust: None,
+                status: main.weather.status.clone(),
+                pop,
+                rain: None,
+                snow: None,
+            })
+        })
+        .collect()
+}
+
+impl From<NwsAlertProperties> for WeatherAlert {
+    fn from(alert: NwsAlertProperties) -> Self {
+        let start = alert.onset.as_deref()
+            .or(alert.effective.as_deref())
+            .and_then(parse_time)
+            .map(|t| t.unix_timestamp())
+            .unwrap_or(0);
+        let end = alert.ends.as_deref()
+            .or(alert.expires.as_deref())
+            .and_then(parse_time)
+            .map(|t| t.unix_timestamp())
+            .unwrap_or(start);
+        let mut description = alert.description.unwrap_or_default();
+        if let Some(instruction) = alert.instruction {
+            description = format!("{}\n\n{}", description, instruction);
+        }
+
+        WeatherAlert {
+            start,
+            end,
+            sender_name: alert.sender_name
+                .unwrap_or_else(|| String::from("National Weather Service")),
+            event: alert.event,
+            description,
+            severity: alert.severity,
+            urgency: alert.urgency,
+            area: alert.area_desc,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct NwsProvider {
+    points: Mutex<Option<NwsCachedPoint>>,
+}
+
+impl NwsProvider {
+    pub fn new() -> Self {
+        NwsProvider {
+            points: Mutex::new(None),
+        }
+    }
+
+    async fn get_json<T: serde::de::DeserializeOwned>(&self, url: &str) -> Result<T, WeatherError> {
+        let user_agent = user_agent();
+        let headers = [
+            ("User-Agent", user_agent.as_str()),
+            ("Accept", "application/geo+json"),
+        ];
+
+        HttpClient::shared().get_json(url, &headers).await
+    }
+
+    /// Resolves the gridpoint forecast urls for a location, these do not change so are kept
+    async fn get_point(&self, lat: &str, lon: &str) -> Result<(String, String), WeatherError> {
+        if let Ok(points) = self.points.lock() {
+            if let Some(cached) = points.as_ref() {
+                if cached.lat == lat && cached.lon == lon {
+                    return Ok((
+                        cached.properties.forecast.clone(),
+                        cached.properties.forecast_hourly.clone()));
+                }
+            }
+        }
+
+        let url = format!("{}/points/{},{}", NWS_API_URL, lat, lon);
+        let point: NwsPoint = self.get_json(&url).await?;
+        let urls = (
+            point.properties.forecast.clone(),
+            point.properties.forecast_hourly.clone());
+
+        if let Ok(mut points) = self.points.lock() {
+            *points = Some(NwsCachedPoint {
+                lat: String::from(lat),
+                lon: String::from(lon),
+                properties: point.properties,
+            });
+        }
+
+        Ok(urls)
+    }
+
+    async fn get_alerts(&self, lat: &str, lon: &str) -> Vec<WeatherAlert> {
+        let url = format!("{}/alerts/active?point={},{}", NWS_API_URL, lat, lon);
+        // Alerts are supplementary, the forecast is still shown without them
+        match self.get_json::<NwsAlerts>(&url).await {
+            Ok(alerts) => alerts.features.into_iter()
+                .map(|f| WeatherAlert::from(f.properties))
+                .collect(),
+            Err(err) => {
+                println!("NWS: unable to get alerts: {}", err);
+                Vec::new()
+            }
+        }
+    }
+
+    async fn get_forecast(&self, units: Units, lat: f64, lon: f64) -> Result<WeatherData, WeatherError> {
+        // NWS redirects coordinates with more than 4 decimals
+        let lat = format!("{:.4}", lat);
+        let lon = format!("{:.4}", lon);
+
+        let (forecast_url, hourly_url) = self.get_point(&lat, &lon).await?;
+        let forecast: NwsForecast = self.get_json(&format!("{}?units=si", forecast_url)).await?;
+        let hourly: NwsForecast = self.get_json(&format!("{}?units=si", hourly_url)).await?;
+        let alerts = self.get_alerts(&lat, &lon).await;
+
+        let hourly = hourly.properties.periods.iter()
+            .filter_map(|p| p.step(units))
+            .collect::<Vec<_>>();
+        let periods = forecast.properties.periods.iter()
+            .filter_map(|p| p.step(units))
+            .collect::<Vec<_>>();
+        let current = hourly.first()
+            .ok_or_else(|| WeatherError::Decode {
+                path: String::from("properties.periods"),
+                message: String::from("no hourly forecast periods"),
+            })?
+            .weather.clone();
+
+        Ok(WeatherData {
+            current,
+            daily: daily_weather(&periods, &hourly),
+            hourly: hourly.iter()
+                .take(HOURLY_LIMIT)
+                .map(|h| h.weather.clone())
+                .collect(),
+            minutely: Vec::new(),
+            alerts,
+            units: None,
+            provider: None,
+            provenance: Vec::new(),
+            // Period times are given in the location's time zone
+            utc_offset: forecast.properties.periods.first()
+                .and_then(|p| parse_time(&p.start_time))
+                .map(|start| start.offset().whole_seconds()),
+        })
+    }
+}
+
+impl WeatherProvider for NwsProvider {
+    fn name(&self) -> &str {
+        "US National Weather Service"
+    }
+
+    fn fetch<'a>(&'a self, units: Units, lat: f64, lon: f64) -> WeatherFuture<'a> {
+        Box::pin(self.get_forecast(units, lat, lon))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_icons() {
+        let cases = [
+            (Some("https://api.weather.gov/icons/land/day/tsra_sct,40?size=medium"), "11d", "Thunderstorm"),
+            (Some("https://api.weather.gov/icons/land/night/few?size=small"), "02n", "Clouds"),
+            (Some("https://api.weather.gov/icons/land/day/skc"), "01d", "Clear"),
+            (Some("https://api.weather.gov/icons/land/night/rain_showers,30/tsra,60?size=medium"), "11n", "Thunderstorm"),
+            (Some("https://api.weather.gov/icons/land/day/snow,50/rain_snow,70"), "13d", "Snow"),
+            (Some("https://api.weather.gov/icons/land/night/fog"), "50n", "Fog"),
+            (Some("https://api.weather.gov/icons/land/day/wind_bkn"), "04d", "Clouds"),
+            (Some("https://api.weather.gov/icons/land/day/volcano"), "unknown", "Unknown"),
+            (None, "unknown", "Unknown"),
+        ];
+        for (icon, expected_icon, expected_main) in cases.iter() {
+            let status = icon_status(*icon, "Chance Showers");
+            assert_eq!((status.icon.as_str(), status.main.as_str()), (*expected_icon, *expected_main), "{:?}", icon);
+            assert_eq!(status.description, "Chance Showers");
+        }
+    }
+
+    #[test]
+    fn parses_wind_speeds() {
+        assert_eq!(parse_wind_speed(Some("20 km/h")), Some(20.0));
+        assert_eq!(parse_wind_speed(Some("5 to 10 km/h")), Some(10.0));
+        assert!((parse_wind_speed(Some("10 to 15 mph")).unwrap() - 24.140_16).abs() < 1e-9);
+        assert_eq!(parse_wind_speed(Some("Calm")), None);
+        assert_eq!(parse_wind_speed(None), None);
+    }
+
+    #[test]
+    fn converts_compass_points() {
+        let cases = [
+            (Some("N"), 0),
+            (Some("NNE"), 22),
+            (Some("E"), 90),
+            (Some("SW"), 225),
+            (Some("NNW"), 337),
+            (Some("nnw"), 0),
+            (None, 0),
+        ];
+        for (direction, degrees) in cases.iter() {
+            assert_eq!(compass_degrees(*direction), *degrees, "{:?}", direction);
+        }
+    }
+
+    #[test]
+    fn converts_alerts() {
+        let properties: NwsAlertProperties = serde_json::from_str(r#"{
+            "event": "Severe Thunderstorm Warning",
+            "senderName": "NWS Norman OK",
+            "description": "At 512 PM CDT, a severe thunderstorm was located near Moore.",
+            "instruction": "Move to an interior room on the lowest floor of a building.",
+            "severity": "Severe",
+            "urgency": "Immediate",
+            "areaDesc": "Cleveland, OK; McClain, OK",
+            "effective": "2024-05-06T17:12:00-05:00",
+            "onset": "2024-05-06T17:15:00-05:00",
+            "expires": "2024-05-06T18:00:00-05:00",
+            "ends": "2024-05-06T17:45:00-05:00"
+        }"#).unwrap();
+        let alert = WeatherAlert::from(properties);
+
+        assert_eq!(alert.start, 1_715_033_700);
+        assert_eq!(alert.end, 1_715_035_500);
+        assert_eq!(alert.sender_name, "NWS Norman OK");
+        assert_eq!(alert.event, "Severe Thunderstorm Warning");
+        assert_eq!(alert.description, "At 512 PM CDT, a severe thunderstorm was located near Moore.\n\n\
+            Move to an interior room on the lowest floor of a building.");
+        assert_eq!(alert.severity.as_deref(), Some("Severe"));
+        assert_eq!(alert.urgency.as_deref(), Some("Immediate"));
+        assert_eq!(alert.area.as_deref(), Some("Cleveland, OK; McClain, OK"));
+    }
+
+    #[test]
+    fn fills_in_missing_alert_fields() {
+        let properties: NwsAlertProperties = serde_json::from_str(r#"{
+            "event": "Heat Advisory",
+            "effective": "2024-07-01T10:00:00Z"
+        }"#).unwrap();
+        let alert = WeatherAlert::from(properties);
+
+        // Without onset or ends, the effective time is used for both
+        assert_eq!(alert.start, 1_719_828_000);
+        assert_eq!(alert.end, 1_719_828_000);
+        assert_eq!(alert.sender_name, "National Weather Service");
+        assert_eq!(alert.description, "");
+        assert_eq!(alert.severity, None);
+    }
+}
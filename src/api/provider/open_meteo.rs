@@ -0,0 +1,467 @@
+use std::collections::HashMap;
+use serde::Deserialize;
+use crate::api::units::Units;
+use crate::api::weather::{
+    Weather,
+    WeatherData,
+    WeatherDayTemps,
+    WeatherStatus,
+    WeatherMinutely,
+};
+use crate::api::error::WeatherError;
+use crate::api::client::HttpClient;
+use super::{
+    WeatherProvider,
+    WeatherFuture,
+};
+
+const OPEN_METEO_API_URL: &str = "https://api.open-meteo.com";
+const OPEN_METEO_API_VERSION: &str = "v1";
+
+const HOURLY_VARIABLES: &str = "temperature_2m,apparent_temperature,relative_humidity_2m,dew_point_2m,\
+pressure_msl,cloud_cover,visibility,wind_speed_10m,wind_direction_10m,wind_gusts_10m,\
+precipitation_probability,rain,snowfall,weather_code,uv_index,is_day";
+const CURRENT_VARIABLES: &str = "temperature_2m,apparent_temperature,relative_humidity_2m,dew_point_2m,\
+pressure_msl,cloud_cover,wind_speed_10m,wind_direction_10m,wind_gusts_10m,\
+rain,snowfall,weather_code,uv_index,is_day";
+const DAILY_VARIABLES: &str = "weather_code,temperature_2m_max,temperature_2m_min,\
+apparent_temperature_max,apparent_temperature_min,sunrise,sunset,uv_index_max,\
+precipitation_probability_max,rain_sum,snowfall_sum,wind_speed_10m_max,wind_gusts_10m_max,\
+wind_direction_10m_dominant";
+
+/// Number of hours shown in the hourly view, matching OpenWeather's One Call
+const HOURLY_LIMIT: usize = 48;
+
+#[derive(Deserialize)]
+struct OpenMeteoCurrent {
+    time: i64,
+    temperature_2m: f64,
+    apparent_temperature: f64,
+    relative_humidity_2m: f64,
+    dew_point_2m: f64,
+    pressure_msl: f64,
+    cloud_cover: f64,
+    wind_speed_10m: f64,
+    wind_direction_10m: f64,
+    #[serde(default)]
+    wind_gusts_10m: Option<f64>,
+    #[serde(default)]
+    rain: Option<f64>,
+    #[serde(default)]
+    snowfall: Option<f64>,
+    weather_code: u32,
+    #[serde(default)]
+    uv_index: Option<f64>,
+    is_day: u8,
+}
+
+#[derive(Deserialize)]
+struct OpenMeteoHourly {
+    time: Vec<i64>,
+    temperature_2m: Vec<Option<f64>>,
+    apparent_temperature: Vec<Option<f64>>,
+    relative_humidity_2m: Vec<Option<f64>>,
+    dew_point_2m: Vec<Option<f64>>,
+    pressure_msl: Vec<Option<f64>>,
+    cloud_cover: Vec<Option<f64>>,
+    visibility: Vec<Option<f64>>,
+    wind_speed_10m: Vec<Option<f64>>,
+    wind_direction_10m: Vec<Option<f64>>,
+    wind_gusts_10m: Vec<Option<f64>>,
+    precipitation_probability: Vec<Option<f64>>,
+    rain: Vec<Option<f64>>,
+    snowfall: Vec<Option<f64>>,
+    weather_code: Vec<Option<u32>>,
+    uv_index: Vec<Option<f64>>,
+    is_day: Vec<Option<u8>>,
+}
+
+#[derive(Deserialize)]
+struct OpenMeteoDaily {
+    time: Vec<i64>,
+    weather_code: Vec<Option<u32>>,
+    temperature_2m_max: Vec<Option<f64>>,
+    temperature_2m_min: Vec<Option<f64>>,
+    apparent_temperature_max: Vec<Option<f64>>,
+    apparent_temperature_min: Vec<Option<f64>>,
+    sunrise: Vec<Option<i64>>,
+    sunset: Vec<Option<i64>>,
+    uv_index_max: Vec<Option<f64>>,
+    precipitation_probability_max: Vec<Option<f64>>,
+    rain_sum: Vec<Option<f64>>,
+    snowfall_sum: Vec<Option<f64>>,
+    wind_speed_10m_max: Vec<Option<f64>>,
+    wind_gusts_10m_max: Vec<Option<f64>>,
+    wind_direction_10m_dominant: Vec<Option<f64>>,
+}
+
+#[derive(Deserialize)]
+struct OpenMeteoMinutely {
+    time: Vec<i64>,
+    precipitation: Vec<Option<f64>>,
+}
+
+#[derive(Deserialize)]
+struct OpenMeteoForecast {
+    current: OpenMeteoCurrent,
+    hourly: OpenMeteoHourly,
+    daily: OpenMeteoDaily,
+    #[serde(default)]
+    minutely_15: Option<OpenMeteoMinutely>,
+    #[serde(default)]
+    utc_offset_seconds: Option<i32>,
+}
+
+fn at<T: Copy + Default>(values: &[Option<T>], index: usize) -> T {
+    values.get(index).copied().flatten().unwrap_or_default()
+}
+
+fn at_opt<T: Copy>(values: &[Option<T>], index: usize) -> Option<T> {
+    values.get(index).copied().flatten()
+}
+
+/// Only report precipitation volumes when some actually fell, as OpenWeather does
+fn volume(value: Option<f64>) -> Option<f64> {
+    value.filter(|v| *v > 0.0)
+}
+
+/// Open-Meteo reports snowfall in cm of snow, 7 cm being about 10 mm of water
+pub fn snowfall_water(snowfall: Option<f64>) -> Option<f64> {
+    snowfall.map(|cm| cm * 10.0 / 7.0)
+}
+
+/// Maps WMO weather interpretation codes onto the OpenWeather icon set in icons/
+pub fn weather_code_status(code: u32, is_day: bool) -> WeatherStatus {
+    let (icon, main, description) = match code {
+        0 => ("01", "Clear", "clear sky"),
+        1 => ("02", "Clouds", "mainly clear"),
+        2 => ("03", "Clouds", "partly cloudy"),
+        3 => ("04", "Clouds", "overcast"),
+        45 | 48 => ("50", "Fog", "fog"),
+        51 | 53 | 55 => ("09", "Drizzle", "drizzle"),
+        56 | 57 => ("09", "Drizzle", "freezing drizzle"),
+        61 | 63 | 65 => ("10", "Rain", "rain"),
+        66 | 67 => ("10", "Rain", "freezing rain"),
+        71 | 73 | 75 => ("13", "Snow", "snow"),
+        77 => ("13", "Snow", "snow grains"),
+        80 | 81 | 82 => ("09", "Rain", "rain showers"),
+        85 | 86 => ("13", "Snow", "snow showers"),
+        95 => ("11", "Thunderstorm", "thunderstorm"),
+        96 | 99 => ("11", "Thunderstorm", "thunderstorm with hail"),
+        _ => {
+            return WeatherStatus {
+                main: String::from("Unknown"),
+                description: String::from("unknown"),
+                icon: String::from("unknown"),
+            };
+        }
+    };
+
+    WeatherStatus {
+        main: String::from(main),
+        description: String::from(description),
+        icon: format!("{}{}", icon, if is_day { "d" } else { "n" }),
+    }
+}
+
+impl OpenMeteoHourly {
+    fn weather(&self, index: usize) -> Weather<f64> {
+        Weather {
+            dt: self.time[index],
+            sunrise: None,
+            sunset: None,
+            temp: at(&self.temperature_2m, index),
+            feels_like: at(&self.apparent_temperature, index),
+            pressure: at(&self.pressure_msl, index) as u32,
+            humidity: at(&self.relative_humidity_2m, index) as u32,
+            dew_point: at(&self.dew_point_2m, index),
+            uvi: at(&self.uv_index, index),
+            clouds: at(&self.cloud_cover, index),
+            visibility: at_opt(&self.visibility, index).map(|v| v as u32),
+            wind_speed: at(&self.wind_speed_10m, index),
+            wind_deg: at(&self.wind_direction_10m, index) as u32,
+            wind_gust: at_opt(&self.wind_gusts_10m, index),
+            status: vec![weather_code_status(
+                at(&self.weather_code, index),
+                at(&self.is_day, index) == 1)],
+            pop: at(&self.precipitation_probability, index) / 100.0,
+            rain: volume(at_opt(&self.rain, index)),
+            snow: volume(snowfall_water(at_opt(&self.snowfall, index))),
+        }
+    }
+}
+
+impl OpenMeteoForecast {
+    fn current_weather(&self) -> Weather<f64> {
+        let current = &self.current;
+        let hour = self.hourly.time.iter()
+            .rposition(|t| *t <= current.time);
+        let (visibility, pop) = match hour {
+            Some(hour) => (
+                at_opt(&self.hourly.visibility, hour).map(|v| v as u32),
+                at(&self.hourly.precipitation_probability, hour) / 100.0,
+            ),
+            None => (None, 0.0),
+        };
+
+        Weather {
+            dt: current.time,
+            sunrise: at_opt(&self.daily.sunrise, 0),
+            sunset: at_opt(&self.daily.sunset, 0),
+            temp: current.temperature_2m,
+            feels_like: current.apparent_temperature,
+            pressure: current.pressure_msl as u32,
+            humidity: current.relative_humidity_2m as u32,
+            dew_point: current.dew_point_2m,
+            uvi: current.uv_index.unwrap_or_default(),
+            clouds: current.cloud_cover,
+            visibility,
+            wind_speed: current.wind_speed_10m,
+            wind_deg: current.wind_direction_10m as u32,
+            wind_gust: current.wind_gusts_10m,
+            status: vec![weather_code_status(current.weather_code, current.is_day == 1)],
+            pop,
+            rain: volume(current.rain),
+            snow: volume(snowfall_water(current.snowfall)),
+        }
+    }
+
+    fn hourly_weather(&self) -> Vec<Weather<f64>> {
+        let current = self.current.time;
+        self.hourly.time.iter()
+            .enumerate()
+            .filter(|(_, t)| **t + 3600 > current)
+            .take(HOURLY_LIMIT)
+            .map(|(i, _)| self.hourly.weather(i))
+            .collect()
+    }
+
+    /// Daily temperatures are reported as a max / min, so the time of day
+    /// temperatures are taken from the hourly series where it covers the day
+    fn daily_weather(&self) -> Vec<Weather<WeatherDayTemps>> {
+        let hourly = &self.hourly;
+        let hours = hourly.time.iter()
+            .enumerate()
+            .map(|(i, t)| (*t, i))
+            .collect::<HashMap<i64, usize>>();
+        let temps_at = |day: i64, hour: i64, values: &[Option<f64>], fallback: f64| {
+            hours.get(&(day + hour * 3600))
+                .and_then(|i| at_opt(values, *i))
+                .unwrap_or(fallback)
+        };
+
+        let daily = &self.daily;
+        daily.time.iter()
+            .enumerate()
+            .map(|(i, day)| {
+                let max = at(&daily.temperature_2m_max, i);
+                let min = at(&daily.temperature_2m_min, i);
+                let feels_max = at(&daily.apparent_temperature_max, i);
+                let feels_min = at(&daily.apparent_temperature_min, i);
+
+                Weather {
+                    dt: *day,
+                    sunrise: at_opt(&daily.sunrise, i),
+                    sunset: at_opt(&daily.sunset, i),
+                    temp: WeatherDayTemps {
+                        day: max,
+                        night: min,
+                        eve: temps_at(*day, 18, &hourly.temperature_2m, max),
+                        morn: temps_at(*day, 6, &hourly.temperature_2m, min),
+                    },
+                    feels_like: WeatherDayTemps {
+                        day: feels_max,
+                        night: feels_min,
+                        eve: temps_at(*day, 18, &hourly.apparent_temperature, feels_max),
+                        morn: temps_at(*day, 6, &hourly.apparent_temperature, feels_min),
+                    },
+                    pressure: hours.get(&(day + 12 * 3600))
+                        .map(|h| at(&hourly.pressure_msl, *h) as u32)
+                        .unwrap_or(0),
+                    humidity: hours.get(&(day + 12 * 3600))
+                        .map(|h| at(&hourly.relative_humidity_2m, *h) as u32)
+                        .unwrap_or(0),
+                    dew_point: hours.get(&(day + 12 * 3600))
+                        .map(|h| at(&hourly.dew_point_2m, *h))
+                        .unwrap_or(0.0),
+                    uvi: at(&daily.uv_index_max, i),
+                    clouds: hours.get(&(day + 12 * 3600))
+                        .map(|h| at(&hourly.cloud_cover, *h))
+                        .unwrap_or(0.0),
+                    visibility: None,
+                    wind_speed: at(&daily.wind_speed_10m_max, i),
+                    wind_deg: at(&daily.wind_direction_10m_dominant, i) as u32,
+                    wind_gust: at_opt(&daily.wind_gusts_10m_max, i),
+                    status: vec![weather_code_status(at(&daily.weather_code, i), true)],
+                    pop: at(&daily.precipitation_probability_max, i) / 100.0,
+                    rain: volume(at_opt(&daily.rain_sum, i)),
+                    snow: volume(snowfall_water(at_opt(&daily.snowfall_sum, i))),
+                }
+            })
+            .collect()
+    }
+
+    /// Open-Meteo reports precipitation per 15 minutes, spread it out per minute
+    /// as a mm/h rate like OpenWeather's minutely forecast
+    fn minutely_weather(&self) -> Vec<WeatherMinutely> {
+        if let Some(minutely) = &self.minutely_15 {
+            minutely.time.iter()
+                .enumerate()
+                .flat_map(|(i, t)| {
+                    let rate = at(&minutely.precipitation, i) * 4.0;
+                    (0..15).map(move |m| WeatherMinutely {
+                        dt: t + m * 60,
+                        precipitation: rate,
+                    })
+                })
+                .collect()
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+impl From<OpenMeteoForecast> for WeatherData {
+    fn from(forecast: OpenMeteoForecast) -> Self {
+        WeatherData {
+            current: forecast.current_weather(),
+            hourly: forecast.hourly_weather(),
+            minutely: forecast.minutely_weather(),
+            daily: forecast.daily_weather(),
+            alerts: Vec::new(),
+            units: None,
+            provider: None,
+            provenance: Vec::new(),
+            utc_offset: forecast.utc_offset_seconds,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct OpenMeteoProvider;
+
+impl OpenMeteoProvider {
+    pub fn new() -> Self {
+        OpenMeteoProvider
+    }
+
+    fn base_url(&self) -> String {
+        format!("{}/{}", 
+            OPEN_METEO_API_URL, 
+            OPEN_METEO_API_VERSION)
+    }
+
+    fn forecast_url(&self, units: Units, lat: f64, lon: f64) -> String {
+        let (temperature_unit, wind_speed_unit) = match units {
+            Units::Metric => ("celsius", "ms"),
+            Units::Imperial => ("fahrenheit", "mph"),
+        };
+
+        format!("{}/forecast?latitude={}&longitude={}\
+&current={}&hourly={}&daily={}&minutely_15=precipitation&forecast_minutely_15=4\
+&temperature_unit={}&wind_speed_unit={}&precipitation_unit=mm\
+&timeformat=unixtime&timezone=auto",
+            self.base_url(),
+            lat, lon,
+            CURRENT_VARIABLES,
+            HOURLY_VARIABLES,
+            DAILY_VARIABLES,
+            temperature_unit,
+            wind_speed_unit)
+    }
+
+    async fn get_forecast(&self, units: Units, lat: f64, lon: f64) -> Result<WeatherData, WeatherError> {
+        let url = self.forecast_url(units, lat, lon);
+
+        let forecast: OpenMeteoForecast = HttpClient::shared().get_json(&url, &[]).await?;
+
+        Ok(WeatherData::from(forecast))
+    }
+}
+
+impl WeatherProvider for OpenMeteoProvider {
+    fn name(&self) -> &str {
+        "Open-Meteo"
+    }
+
+    fn fetch<'a>(&'a self, units: Units, lat: f64, lon: f64) -> WeatherFuture<'a> {
+        Box::pin(self.get_forecast(units, lat, lon))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn forecast() -> WeatherData {
+        let forecast: OpenMeteoForecast = serde_json::from_str(
+            include_str!("../../../tests/fixtures/open_meteo_forecast.json"))
+            .expect("fixture should decode");
+
+        WeatherData::from(forecast)
+    }
+
+    #[test]
+    fn maps_current_conditions() {
+        let current = forecast().current;
+
+        assert_eq!(current.dt, 1760620500);
+        assert_eq!(current.temp, 1.4);
+        assert_eq!(current.feels_like, -2.3);
+        assert_eq!(current.pressure, 1012);
+        assert_eq!(current.humidity, 88);
+        assert_eq!(current.wind_deg, 265);
+        assert_eq!(current.wind_gust, Some(9.1));
+        assert_eq!(current.sunrise, Some(1760593680));
+        // Visibility and chance of rain come from the hour the conditions fall in
+        assert_eq!(current.visibility, Some(9800));
+        assert!((current.pop - 0.55).abs() < 1e-9);
+        assert_eq!(current.rain, Some(0.2));
+        assert_eq!(current.snow, None);
+        assert_eq!(current.status[0].main, "Rain");
+        assert_eq!(current.status[0].icon, "10d");
+    }
+
+    #[test]
+    fn maps_hourly_forecast_from_the_current_hour() {
+        let hourly = forecast().hourly;
+
+        assert_eq!(hourly.len(), 2);
+        assert_eq!(hourly[0].dt, 1760619600);
+        assert_eq!(hourly[1].dt, 1760623200);
+        assert_eq!(hourly[1].temp, 0.6);
+        assert_eq!(hourly[1].visibility, Some(3100));
+        assert_eq!(hourly[1].uvi, 0.0);
+        assert_eq!(hourly[1].rain, None);
+        // 0.7 cm of snow is 1 mm of water
+        assert!((hourly[1].snow.unwrap() - 1.0).abs() < 1e-9);
+        assert_eq!(hourly[1].status[0].main, "Snow");
+    }
+
+    #[test]
+    fn maps_daily_forecast() {
+        let daily = forecast().daily;
+
+        assert_eq!(daily.len(), 2);
+        assert_eq!(daily[0].dt, 1760572800);
+        assert_eq!(daily[0].temp.day, 2.1);
+        assert_eq!(daily[0].temp.night, -1.5);
+        // Midday pressure from the hourly series, when it covers the day
+        assert_eq!(daily[0].pressure, 1013);
+        assert_eq!(daily[1].pressure, 0);
+        assert_eq!(daily[1].sunset, Some(1760717880));
+        assert_eq!(daily[1].wind_deg, 290);
+        assert!((daily[1].pop - 0.95).abs() < 1e-9);
+        assert_eq!(daily[1].rain, None);
+        assert!((daily[1].snow.unwrap() - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn spreads_minutely_precipitation() {
+        let minutely = forecast().minutely;
+
+        assert_eq!(minutely.len(), 30);
+        assert_eq!(minutely[14].dt, 1760619600 + 14 * 60);
+        assert!((minutely[15].precipitation - 1.2).abs() < 1e-9);
+    }
+}
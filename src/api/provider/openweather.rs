@@ -0,0 +1,235 @@
+use serde::Deserialize;
+use crate::api::units::Units;
+use crate::api::weather::{
+    Weather,
+    WeatherData,
+    WeatherDayTemps,
+    WeatherStatus,
+    WeatherAlert,
+    WeatherMinutely,
+};
+use crate::api::error::WeatherError;
+use crate::api::client::HttpClient;
+use super::{
+    WeatherProvider,
+    WeatherFuture,
+};
+
+const OPEN_WEATHER_API_URL: &str = "https://api.openweathermap.org/data";
+const OPEN_WEATHER_API_VERSION: &str = "3.0";
+
+#[derive(Deserialize)]
+struct OneCallMinutely {
+    dt: i64, 
+    precipitation: f64,
+}
+
+#[derive(Deserialize)]
+struct OneCallStatus {
+    main: String,
+    description: String,
+    icon: String
+}
+
+#[derive(Deserialize)]
+struct OneCallAlert {
+    start: i64,
+    end: i64,
+    sender_name: String,
+    event: String,
+    description: String,
+}
+
+#[derive(Deserialize)]
+struct OneCallWeather<T, P> {
+    dt: i64,
+    #[serde(default)]
+    sunrise: Option<i64>,
+    #[serde(default)]
+    sunset: Option<i64>,
+    temp: T,
+    feels_like: T,
+    pressure: u32,
+    humidity: u32,
+    dew_point: f64,
+    uvi: f64,
+    clouds: f64,
+    #[serde(default)]
+    visibility: Option<u32>,
+    wind_speed: f64,
+    wind_deg: u32,
+    #[serde(default)]
+    wind_gust: Option<f64>,
+    #[serde(rename = "weather")]
+    status: Vec<OneCallStatus>,
+    #[serde(default)]
+    pop: f64,
+    #[serde(default)]
+    rain: Option<P>,
+    #[serde(default)]
+    snow: Option<P>,
+}
+
+#[derive(Deserialize, Default)]
+struct OneCallPrecipitationHourly {
+    #[serde(default)]
+    #[serde(rename = "1h")]
+    volume: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct OneCallDayTemps {
+    day: f64,
+    night: f64,
+    eve: f64,
+    morn: f64,
+}
+
+type OneCallHourly = OneCallWeather<f64, OneCallPrecipitationHourly>;
+type OneCallDaily = OneCallWeather<OneCallDayTemps, f64>;
+
+#[derive(Deserialize)]
+struct OneCallData {
+    current: OneCallHourly,
+    hourly: Vec<OneCallHourly>,
+    #[serde(default)]
+    minutely: Vec<OneCallMinutely>,
+    daily: Vec<OneCallDaily>,
+    #[serde(default)]
+    alerts: Vec<OneCallAlert>,
+    #[serde(default)]
+    timezone_offset: Option<i32>,
+}
+
+impl From<OneCallStatus> for WeatherStatus {
+    fn from(status: OneCallStatus) -> Self {
+        WeatherStatus {
+            main: status.main,
+            description: status.description,
+            icon: status.icon,
+        }
+    }
+}
+
+impl From<OneCallDayTemps> for WeatherDayTemps {
+    fn from(temps: OneCallDayTemps) -> Self {
+        WeatherDayTemps {
+            day: temps.day,
+            night: temps.night,
+            eve: temps.eve,
+            morn: temps.morn,
+        }
+    }
+}
+
+impl<T, P> OneCallWeather<T, P> {
+    fn into_weather<U: From<T>>(self, volume: fn(P) -> Option<f64>) -> Weather<U> {
+        Weather {
+            dt: self.dt,
+            sunrise: self.sunrise,
+            sunset: self.sunset,
+            temp: U::from(self.temp),
+            feels_like: U::from(self.feels_like),
+            pressure: self.pressure,
+            humidity: self.humidity,
+            dew_point: self.dew_point,
+            uvi: self.uvi,
+            clouds: self.clouds,
+            visibility: self.visibility,
+            wind_speed: self.wind_speed,
+            wind_deg: self.wind_deg,
+            wind_gust: self.wind_gust,
+            status: self.status.into_iter().map(WeatherStatus::from).collect(),
+            pop: self.pop,
+            rain: self.rain.and_then(volume),
+            snow: self.snow.and_then(volume),
+        }
+    }
+}
+
+fn hourly_volume(precipitation: OneCallPrecipitationHourly) -> Option<f64> {
+    precipitation.volume
+}
+
+fn daily_volume(volume: f64) -> Option<f64> {
+    Some(volume)
+}
+
+impl From<OneCallData> for WeatherData {
+    fn from(data: OneCallData) -> Self {
+        WeatherData {
+            current: data.current.into_weather(hourly_volume),
+            hourly: data.hourly.into_iter()
+                .map(|h| h.into_weather(hourly_volume))
+                .collect(),
+            minutely: data.minutely.into_iter()
+                .map(|m| WeatherMinutely {
+                    dt: m.dt,
+                    precipitation: m.precipitation,
+                })
+                .collect(),
+            daily: data.daily.into_iter()
+                .map(|d| d.into_weather(daily_volume))
+                .collect(),
+            alerts: data.alerts.into_iter()
+                .map(|a| WeatherAlert {
+                    start: a.start,
+                    end: a.end,
+                    sender_name: a.sender_name,
+                    event: a.event,
+                    description: a.description,
+                    severity: None,
+                    urgency: None,
+                    area: None,
+                })
+                .collect(),
+            units: None,
+            provider: None,
+            provenance: Vec::new(),
+            utc_offset: data.timezone_offset,
+        }
+    }
+}
+
+pub struct OpenWeatherProvider {
+    api_key: Option<String>,
+}
+
+impl OpenWeatherProvider {
+    /// One Call 3.0 needs a personal subscription, so the API key is supplied by the user
+    pub fn new(api_key: Option<String>) -> Self {
+        OpenWeatherProvider {
+            api_key: api_key.filter(|key| !key.trim().is_empty()),
+        }
+    }
+
+    fn base_url(&self) -> String {
+        format!("{}/{}", 
+            OPEN_WEATHER_API_URL, 
+            OPEN_WEATHER_API_VERSION)
+    }
+
+    async fn get_one_call(&self, units: Units, lat: f64, lon: f64) -> Result<WeatherData, WeatherError> {
+        let api_key = self.api_key.as_ref()
+            .ok_or(WeatherError::MissingApiKey)?;
+        let url = format!("{}/onecall?lat={}&lon={}&units={}&appid={}",
+           self.base_url(),
+           lat, lon,
+           units,
+           api_key.trim());
+
+        let data: OneCallData = HttpClient::shared().get_json(&url, &[]).await?;
+
+        Ok(WeatherData::from(data))
+    }
+}
+
+impl WeatherProvider for OpenWeatherProvider {
+    fn name(&self) -> &str {
+        "OpenWeather"
+    }
+
+    fn fetch<'a>(&'a self, units: Units, lat: f64, lon: f64) -> WeatherFuture<'a> {
+        Box::pin(self.get_one_call(units, lat, lon))
+    }
+}
@@ -0,0 +1,405 @@
+use std::collections::HashMap;
+use std::io::{
+    ErrorKind,
+    Read,
+    Write,
+};
+use std::net::{
+    TcpListener,
+    TcpStream,
+};
+use std::sync::Arc;
+use std::sync::atomic::{
+    AtomicBool,
+    Ordering,
+};
+use std::thread::{
+    self,
+    JoinHandle,
+};
+use std::time::{
+    Duration,
+    SystemTime,
+};
+use super::units::Units;
+use super::provider::{
+    speed_in,
+    temperature_in,
+};
+
+/// Port stations are pointed at unless another is chosen
+pub const DEFAULT_STATION_PORT: u16 = 8080;
+
+/// Largest request read from a station, uploads are a few hundred bytes
+const MAX_REQUEST_BYTES: usize = 16 * 1024;
+/// How often the listener checks whether it has been stopped
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Latest upload from a personal weather station, in the imperial units both protocols use
+#[derive(Clone)]
+pub struct StationReading {
+    pub station: Option<String>,
+    pub received_at: i64,
+    pub temperature_f: Option<f64>,
+    pub humidity: Option<f64>,
+    pub wind_speed_mph: Option<f64>,
+    pub wind_gust_mph: Option<f64>,
+    pub wind_deg: Option<f64>,
+    pub pressure_inhg: Option<f64>,
+    /// Rain over the last hour (Weather Underground) or the current rate (Ecowitt)
+    pub rain_rate_in: Option<f64>,
+    pub daily_rain_in: Option<f64>,
+    pub uvi: Option<f64>,
+}
+
+fn fahrenheit_to_celsius(fahrenheit: f64) -> f64 {
+    (fahrenheit - 32.0) * 5.0 / 9.0
+}
+
+fn mph_to_metres_per_second(mph: f64) -> f64 {
+    mph / 2.236_936
+}
+
+impl StationReading {
+    /// Reads the fields of an Ecowitt or Weather Underground upload,
+    /// None when none of the readings shown are present
+    pub fn from_fields(fields: &HashMap<String, String>) -> Option<StationReading> {
+        let number = |names: &[&str]| names.iter()
+            .filter_map(|name| fields.get(*name))
+            .find_map(|value| value.trim().parse::<f64>().ok())
+            // Weather Underground uses -9999 for a sensor with no reading
+            .filter(|value| *value > -9999.0);
+
+        let reading = StationReading {
+            station: fields.get("ID")
+                .or_else(|| fields.get("model"))
+                .or_else(|| fields.get("stationtype"))
+                .cloned(),
+            received_at: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map(|d| d.as_secs() as i64)
+                .unwrap_or(0),
+            temperature_f: number(&["tempf"]),
+            humidity: number(&["humidity"]),
+            wind_speed_mph: number(&["windspeedmph"]),
+            wind_gust_mph: number(&["windgustmph"]),
+            wind_deg: number(&["winddir"]),
+            pressure_inhg: number(&["baromrelin", "baromin", "baromabsin"]),
+            rain_rate_in: number(&["rainratein", "rainin"]),
+            daily_rain_in: number(&["dailyrainin"]),
+            uvi: number(&["uv", "UV"]),
+        };
+
+        if reading.temperature_f.is_none()
+            && reading.humidity.is_none()
+            && reading.wind_speed_mph.is_none()
+            && reading.pressure_inhg.is_none() {
+            return None;
+        }
+
+        Some(reading)
+    }
+
+    pub fn temperature(&self, units: Units) -> Option<f64> {
+        self.temperature_f.map(|t| temperature_in(units, fahrenheit_to_celsius(t)))
+    }
+
+    pub fn wind_speed(&self, units: Units) -> Option<f64> {
+        self.wind_speed_mph.map(|s| speed_in(units, mph_to_metres_per_second(s)))
+    }
+
+    pub fn wind_gust(&self, units: Units) -> Option<f64> {
+        self.wind_gust_mph.map(|s| speed_in(units, mph_to_metres_per_second(s)))
+    }
+
+    pub fn pressure_hpa(&self) -> Option<f64> {
+        self.pressure_inhg.map(|p| p * 33.863_9)
+    }
+
+    pub fn rain_rate_mm(&self) -> Option<f64> {
+        self.rain_rate_in.map(|r| r * 25.4)
+    }
+
+    pub fn daily_rain_mm(&self) -> Option<f64> {
+        self.daily_rain_in.map(|r| r * 25.4)
+    }
+}
+
+fn hex_value(byte: u8) -> Option<u8> {
+    (byte as char).to_digit(16).map(|d| d as u8)
+}
+
+/// Decodes a form or query string component, + as a space and %XX escapes
+fn decode_component(component: &str) -> String {
+    let bytes = component.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                match (hex_value(bytes[i + 1]), hex_value(bytes[i + 2])) {
+                    (Some(high), Some(low)) => {
+                        decoded.push(high * 16 + low);
+                        i += 2;
+                    },
+                    _ => decoded.push(b'%'),
+                }
+            },
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn parse_form(form: &str) -> HashMap<String, String> {
+    form.split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.split_once('=') {
+            Some((key, value)) => (decode_component(key), decode_component(value)),
+            None => (decode_component(pair), String::new()),
+        })
+        .collect()
+}
+
+/// Fields of an upload, from the query of a GET (Weather Underground)
+/// or the form body of a POST (Ecowitt)
+fn request_fields(request: &[u8]) -> Option<HashMap<String, String>> {
+    let text = String::from_utf8_lossy(request);
+    let (head, body) = text.split_once("\r\n\r\n")?;
+    let request_line = head.lines().next()?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next()?;
+    let target = parts.next()?;
+
+    let mut fields = target.split_once('?')
+        .map(|(_, query)| parse_form(query))
+        .unwrap_or_default();
+    if method == "POST" {
+        fields.extend(parse_form(body.trim()));
+    }
+
+    Some(fields)
+}
+
+/// Position just past the headers and the declared body length, once the headers are complete
+fn request_length(request: &[u8]) -> Option<usize> {
+    let end = request.windows(4).position(|w| w == b"\r\n\r\n")? + 4;
+    let head = String::from_utf8_lossy(&request[..end]);
+    let content_length = head.lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+        .unwrap_or(0);
+
+    Some(end + content_length)
+}
+
+fn read_request(stream: &mut TcpStream) -> std::io::Result<Vec<u8>> {
+    let mut request = Vec::new();
+    let mut buffer = [0; 1024];
+    loop {
+        let read = stream.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        request.extend_from_slice(&buffer[..read]);
+        match request_length(&request) {
+            Some(length) if request.len() >= length => break,
+            _ if request.len() >= MAX_REQUEST_BYTES => break,
+            _ => {},
+        }
+    }
+
+    Ok(request)
+}
+
+fn handle_upload<F: Fn(StationReading)>(mut stream: TcpStream, on_reading: &F) -> std::io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+
+    let request = read_request(&mut stream)?;
+    let reading = request_fields(&request)
+        .and_then(|fields| StationReading::from_fields(&fields));
+
+    // Weather Underground stations expect "success", Ecowitt any 200 response
+    let response = if let Some(reading) = reading {
+        on_reading(reading);
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 7\r\nConnection: close\r\n\r\nsuccess"
+    } else {
+        "HTTP/1.1 400 Bad Request\r\nContent-Type: text/plain\r\nContent-Length: 11\r\nConnection: close\r\n\r\nbad request"
+    };
+    stream.write_all(response.as_bytes())
+}
+
+/// Local HTTP listener for stations uploading with the Ecowitt or Weather Underground protocol,
+/// stopped when dropped. Dropping waits for the listener thread to close its socket, so the
+/// port can be bound again straight away
+pub struct StationServer {
+    port: u16,
+    stop: Arc<AtomicBool>,
+    listener: Option<JoinHandle<()>>,
+}
+
+impl StationServer {
+    /// Listens on all interfaces so stations on the local network can reach it,
+    /// calling on_reading from the listener thread for every upload
+    pub fn start<F: Fn(StationReading) + Send + 'static>(port: u16, on_reading: F) -> std::io::Result<StationServer> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        listener.set_nonblocking(true)?;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let listener = thread::spawn(move || {
+            while !thread_stop.load(Ordering::Relaxed) {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        if let Err(err) = handle_upload(stream, &on_reading) {
+                            println!("Unable to read station upload: {}", err);
+                        }
+                    },
+                    Err(err) if err.kind() == ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+                    Err(err) => {
+                        println!("Station listener failed: {}", err);
+                        thread::sleep(POLL_INTERVAL);
+                    },
+                }
+            }
+        });
+
+        Ok(StationServer {
+            port,
+            stop,
+            listener: Some(listener),
+        })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+}
+
+impl Drop for StationServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(listener) = self.listener.take() {
+            if listener.join().is_err() {
+                println!("Station listener panicked");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ECOWITT_BODY: &str = "PASSKEY=0123456789ABCDEF&stationtype=EasyWeatherV1.6.4\
+        &dateutc=2024-05-01+12%3A00%3A00&tempf=68.0&humidity=55&winddir=270\
+        &windspeedmph=4.474&windgustmph=8.053&baromrelin=29.921&baromabsin=29.700\
+        &rainratein=0.000&dailyrainin=0.120&uv=3&model=GW1000_Pro";
+
+    fn ecowitt_request() -> String {
+        format!("POST /data/report/ HTTP/1.1\r\nHost: 192.168.1.10:8080\r\n\
+            Content-Type: application/x-www-form-urlencoded\r\nContent-Length: {}\r\n\r\n{}",
+            ECOWITT_BODY.len(), ECOWITT_BODY)
+    }
+
+    const WUNDERGROUND_REQUEST: &str = "GET /weatherstation/updateweatherstation.php?ID=KNYNEWYO123\
+        &PASSWORD=secret&dateutc=now&tempf=-9999&humidity=80&baromin=30.01\
+        &windspeedmph=0&winddir=&rainin=0.02&action=updateraw HTTP/1.1\r\nHost: 192.168.1.10\r\n\r\n";
+
+    fn close(a: Option<f64>, b: f64) -> bool {
+        a.map(|a| (a - b).abs() < 1e-3).unwrap_or(false)
+    }
+
+    #[test]
+    fn decodes_components() {
+        assert_eq!(decode_component("2024-05-01+12%3A00%3A00"), "2024-05-01 12:00:00");
+        assert_eq!(decode_component("%E2%9C%93"), "\u{2713}");
+        assert_eq!(decode_component("%7e"), "~");
+        // Malformed escapes are kept as they are
+        assert_eq!(decode_component("100%"), "100%");
+        assert_eq!(decode_component("%3"), "%3");
+        assert_eq!(decode_component("%zz"), "%zz");
+    }
+
+    #[test]
+    fn parses_forms() {
+        let fields = parse_form("a=1&b=&c&&d=x+y");
+        assert_eq!(fields.len(), 4);
+        assert_eq!(fields["a"], "1");
+        assert_eq!(fields["b"], "");
+        assert_eq!(fields["c"], "");
+        assert_eq!(fields["d"], "x y");
+        assert!(parse_form("").is_empty());
+    }
+
+    #[test]
+    fn measures_requests() {
+        let request = ecowitt_request();
+        assert_eq!(request_length(request.as_bytes()), Some(request.len()));
+        // Headers still arriving
+        let head_end = request.find("\r\n\r\n").unwrap();
+        assert_eq!(request_length(&request.as_bytes()[..head_end]), None);
+
+        assert_eq!(request_length(WUNDERGROUND_REQUEST.as_bytes()), Some(WUNDERGROUND_REQUEST.len()));
+        assert_eq!(request_length(b"POST / HTTP/1.1\r\ncontent-length: 12\r\n\r\n"), Some(51));
+    }
+
+    #[test]
+    fn reads_ecowitt_upload() {
+        let fields = request_fields(ecowitt_request().as_bytes()).unwrap();
+        assert_eq!(fields["dateutc"], "2024-05-01 12:00:00");
+
+        let reading = StationReading::from_fields(&fields).unwrap();
+        assert_eq!(reading.station.as_deref(), Some("GW1000_Pro"));
+        assert!(close(reading.temperature(Units::Metric), 20.0));
+        assert!(close(reading.temperature(Units::Imperial), 68.0));
+        assert!(close(reading.wind_speed(Units::Metric), 2.0));
+        assert!(close(reading.wind_speed(Units::Imperial), 4.474));
+        assert!(close(reading.wind_gust(Units::Metric), 3.6));
+        assert_eq!(reading.wind_deg, Some(270.0));
+        assert_eq!(reading.humidity, Some(55.0));
+        // The relative pressure is preferred over the absolute one
+        assert!(close(reading.pressure_hpa(), 29.921 * 33.8639));
+        assert!(close(reading.rain_rate_mm(), 0.0));
+        assert!(close(reading.daily_rain_mm(), 3.048));
+        assert_eq!(reading.uvi, Some(3.0));
+    }
+
+    #[test]
+    fn reads_wunderground_upload() {
+        let fields = request_fields(WUNDERGROUND_REQUEST.as_bytes()).unwrap();
+        let reading = StationReading::from_fields(&fields).unwrap();
+
+        assert_eq!(reading.station.as_deref(), Some("KNYNEWYO123"));
+        // -9999 is a sensor without a reading, an empty value is no reading either
+        assert_eq!(reading.temperature_f, None);
+        assert_eq!(reading.wind_deg, None);
+        assert_eq!(reading.humidity, Some(80.0));
+        assert!(close(reading.pressure_hpa(), 30.01 * 33.8639));
+        assert_eq!(reading.wind_speed_mph, Some(0.0));
+        assert!(close(reading.rain_rate_mm(), 0.508));
+    }
+
+    #[test]
+    fn ignores_uploads_without_readings() {
+        let fields = request_fields(b"GET /?ID=KNYNEWYO123&tempf=-9999&uv=2 HTTP/1.1\r\n\r\n").unwrap();
+        assert!(StationReading::from_fields(&fields).is_none());
+        assert!(request_fields(b"GET /?tempf=60 HTTP/1.1\r\n").is_none());
+    }
+
+    #[test]
+    fn rebinds_port_after_stopping() {
+        let port = TcpListener::bind(("0.0.0.0", 0)).unwrap().local_addr().unwrap().port();
+
+        let server = StationServer::start(port, |_| {}).unwrap();
+        drop(server);
+        let server = StationServer::start(port, |_| {}).unwrap();
+        assert_eq!(server.port(), port);
+    }
+}
@@ -3,7 +3,7 @@ use serde::{
     Serialize,
 };
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
 pub enum Units {
     Metric,
     Imperial
@@ -18,12 +18,6 @@ impl std::fmt::Display for Units {
     }
 }
 
-impl PartialEq for Units {
-    fn eq(&self, other: &Self) -> bool {
-        self == other
-    }
-}
-
 impl Units {
     pub fn temperature_unit(&self) -> &str {
         match *self {
@@ -43,6 +37,12 @@ impl Units {
             Units::Metric => "mm",
         }
     }
+    pub fn height_unit(&self) -> &str {
+        match *self {
+            Units::Imperial => "ft",
+            Units::Metric => "m",
+        }
+    }
     pub fn temperature_value<T: std::fmt::Display>(&self, value: T) -> String {
         format!("{} {}", value, self.temperature_unit())
     }
@@ -52,4 +52,7 @@ impl Units {
     pub fn volume_value<T: std::fmt::Display>(&self, value: T) -> String {
         format!("{} {}", value, self.volume_unit())
     }
+    pub fn height_value<T: std::fmt::Display>(&self, value: T) -> String {
+        format!("{} {}", value, self.height_unit())
+    }
 }
@@ -1,19 +1,23 @@
-use serde::Deserialize;
-use isahc::prelude::*;
+use serde::{
+    Deserialize,
+    Serialize,
+};
 use super::units::Units;
-use time::OffsetDateTime;
+use super::provider::WeatherProvider;
+use super::error::WeatherError;
+use time::{
+    OffsetDateTime,
+    UtcOffset,
+    Date,
+};
 
-const OPEN_WEATHER_API_KEY: &str = "ad589466a7b4db65d43f8e6c850f97e5";
-const OPEN_WEATHER_API_URL: &str = "https://api.openweathermap.org/data";
-const OPEN_WEATHER_API_VERSION: &str = "2.5";
-
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct WeatherMinutely {
-    dt: i64, 
+    pub dt: i64,
     pub precipitation: f64,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct WeatherDayTemps {
     pub day: f64,
     pub night: f64,
@@ -21,29 +25,40 @@ pub struct WeatherDayTemps {
     pub morn: f64,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct WeatherStatus {
-    pub id: u32,
     pub main: String,
     pub description: String,
     pub icon: String
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct WeatherAlert {
-    start: i64,
-    end: i64,
+    pub start: i64,
+    pub end: i64,
     pub sender_name: String,
     pub event: String,
     pub description: String,
+    #[serde(default)]
+    pub severity: Option<String>,
+    #[serde(default)]
+    pub urgency: Option<String>,
+    /// Description of the affected area, when the provider reports it
+    #[serde(default)]
+    pub area: Option<String>,
 }
 
-#[derive(Deserialize)]
-pub struct Weather<T, P> {
-    dt: i64,
-    #[serde(default)]
+/// Where a field of a blended forecast came from, e.g. Temperature: median of OpenWeather, Open-Meteo
+#[derive(Serialize, Deserialize, Clone)]
+pub struct FieldProvenance {
+    pub field: String,
+    pub source: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Weather<T> {
+    pub dt: i64,
     pub sunrise: Option<i64>,
-    #[serde(default)]
     pub sunset: Option<i64>,
     pub temp: T,
     pub feels_like: T,
@@ -52,119 +67,126 @@ pub struct Weather<T, P> {
     pub dew_point: f64,
     pub uvi: f64,
     pub clouds: f64,
-    #[serde(default)]
     pub visibility: Option<u32>,
     pub wind_speed: f64,
     pub wind_deg: u32,
-    #[serde(default)]
     pub wind_gust: Option<f64>,
-    #[serde(rename = "weather")]
     pub status: Vec<WeatherStatus>,
-    #[serde(default)]
     pub pop: f64,
-    #[serde(default)]
-    pub rain: Option<P>,
-    #[serde(default)]
-    pub snow: Option<P>,
-}
-
-#[derive(Deserialize, Default)]
-pub struct PrecipitationHourly {
-    #[serde(default)]
-    #[serde(rename = "1h")]
-    pub volume: Option<f64>,
+    /// Rain volume over the period (1h for current / hourly, the whole day for daily)
+    pub rain: Option<f64>,
+    /// Snow volume over the period (1h for current / hourly, the whole day for daily)
+    pub snow: Option<f64>,
 }
 
-pub type CurrentWeather = Weather<f64, PrecipitationHourly>;
-pub type HourlyWeather = Weather<f64, PrecipitationHourly>;
-pub type DailyWeather = Weather<WeatherDayTemps, f64>;
+pub type CurrentWeather = Weather<f64>;
+pub type HourlyWeather = Weather<f64>;
+pub type DailyWeather = Weather<WeatherDayTemps>;
 
-#[derive(Deserialize)]
+/// Provider neutral forecast, every WeatherProvider maps its response onto this
+#[derive(Serialize, Deserialize, Clone)]
 pub struct WeatherData {
    pub current: CurrentWeather,
    pub hourly: Vec<HourlyWeather>,
    pub minutely: Vec<WeatherMinutely>,
    pub daily: Vec<DailyWeather>,
-    #[serde(default)]
    pub alerts: Vec<WeatherAlert>,
    pub units: Option<Units>,
+    /// Name of the provider that served this forecast
+    #[serde(default)]
+   pub provider: Option<String>,
+    /// Per-field sources, only given for blended forecasts
+    #[serde(default)]
+   pub provenance: Vec<FieldProvenance>,
+    /// Offset of the location's time zone from UTC in seconds, when the provider reports it
+    #[serde(default)]
+   pub utc_offset: Option<i32>,
 }
 
-pub fn time_from(dt: i64, format: &str) -> String {   
-    let datetime = datetime_from(dt);
+pub fn time_from(dt: i64, format: &str) -> Result<String, WeatherError> {
+    let datetime = datetime_from(dt)?;
     let time = datetime.time();
-    if let Ok(format_desc) = time::format_description::parse(format) {
-        if let Ok(formatted_time) = time.format(&format_desc) {
-            formatted_time
-        } else {
-            // TODO: crap
-            panic!("Unable to convert time from time api value")
-        }
-    } else {
-        panic!("Invalid format description supplied")
-    }
+    let format_desc = time::format_description::parse(format)
+        .map_err(|_| WeatherError::InvalidTimestamp(dt))?;
+
+    time.format(&format_desc)
+        .map_err(|_| WeatherError::InvalidTimestamp(dt))
 }
 
-pub fn datetime_from(dt: i64) -> OffsetDateTime {
-    if let Ok(datetime) = OffsetDateTime::from_unix_timestamp(dt) {
-        datetime
-    } else {
-        // TODO: crap
-        panic!("Unable to convert to date & time offset from api value")
-    }
+pub fn datetime_from(dt: i64) -> Result<OffsetDateTime, WeatherError> {
+    OffsetDateTime::from_unix_timestamp(dt)
+        .map_err(|_| WeatherError::InvalidTimestamp(dt))
 }
 
-pub fn date_from(dt: i64) -> String {
-    let datetime = datetime_from(dt);
-    datetime.date().to_string()
+/// The date of a timestamp in the device's time zone
+pub fn local_date_from(dt: i64) -> Result<Date, WeatherError> {
+    let offset = UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC);
+    date_at(dt, offset)
+}
+
+/// Offset of solar time at a longitude, for locations whose time zone is not reported.
+/// Time zones mostly keep within an hour or two of it, so midday still falls on the same date
+pub fn estimated_offset(lon: f64) -> UtcOffset {
+    let hours = (lon / 15.0).round().max(-12.0).min(12.0) as i8;
+    UtcOffset::from_hms(hours, 0, 0).unwrap_or(UtcOffset::UTC)
+}
+
+/// The date of a timestamp at a UTC offset, such as the location's
+pub fn date_at(dt: i64, offset: UtcOffset) -> Result<Date, WeatherError> {
+    Ok(datetime_from(dt)?.to_offset(offset).date())
+}
+
+pub fn date_from(dt: i64) -> Result<String, WeatherError> {
+    let datetime = datetime_from(dt)?;
+    Ok(datetime.date().to_string())
 }
 
 pub trait TimeStamped {
-    fn time(&self, format: &str) -> String;
-    fn date(&self) -> String;
+    fn time(&self, format: &str) -> Result<String, WeatherError>;
+    fn date(&self) -> Result<String, WeatherError>;
 }
 
 impl TimeStamped for CurrentWeather {
-    fn time(&self, format: &str) -> String {
+    fn time(&self, format: &str) -> Result<String, WeatherError> {
         time_from(self.dt, format)
     }
-    fn date(&self) -> String {
+    fn date(&self) -> Result<String, WeatherError> {
         date_from(self.dt)
     }
 }
 impl TimeStamped for DailyWeather {
-    fn time(&self, format: &str) -> String {
+    fn time(&self, format: &str) -> Result<String, WeatherError> {
         time_from(self.dt, format)
     }
-    fn date(&self) -> String {
+    fn date(&self) -> Result<String, WeatherError> {
         date_from(self.dt)
     }
 }
 impl TimeStamped for WeatherMinutely {
-    fn time(&self, format: &str) -> String {
+    fn time(&self, format: &str) -> Result<String, WeatherError> {
         time_from(self.dt, format)
     }
-    fn date(&self) -> String {
+    fn date(&self) -> Result<String, WeatherError> {
         date_from(self.dt)
     }
 }
 impl DailyWeather {
     pub fn sunset(&self) -> Option<String> {
         if let Some(time) = self.sunset {
-            Some(time_from(time, "[hour]:[minute]"))
+            time_from(time, "[hour]:[minute]").ok()
         } else {
             None
         }
     }
     pub fn sunrise(&self) -> Option<String> {
         if let Some(time) = self.sunrise {
-            Some(time_from(time, "[hour]:[minute]"))
+            time_from(time, "[hour]:[minute]").ok()
         } else {
             None
         }
     }
-    pub fn day_of_week(&self) -> String {
-        let date_time = datetime_from(self.dt);
+    pub fn day_of_week(&self) -> Result<String, WeatherError> {
+        let date_time = datetime_from(self.dt)?;
         let local = std::time::SystemTime::now();
         let today = OffsetDateTime::from(local);
         let today_date = today.date();
@@ -172,58 +194,77 @@ impl DailyWeather {
         if today_date.year() == date.year()
         && today_date.month() == date.month()
         && today_date.day() == date.day() {
-            String::from("Today")
+            Ok(String::from("Today"))
         } else {
-            date_time.weekday().to_string()
+            Ok(date_time.weekday().to_string())
         }
     }
 }
 
-fn base_url() -> String {
-    return format!("{}/{}", 
-        OPEN_WEATHER_API_URL, 
-        OPEN_WEATHER_API_VERSION);
-}
+/// Precipitation rate (mm/h) from which a minute counts as wet in the nowcast
+pub const NOWCAST_THRESHOLD: f64 = 0.1;
 
-pub async fn get_weather_data(units: Units, lat: f64, lon: f64) -> Option<WeatherData> {
-    let url = format!("{}/onecall?lat={}&lon={}&units={}&appid={}",
-       base_url(),
-       lat, lon,
-       units,
-       OPEN_WEATHER_API_KEY);
+/// Minutes from now until precipitation starts and then stops within the minutely
+/// forecast, a start of 0 means it is already falling
+pub fn precipitation_window(minutely: &[WeatherMinutely], now: i64) -> (Option<i64>, Option<i64>) {
+    let minutes_from_now = |m: &WeatherMinutely| ((m.dt - now) / 60).max(0);
+    let start = minutely.iter()
+        .find(|m| m.precipitation >= NOWCAST_THRESHOLD);
+    let stop = start.and_then(|start| minutely.iter()
+        .filter(|m| m.dt > start.dt)
+        .find(|m| m.precipitation < NOWCAST_THRESHOLD));
 
-    let response = isahc::get_async(url).await;
-    if let Some(mut body) = response.ok() {
-        let text = body.text().await;
-        let text = text.unwrap();
-        //println!("weather got: {}", text);
-        
-        let data: Option<WeatherData> = serde_json::from_str(&text)
-            .unwrap();
-            //.unwrap_or(None);
+    (start.map(minutes_from_now), stop.map(minutes_from_now))
+}
 
-        if let Some(mut data) = data {
+pub async fn get_weather_data(provider: &dyn WeatherProvider, units: Units, lat: f64, lon: f64) -> Result<WeatherData, WeatherError> {
+    match provider.fetch(units, lat, lon).await {
+        Ok(mut data) => {
             data.units = Some(units);
-            Some(data)
-        } else {
-            None
+            if data.provider.is_none() {
+                data.provider = Some(provider.name().to_string());
+            }
+            Ok(data)
+        },
+        Err(err) => {
+            println!("get_weather_data: {} did not return any data: {}", provider.name(), err);
+            Err(err)
         }
-    } else {
-        None
     }
 }
 
 impl WeatherAlert {
-    pub fn when(&self) -> String {
-        let start = date_from(self.start);
-        let start_time = time_from(self.start, "[hour]:[minute]");
-        let end = date_from(self.end);
-        let end_time = time_from(self.end, "[hour]:[minute]");
+    pub fn when(&self) -> Result<String, WeatherError> {
+        let start = date_from(self.start)?;
+        let start_time = time_from(self.start, "[hour]:[minute]")?;
+        let end = date_from(self.end)?;
+        let end_time = time_from(self.end, "[hour]:[minute]")?;
 
         if start != end {
-            format!("{} {} to {} {}", start, start_time, end, end_time)
+            Ok(format!("{} {} to {} {}", start, start_time, end, end_time))
         } else {
-            format!("{} {} to {}", start, start_time, end_time)
+            Ok(format!("{} {} to {}", start, start_time, end_time))
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_time_of_day() {
+        assert_eq!(time_from(1760620500, "[hour]:[minute]").unwrap(), "13:15");
+    }
+
+    #[test]
+    fn invalid_format_is_an_error() {
+        assert!(matches!(time_from(1760620500, "[hour"), Err(WeatherError::InvalidTimestamp(1760620500))));
+    }
+
+    #[test]
+    fn out_of_range_timestamp_is_an_error() {
+        assert!(matches!(datetime_from(i64::MAX), Err(WeatherError::InvalidTimestamp(i64::MAX))));
+        assert!(matches!(time_from(i64::MAX, "[hour]:[minute]"), Err(WeatherError::InvalidTimestamp(_))));
+    }
+}
@@ -0,0 +1,115 @@
+use dirs::cache_dir;
+use std::path::PathBuf;
+use std::fs::{
+    File,
+    create_dir_all,
+};
+use std::time::SystemTime;
+use serde::{
+    Serialize,
+    Deserialize,
+};
+use super::api::weather::WeatherData;
+use super::api::units::Units;
+use super::api::provider::ProviderKind;
+use super::api::location::LocationPoint;
+
+const WEATHER_CACHE_DIR: &str = "weather-mobile";
+
+/// Default minutes before cached weather is refreshed in the background
+pub const DEFAULT_CACHE_TTL_MINUTES: u32 = 30;
+
+/// The last forecast fetched for a location, kept so it can be shown
+/// straight away at startup and when offline
+#[derive(Serialize, Deserialize)]
+pub struct CachedForecast {
+    pub fetched_at: i64,
+    pub units: Units,
+    pub provider: ProviderKind,
+    pub data: WeatherData,
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+fn cache_path(lat: f64, lon: f64) -> Option<PathBuf> {
+    let mut dir = cache_dir()?;
+    dir.push(WEATHER_CACHE_DIR);
+    dir.push(format!("forecast_{:.4}_{:.4}.json", lat, lon));
+
+    Some(dir)
+}
+
+impl CachedForecast {
+    pub fn load(location: &LocationPoint) -> Option<CachedForecast> {
+        let path = cache_path(location.lat, location.lon)?;
+        let file = File::open(path).ok()?;
+
+        match serde_json::from_reader(file) {
+            Ok(cached) => Some(cached),
+            Err(err) => {
+                println!("Ignoring unreadable forecast cache: {}", err);
+                None
+            }
+        }
+    }
+
+    pub fn save(location: &LocationPoint, provider: ProviderKind, data: &WeatherData) {
+        let path = if let Some(path) = cache_path(location.lat, location.lon) {
+            path
+        } else {
+            println!("Unable to resolve cache path");
+            return;
+        };
+        if let Some(dir) = path.parent() {
+            if let Err(err) = create_dir_all(dir) {
+                println!("Unable to create cache directory: {}", err);
+                return;
+            }
+        }
+
+        let cached = CachedForecast {
+            fetched_at: now(),
+            units: data.units.unwrap_or(Units::Metric),
+            provider,
+            data: data.clone(),
+        };
+        let result = File::create(path)
+            .map_err(|err| err.to_string())
+            .and_then(|file| serde_json::to_writer(file, &cached)
+                .map_err(|err| err.to_string()));
+        if let Err(err) = result {
+            println!("Unable to write forecast cache: {}", err);
+        }
+    }
+
+    pub fn age(&self) -> i64 {
+        (now() - self.fetched_at).max(0)
+    }
+
+    pub fn is_stale(&self, ttl_minutes: u32) -> bool {
+        self.age() > ttl_minutes as i64 * 60
+    }
+
+    /// Whether this forecast can stand in for one requested with these settings
+    pub fn matches(&self, units: Units, provider: ProviderKind) -> bool {
+        self.units == units && self.provider == provider
+    }
+
+    pub fn age_text(&self) -> String {
+        let age = self.age();
+        if age < 60 {
+            String::from("just now")
+        } else if age < 60 * 60 {
+            format!("{} min ago", age / 60)
+        } else if age < 60 * 60 * 24 {
+            format!("{} h ago", age / (60 * 60))
+        } else {
+            format!("{} days ago", age / (60 * 60 * 24))
+        }
+    }
+}
@@ -8,6 +8,8 @@ mod api;
 mod ui;
 mod preferences;
 mod rpc;
+mod cache;
+mod accuracy;
 
 use preferences::WeatherPreferences;
 use ui::WeatherApplication;
@@ -6,14 +6,95 @@ use serde::{
     Deserialize,
 };
 use super::api::units::Units;
-use super::api::location::LocationPoint;
+use super::api::location::{
+    GeocoderKind,
+    LocationPoint,
+};
+use super::api::provider::ProviderKind;
+use super::cache::DEFAULT_CACHE_TTL_MINUTES;
+use super::api::pollen::Allergen;
+use super::api::aviation::{
+    DEFAULT_METAR_URL,
+    DEFAULT_TAF_URL,
+};
+use super::api::map::{
+    MapLayer,
+    DEFAULT_BASE_TILE_URL,
+    DEFAULT_LAYER_TILE_URL,
+};
 
 #[derive(Serialize, Deserialize)]
 pub struct WeatherPreferences {
+    /// The location shown, kept alongside the saved locations so older versions can still read the file
     pub location: String,
     pub lat: f64,
     pub lon: f64,
+    /// Saved locations, in the order shown in the switcher
+    #[serde(default)]
+    pub locations: Vec<LocationPoint>,
+    /// Index of the location shown in the saved locations, None when it is not saved
+    #[serde(default)]
+    pub selected_location: Option<usize>,
     pub units: Units,
+    #[serde(default)]
+    pub provider: ProviderKind,
+    /// Providers tried in order when the primary provider fails
+    #[serde(default)]
+    pub fallback_providers: Vec<ProviderKind>,
+    #[serde(default)]
+    pub open_weather_api_key: Option<String>,
+    /// Service used to search for locations
+    #[serde(default)]
+    pub geocoder: GeocoderKind,
+    /// Minutes before the cached forecast is refreshed
+    #[serde(default = "default_cache_ttl_minutes")]
+    pub cache_ttl_minutes: u32,
+    /// Allergens highlighted in the pollen outlook
+    #[serde(default = "default_tracked_allergens")]
+    pub tracked_allergens: Vec<Allergen>,
+    /// Tile URL template for the base map, with {z}, {x} and {y} placeholders
+    #[serde(default = "default_map_base_url")]
+    pub map_base_url: String,
+    /// Tile URL template for the weather layers, also with {layer} and {api_key}
+    #[serde(default = "default_map_layer_url")]
+    pub map_layer_url: String,
+    #[serde(default)]
+    pub map_layer: MapLayer,
+    /// ICAO code of the aerodrome shown on the aviation page
+    #[serde(default)]
+    pub aviation_station: Option<String>,
+    /// METAR and TAF sources, URL templates with {icao} or local file paths
+    #[serde(default = "default_metar_url")]
+    pub metar_url: String,
+    #[serde(default = "default_taf_url")]
+    pub taf_url: String,
+    /// Port listened on for personal weather station uploads, None when not listening
+    #[serde(default)]
+    pub station_port: Option<u16>,
+}
+
+fn default_metar_url() -> String {
+    String::from(DEFAULT_METAR_URL)
+}
+
+fn default_taf_url() -> String {
+    String::from(DEFAULT_TAF_URL)
+}
+
+fn default_map_base_url() -> String {
+    String::from(DEFAULT_BASE_TILE_URL)
+}
+
+fn default_map_layer_url() -> String {
+    String::from(DEFAULT_LAYER_TILE_URL)
+}
+
+fn default_tracked_allergens() -> Vec<Allergen> {
+    Allergen::ALL.to_vec()
+}
+
+fn default_cache_ttl_minutes() -> u32 {
+    DEFAULT_CACHE_TTL_MINUTES
 }
 
 const WEATHER_CONFIG_FILE: &str = "weather.json";
@@ -37,7 +118,7 @@ impl WeatherPreferences {
                 .expect("Unable to read config file");
             let file_value = serde_json::from_reader(file)
                 .expect("File contained incorrect JSON format");
-            let config_file: WeatherPreferences = serde_json::from_value(file_value)
+            let config_file = WeatherPreferences::from_value(file_value)
                 .expect("Config file in the wrong format");
 
             Some(config_file)
@@ -54,11 +135,75 @@ impl WeatherPreferences {
             .expect("Unable to write to configuration file");
     }
 
+    /// Files from before locations could be saved have no `locations` key, their single location
+    /// becomes the first saved one. An empty list is kept, as the user removed every location
+    fn from_value(value: serde_json::Value) -> serde_json::Result<WeatherPreferences> {
+        let saved: Option<Vec<LocationPoint>> = match value.get("locations") {
+            Some(locations) => serde_json::from_value(locations.clone())?,
+            None => None,
+        };
+        let mut preferences: WeatherPreferences = serde_json::from_value(value)?;
+        if saved.is_none() && !preferences.location.is_empty() {
+            preferences.locations.push(preferences.current_location());
+            preferences.selected_location = Some(0);
+        }
+
+        Ok(preferences)
+    }
+
+    pub fn current_location(&self) -> LocationPoint {
+        LocationPoint {
+            location: self.location.clone(),
+            lat: self.lat,
+            lon: self.lon,
+        }
+    }
+
     pub fn set_from_location_point(&mut self, location_point: &LocationPoint) -> &Self {
         self.location = location_point.location.clone();
         self.lat = location_point.lat;
         self.lon = location_point.lon;
+        self.selected_location = self.locations.iter()
+            .position(|saved| saved.is_same_place(location_point));
 
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrates_single_location_file() {
+        let value = serde_json::json!({
+            "location": "Berlin, Germany",
+            "lat": 52.52,
+            "lon": 13.41,
+            "units": "Metric",
+        });
+        let preferences = WeatherPreferences::from_value(value).unwrap();
+
+        assert_eq!(preferences.locations.len(), 1);
+        assert_eq!(preferences.locations[0].location, "Berlin, Germany");
+        assert_eq!(preferences.locations[0].lat, 52.52);
+        assert_eq!(preferences.locations[0].lon, 13.41);
+        assert_eq!(preferences.selected_location, Some(0));
+    }
+
+    #[test]
+    fn keeps_emptied_location_list() {
+        let value = serde_json::json!({
+            "location": "Berlin, Germany",
+            "lat": 52.52,
+            "lon": 13.41,
+            "units": "Metric",
+            "locations": [],
+            "selected_location": null,
+        });
+        let preferences = WeatherPreferences::from_value(value).unwrap();
+
+        assert!(preferences.locations.is_empty());
+        assert_eq!(preferences.selected_location, None);
+    }
+}
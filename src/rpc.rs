@@ -1,14 +1,64 @@
 use super::api::weather::WeatherData;
-use super::api::location::LocationPoint;
+use super::api::error::WeatherError;
+use super::api::air_quality::AirQualityData;
+use super::api::pollen::{
+    Allergen,
+    PollenDay,
+};
+use super::api::marine::MarineData;
+use super::api::history::HistoricalDay;
+use super::api::map::MapLayer;
+use super::api::aviation::AviationReport;
+use super::api::models::ModelComparison;
+use super::api::station::StationReading;
+use super::api::location::{
+    GeocoderKind,
+    LocationPoint,
+};
 use super::api::units::Units;
+use super::api::provider::ProviderKind;
 use super::preferences::WeatherPreferences;
+use super::cache::CachedForecast;
+use super::accuracy::AccuracyReport;
 
 pub enum WeatherUpdate {
-    Data(Option<WeatherData>),
+    Data(Result<WeatherData, WeatherError>),
+    Cached(CachedForecast, bool),
+    AirQuality(Result<AirQualityData, WeatherError>),
+    Pollen(Result<Vec<PollenDay>, WeatherError>),
+    Marine(Result<Option<MarineData>, WeatherError>),
     Location(Option<String>),
     SearchLocations(String),
-    SetLocations(Option<Vec<LocationPoint>>),
+    Locate,
+    SelectLocation(usize),
+    SaveLocation,
+    RemoveLocation,
+    MoveLocation(i32),
+    SetLocations(Result<Vec<LocationPoint>, WeatherError>),
+    SearchHistory(time::Date),
+    SetHistory(Result<HistoricalDay, WeatherError>),
+    Yesterday(Result<HistoricalDay, WeatherError>),
     SavePreferences(WeatherPreferences),
     SetUnits(Units),
+    SetProvider(ProviderKind),
+    SetFallbacks(Vec<ProviderKind>),
+    SetGeocoder(GeocoderKind),
+    SetApiKey(Option<String>),
+    SetCacheTtl(u32),
+    SetAllergens(Vec<Allergen>),
+    PanMap(f64, f64),
+    /// Shows the weather at a latitude and longitude clicked on the map
+    PickLocation(f64, f64),
+    ZoomMap(i8),
+    SetMapLayer(MapLayer),
+    SetAerodrome(Option<String>),
+    Aviation(Result<AviationReport, WeatherError>),
+    Models(Result<ModelComparison, WeatherError>),
+    Accuracy(AccuracyReport),
+    SetStationPort(Option<u16>),
+    Station(StationReading),
+    /// Shows the cached forecast for the current location, fetching it once stale
     Refresh,
+    /// Fetches the forecast for the current location even when the cached one is fresh
+    Reload,
 }
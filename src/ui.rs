@@ -1,10 +1,23 @@
 mod daily;
 mod alert;
 mod hourly;
+mod minutely;
+mod air_quality;
+mod marine;
+mod history;
+mod map;
+mod aviation;
+mod models;
+mod accuracy;
+mod station;
 
 use std::sync::{Arc, Mutex, Weak};
 use std::path::{Path, PathBuf};
 use std::env::current_dir;
+use std::time::{
+    Duration,
+    Instant,
+};
 use core::future::Future;
 
 use gtk::prelude::*;
@@ -20,6 +33,8 @@ use gtk::{
     Entry,
     Button,
     Switch,
+    CheckButton,
+    SpinButton,
     Stack,
     ComboBoxText,
     ListStore,
@@ -28,36 +43,140 @@ use gtk::{
 };
 use flume::Sender;
 use super::preferences::WeatherPreferences;
+use super::cache::{
+    CachedForecast,
+    DEFAULT_CACHE_TTL_MINUTES,
+};
+use super::accuracy::ForecastAccuracy;
 use super::api::{
     weather::*,
     location::*,
     units::Units,
+    error::WeatherError,
+    air_quality::{
+        AirQualityData,
+        get_air_quality,
+    },
+    pollen::{
+        Allergen,
+        PollenDay,
+        get_pollen,
+    },
+    marine::{
+        MarineData,
+        get_marine,
+    },
+    history::{
+        HistoricalDay,
+        get_history,
+    },
+    map::{
+        MapLayer,
+        DEFAULT_BASE_TILE_URL,
+        DEFAULT_LAYER_TILE_URL,
+    },
+    models::get_model_comparison,
+    geoclue::get_device_location,
+    station::{
+        StationReading,
+        StationServer,
+        DEFAULT_STATION_PORT,
+    },
+    aviation::{
+        get_aviation,
+        DEFAULT_METAR_URL,
+        DEFAULT_TAF_URL,
+    },
+    provider::{
+        WeatherProvider,
+        ProviderKind,
+        create_chain,
+        default_provider,
+        fallback::ProviderHealth,
+    },
 };
 use alert::WeatherAlerts;
 use daily::DailyView;
 use hourly::HourlyView;
+use minutely::MinutelyView;
+use air_quality::AirQualityView;
+use marine::MarineView;
+use history::{
+    HistoryView,
+    calendar_date,
+    comparison_text,
+};
+use map::MapView;
+use aviation::AviationView;
+use models::ModelsView;
+use accuracy::AccuracyView;
+use station::station_markup;
 use super::rpc::WeatherUpdate;
 
+/// How often the forecast shown is checked against the refresh interval
+const STALE_CHECK_SECONDS: u32 = 60;
+
 pub struct WeatherApplication {
     active: bool,
     sender: Option<Sender<WeatherUpdate>>,
     mutex: Option<Weak<Mutex<Self>>>,
     units_switch: Switch,
+    provider_select: ComboBoxText,
+    fallback_checks: Vec<(ProviderKind, CheckButton)>,
+    geocoder_select: ComboBoxText,
+    api_key_entry: Entry,
+    cache_ttl: SpinButton,
+    allergen_checks: Vec<(Allergen, CheckButton)>,
+    station_switch: Switch,
+    station_port: SpinButton,
+    station_server: Option<StationServer>,
+    station: Option<StationReading>,
+    station_label: Label,
     location: EditableLabel,
     location_search: Entry,
     location_search_button: Button,
+    locate_button: Button,
+    location_switcher: ComboBoxText,
+    save_location_button: Button,
+    remove_location_button: Button,
+    move_location_buttons: Vec<(i32, Button)>,
     location_results: ComboBoxText,
     refresh_button: Button,
     temperature: Label,
     feels_like: Label,
+    comparison_label: Label,
+    updated_label: Label,
+    source_label: Label,
+    retry_button: Button,
+    retry_search: Option<String>,
     current_details: Label,
     current_picture: Picture,
     alerts: WeatherAlerts,
     daily: DailyView,
     hourly: HourlyView,
+    minutely: MinutelyView,
+    air_quality: AirQualityView,
+    pollen: Vec<PollenDay>,
+    marine: MarineView,
+    marine_page: CenterBox,
+    marine_button: Button,
+    history: HistoryView,
+    current: Option<CurrentWeather>,
+    /// Yesterday at the location being shown, compared with the current conditions once both arrive
+    yesterday: Option<HistoricalDay>,
+    /// When the forecast was last requested, and how many requests in a row have failed since
+    last_fetch: Option<Instant>,
+    fetch_failures: u32,
+    map: MapView,
+    aviation: AviationView,
+    models: ModelsView,
+    accuracy: AccuracyView,
     stack_view: Arc<Mutex<Stack>>,
     stack_buttons_container: CenterBox,
     preferences: Option<WeatherPreferences>,
+    provider: Arc<dyn WeatherProvider>,
+    provider_health: Arc<ProviderHealth>,
+    geocoder: Arc<dyn Geocoder>,
 }
 
 pub fn icon_path(icon: Option<String>) -> PathBuf {
@@ -70,6 +189,31 @@ pub fn icon_path(icon: Option<String>) -> PathBuf {
     Path::new(&path).to_path_buf()
 }
 
+/// Scores the new forecast against past ones in its own task, so the forecast is shown without waiting
+fn request_accuracy(sender: Sender<WeatherUpdate>, interest: LocationPoint, data: WeatherData) {
+    gtk::glib::MainContext::default().spawn_local(async move {
+        let report = ForecastAccuracy::record(&interest, &data);
+        if let Err(err) = sender.send_async(WeatherUpdate::Accuracy(report)).await {
+            println!("Unable to send forecast accuracy: {}", err);
+        }
+    });
+}
+
+/// Names coordinates after the place there, falling back to the coordinates themselves
+async fn name_coordinates(geocoder: Arc<dyn Geocoder>, lat: f64, lon: f64) -> LocationPoint {
+    match reverse_geocode(geocoder.as_ref(), lat, lon).await {
+        Ok(point) => point,
+        Err(err) => {
+            println!("Unable to name {}, {}: {}", lat, lon, err);
+            LocationPoint {
+                lat,
+                lon,
+                location: coordinates_label(lat, lon),
+            }
+        },
+    }
+}
+
 fn current_picture_path(current: Option<&CurrentWeather>) -> PathBuf {
     let path = if current.is_some() && current.unwrap().status.len() > 0 {
         icon_path(Some(current.unwrap().status[0].icon.clone()))
@@ -84,6 +228,10 @@ impl WeatherApplication {
     pub fn new(window: &ApplicationWindow) -> Self {
         let temperature = Label::new(None);
         let feels_like = Label::new(None);
+        let comparison_label = Label::new(None);
+        comparison_label.set_visible(false);
+        let station_label = Label::new(None);
+        station_label.set_visible(false);
         let location = EditableLabel::new("");
         location.set_visible(false);
 
@@ -91,6 +239,8 @@ impl WeatherApplication {
 
         let location_search = Entry::new();
         let location_search_button = Button::from_icon_name(Some("edit-find"));
+        let locate_button = Button::from_icon_name(Some("find-location"));
+        locate_button.set_tooltip_text(Some("Use my location"));
         let location_results = ComboBoxText::new();
         location_results.set_visible(false);
         location_results.set_id_column(0);
@@ -105,10 +255,42 @@ impl WeatherApplication {
         location_box.append(&location_search);
         location_box.append(&location_results);
         location_box.append(&location_search_button);
+        location_box.append(&locate_button);
         location_box.append(&refresh_button);
 
         let action_bar = ActionBar::new();
         action_bar.set_center_widget(Some(&location_box));
+
+        // Switches between saved locations, managed from the popover beside it
+        let location_switcher = ComboBoxText::new();
+        location_switcher.set_visible(false);
+        action_bar.pack_start(&location_switcher);
+
+        let saved_locations_container = gtk::Box::new(gtk::Orientation::Vertical, 10);
+        let saved_locations_title = Label::new(None);
+        saved_locations_title.set_markup("<b>Saved locations</b>");
+        saved_locations_container.append(&saved_locations_title);
+
+        let save_location_button = Button::with_label("Save this location");
+        saved_locations_container.append(&save_location_button);
+        let remove_location_button = Button::with_label("Remove this location");
+        saved_locations_container.append(&remove_location_button);
+        let move_location_buttons = vec![
+            (-1, Button::with_label("Move up")),
+            (1, Button::with_label("Move down")),
+        ];
+        for (_, button) in move_location_buttons.iter() {
+            saved_locations_container.append(button);
+        }
+
+        let saved_locations_popover = Popover::new();
+        saved_locations_popover.set_child(Some(&saved_locations_container));
+        saved_locations_popover.set_autohide(true);
+
+        let saved_locations_menu = MenuButton::new();
+        saved_locations_menu.set_icon_name("starred");
+        saved_locations_menu.set_popover(Some(&saved_locations_popover));
+        action_bar.pack_start(&saved_locations_menu);
         
         let preferences_container = gtk::Box::new(gtk::Orientation::Vertical, 10);
         let preferences_title = Label::new(None);
@@ -122,6 +304,82 @@ impl WeatherApplication {
         units_container.append(&Label::new(Some("Metric")));
         preferences_container.append(&units_container);
 
+        let provider_title = Label::new(None);
+        provider_title.set_markup("<b>Provider</b>");
+        preferences_container.append(&provider_title);
+
+        let provider_select = ComboBoxText::new();
+        for kind in ProviderKind::ALL.iter() {
+            provider_select.append(Some(kind.id()), &kind.to_string());
+        }
+        provider_select.set_active_id(Some(ProviderKind::default().id()));
+        preferences_container.append(&provider_select);
+
+        let fallback_title = Label::new(None);
+        fallback_title.set_markup("<b>Fall back to</b>");
+        preferences_container.append(&fallback_title);
+
+        let fallback_checks = ProviderKind::SOURCES.iter()
+            .map(|kind| (*kind, CheckButton::with_label(&kind.to_string())))
+            .collect::<Vec<_>>();
+        for (_, check) in fallback_checks.iter() {
+            preferences_container.append(check);
+        }
+
+        let geocoder_title = Label::new(None);
+        geocoder_title.set_markup("<b>Location search</b>");
+        preferences_container.append(&geocoder_title);
+
+        let geocoder_select = ComboBoxText::new();
+        for kind in GeocoderKind::ALL.iter() {
+            geocoder_select.append(Some(kind.id()), &kind.to_string());
+        }
+        geocoder_select.set_active_id(Some(GeocoderKind::default().id()));
+        preferences_container.append(&geocoder_select);
+
+        let api_key_title = Label::new(None);
+        api_key_title.set_markup("<b>OpenWeather API key</b>");
+        preferences_container.append(&api_key_title);
+
+        let api_key_entry = Entry::new();
+        api_key_entry.set_visibility(false);
+        api_key_entry.set_placeholder_text(Some("Required for OpenWeather"));
+        preferences_container.append(&api_key_entry);
+
+        let cache_ttl_title = Label::new(None);
+        cache_ttl_title.set_markup("<b>Refresh after (minutes)</b>");
+        preferences_container.append(&cache_ttl_title);
+
+        let cache_ttl = SpinButton::with_range(5.0, 24.0 * 60.0, 5.0);
+        cache_ttl.set_value(DEFAULT_CACHE_TTL_MINUTES as f64);
+        preferences_container.append(&cache_ttl);
+
+        let allergens_title = Label::new(None);
+        allergens_title.set_markup("<b>Pollen allergies</b>");
+        preferences_container.append(&allergens_title);
+
+        let allergen_checks = Allergen::ALL.iter()
+            .map(|allergen| (*allergen, CheckButton::with_label(&allergen.to_string())))
+            .collect::<Vec<_>>();
+        for (_, check) in allergen_checks.iter() {
+            check.set_active(true);
+            preferences_container.append(check);
+        }
+
+        let station_title = Label::new(None);
+        station_title.set_markup("<b>Weather station</b>");
+        preferences_container.append(&station_title);
+
+        // Stations on the local network upload to this port (Ecowitt or Weather Underground protocol)
+        let station_container = gtk::Box::new(gtk::Orientation::Horizontal, 5);
+        let station_switch = Switch::new();
+        let station_port = SpinButton::with_range(1024.0, 65535.0, 1.0);
+        station_port.set_value(DEFAULT_STATION_PORT as f64);
+        station_container.append(&station_switch);
+        station_container.append(&Label::new(Some("Listen on port")));
+        station_container.append(&station_port);
+        preferences_container.append(&station_container);
+
         let preferences_popover = Popover::new();
         preferences_popover.set_child(Some(&preferences_container));
         preferences_popover.set_autohide(true);
@@ -140,6 +398,16 @@ impl WeatherApplication {
         chbox.set_center_widget(Some(&hbox));
 
 
+        let updated_label = Label::new(None);
+        updated_label.set_visible(false);
+        let source_label = Label::new(None);
+        source_label.set_visible(false);
+
+        let retry_button = Button::with_label("Retry");
+        retry_button.set_visible(false);
+        let retry_container = CenterBox::new();
+        retry_container.set_center_widget(Some(&retry_button));
+
         let current_details = Label::new(None);
 
         let alerts_container = CenterBox::new();
@@ -156,6 +424,41 @@ impl WeatherApplication {
         let hourly_container = CenterBox::new();
         hourly_container.set_center_widget(Some(&hourly.container));
 
+        let minutely = MinutelyView::new();
+        minutely.set_visible(false);
+        let minutely_container = CenterBox::new();
+        minutely_container.set_center_widget(Some(&minutely.container));
+
+        let air_quality = AirQualityView::new();
+        let air_quality_container = CenterBox::new();
+        air_quality_container.set_center_widget(Some(&air_quality.container));
+
+        // Only shown once the location turns out to have marine data
+        let marine = MarineView::new();
+        let marine_container = CenterBox::new();
+        marine_container.set_center_widget(Some(&marine.container));
+        marine_container.set_visible(false);
+
+        let history = HistoryView::new();
+        let history_container = CenterBox::new();
+        history_container.set_center_widget(Some(&history.container));
+
+        let map = MapView::new();
+        let map_container = CenterBox::new();
+        map_container.set_center_widget(Some(&map.container));
+
+        let aviation = AviationView::new();
+        let aviation_container = CenterBox::new();
+        aviation_container.set_center_widget(Some(&aviation.container));
+
+        let models = ModelsView::new();
+        let models_container = CenterBox::new();
+        models_container.set_center_widget(Some(&models.container));
+
+        let accuracy = AccuracyView::new();
+        let accuracy_container = CenterBox::new();
+        accuracy_container.set_center_widget(Some(&accuracy.container));
+
         let stack = Stack::new();
         stack.set_vhomogeneous(false);
         stack.set_interpolate_size(true);
@@ -163,11 +466,20 @@ impl WeatherApplication {
         let stack_pages = vec![
             stack.add_titled(&alerts_container, Some("alerts"), "Alerts"),
             stack.add_titled(&current_details, Some("current"), "Currently"),
+            stack.add_titled(&minutely_container, Some("minutely"), "Next hour"),
             stack.add_titled(&hourly_container, Some("hourly"), "Hourly"),
             stack.add_titled(&daily_container, Some("daily"), "Weekly"),
+            stack.add_titled(&air_quality_container, Some("air_quality"), "Air quality"),
+            stack.add_titled(&marine_container, Some("marine"), "Marine"),
+            stack.add_titled(&history_container, Some("history"), "History"),
+            stack.add_titled(&map_container, Some("map"), "Map"),
+            stack.add_titled(&models_container, Some("models"), "Models"),
+            stack.add_titled(&accuracy_container, Some("accuracy"), "Accuracy"),
+            stack.add_titled(&aviation_container, Some("aviation"), "Aviation"),
         ];
         let stack_buttons = gtk::Box::new(gtk::Orientation::Horizontal, 0);
         let stack_view = &Arc::new(Mutex::new(stack));
+        let mut marine_button = None;
         for stack_page in stack_pages.iter() {
             let stack_button = Button::new();
             if let Some(title) = stack_page.title() {
@@ -175,6 +487,10 @@ impl WeatherApplication {
             }
             let stack_view_arc = stack_view.clone();
             let name = stack_page.name().clone().unwrap();
+            if name.as_str() == "marine" {
+                stack_button.set_visible(false);
+                marine_button = Some(stack_button.clone());
+            }
             stack_button.connect_clicked(move |_| {
                 if let Ok(stack_view) = stack_view_arc.try_lock() {
                     if let Some(page) = stack_view.child_by_name(&name) {
@@ -184,6 +500,7 @@ impl WeatherApplication {
             });
             stack_buttons.append(&stack_button);
         }
+        let marine_button = marine_button.expect("Marine stack page has a button");
         let stack_buttons_container = CenterBox::new();
         stack_buttons_container.set_center_widget(Some(&stack_buttons));
 
@@ -191,6 +508,11 @@ impl WeatherApplication {
         vbox.append(&action_bar);
         vbox.append(&chbox);
         vbox.append(&feels_like);
+        vbox.append(&comparison_label);
+        vbox.append(&station_label);
+        vbox.append(&updated_label);
+        vbox.append(&source_label);
+        vbox.append(&retry_container);
         vbox.append(&stack_buttons_container);
 
         if let Ok(stack_view) = stack_view.try_lock() {
@@ -205,21 +527,60 @@ impl WeatherApplication {
             location,
             location_search,
             location_search_button,
+            locate_button,
+            location_switcher,
+            save_location_button,
+            remove_location_button,
+            move_location_buttons,
             location_results,
             refresh_button,
             feels_like,
+            comparison_label,
+            updated_label,
+            source_label,
+            retry_button,
+            retry_search: None,
             current_picture,
             current_details,
             units_switch,
+            provider_select,
+            fallback_checks,
+            geocoder_select,
+            api_key_entry,
+            cache_ttl,
+            allergen_checks,
+            station_switch,
+            station_port,
+            station_server: None,
+            station: None,
+            station_label,
             alerts,
             daily,
             hourly,
+            minutely,
+            air_quality,
+            pollen: Vec::new(),
+            marine,
+            marine_page: marine_container,
+            marine_button,
+            history,
+            current: None,
+            yesterday: None,
+            last_fetch: None,
+            fetch_failures: 0,
+            map,
+            aviation,
+            models,
+            accuracy,
             stack_view: stack_view.clone(),
             stack_buttons_container,
             active: true,
             sender: None, 
             mutex: None,
             preferences: None,
+            provider: default_provider(),
+            provider_health: Arc::new(ProviderHealth::new()),
+            geocoder: GeocoderKind::default().create(),
         };
     
         wa
@@ -253,7 +614,26 @@ impl WeatherApplication {
                 Units::Imperial => false,
             };
             self.units_switch.set_state(units_state);
+            self.provider_select.set_active_id(Some(preferences.provider.id()));
+            self.geocoder_select.set_active_id(Some(preferences.geocoder.id()));
+            if let Some(api_key) = &preferences.open_weather_api_key {
+                self.api_key_entry.set_text(api_key);
+            }
+            for (kind, check) in self.fallback_checks.iter() {
+                check.set_active(preferences.fallback_providers.contains(kind));
+            }
+            self.cache_ttl.set_value(preferences.cache_ttl_minutes as f64);
+            for (allergen, check) in self.allergen_checks.iter() {
+                check.set_active(preferences.tracked_allergens.contains(allergen));
+            }
+            self.map.layer_select.set_active_id(Some(preferences.map_layer.id()));
+            self.aviation.set_station(preferences.aviation_station.as_deref());
+            if let Some(port) = preferences.station_port {
+                self.station_port.set_value(port as f64);
+            }
+            self.station_switch.set_state(preferences.station_port.is_some());
         }
+        self.update_saved_locations();
 
         let mutex_units = mutex.clone();
         self.units_switch.connect_state_notify(move |switch| {
@@ -273,6 +653,110 @@ impl WeatherApplication {
             }
         });
 
+        let mutex_provider = mutex.clone();
+        self.provider_select.connect_changed(move |combo| {
+            let kind = combo.active_id()
+                .and_then(|id| ProviderKind::from_id(&id));
+            if let Some(kind) = kind {
+                if let Ok(app) = mutex_provider.upgrade().unwrap().try_lock() {
+                    if let Err(err) = app.get_sender().send(WeatherUpdate::SetProvider(kind)) {
+                        println!("Unable to update provider: {}", err);
+                    }
+                    if let Err(err) = app.get_sender().send(WeatherUpdate::Refresh) {
+                        println!("Unable to refresh weather after provider changed: {}", err);
+                    }
+                }
+            }
+        });
+
+        self.provider = self.create_provider();
+
+        let mutex_geocoder = mutex.clone();
+        self.geocoder_select.connect_changed(move |combo| {
+            let kind = combo.active_id()
+                .and_then(|id| GeocoderKind::from_id(&id));
+            if let Some(kind) = kind {
+                if let Ok(app) = mutex_geocoder.upgrade().unwrap().try_lock() {
+                    if let Err(err) = app.get_sender().send(WeatherUpdate::SetGeocoder(kind)) {
+                        println!("Unable to update location search: {}", err);
+                    }
+                }
+            }
+        });
+        self.geocoder = self.get_geocoder_kind().create();
+
+        for (_, check) in self.fallback_checks.iter() {
+            let mutex_fallback = mutex.clone();
+            check.connect_toggled(move |_| {
+                if let Ok(app) = mutex_fallback.upgrade().unwrap().try_lock() {
+                    let fallbacks = app.get_fallback_providers();
+                    if let Err(err) = app.get_sender().send(WeatherUpdate::SetFallbacks(fallbacks)) {
+                        println!("Unable to update fallback providers: {}", err);
+                    }
+                }
+            });
+        }
+
+        for (_, check) in self.allergen_checks.iter() {
+            let mutex_allergen = mutex.clone();
+            check.connect_toggled(move |_| {
+                if let Ok(app) = mutex_allergen.upgrade().unwrap().try_lock() {
+                    let allergens = app.get_tracked_allergens();
+                    if let Err(err) = app.get_sender().send(WeatherUpdate::SetAllergens(allergens)) {
+                        println!("Unable to update tracked allergens: {}", err);
+                    }
+                }
+            });
+        }
+
+        let mutex_api_key = mutex.clone();
+        self.api_key_entry.connect_activate(move |entry| {
+            let api_key = entry.text().trim().to_string();
+            let api_key = if api_key.is_empty() {
+                None
+            } else {
+                Some(api_key)
+            };
+            if let Ok(app) = mutex_api_key.upgrade().unwrap().try_lock() {
+                if let Err(err) = app.get_sender().send(WeatherUpdate::SetApiKey(api_key)) {
+                    println!("Unable to update API key: {}", err);
+                }
+                // Fetch even if the cached forecast is fresh, so the new key is tried straight away
+                if let Err(err) = app.get_sender().send(WeatherUpdate::Reload) {
+                    println!("Unable to refresh weather after API key changed: {}", err);
+                }
+            }
+        });
+
+        let mutex_cache_ttl = mutex.clone();
+        self.cache_ttl.connect_value_changed(move |spin| {
+            let minutes = spin.value_as_int() as u32;
+            if let Ok(app) = mutex_cache_ttl.upgrade().unwrap().try_lock() {
+                if let Err(err) = app.get_sender().send(WeatherUpdate::SetCacheTtl(minutes)) {
+                    println!("Unable to update refresh interval: {}", err);
+                }
+            }
+        });
+
+        let mutex_station_switch = mutex.clone();
+        self.station_switch.connect_state_notify(move |_| {
+            if let Ok(app) = mutex_station_switch.upgrade().unwrap().try_lock() {
+                if let Err(err) = app.get_sender().send(WeatherUpdate::SetStationPort(app.get_station_port())) {
+                    println!("Unable to update weather station: {}", err);
+                }
+            }
+        });
+
+        let mutex_station_port = mutex.clone();
+        self.station_port.connect_value_changed(move |_| {
+            if let Ok(app) = mutex_station_port.upgrade().unwrap().try_lock() {
+                if let Err(err) = app.get_sender().send(WeatherUpdate::SetStationPort(app.get_station_port())) {
+                    println!("Unable to update weather station port: {}", err);
+                }
+            }
+        });
+        self.listen_for_station();
+
         let mutex_location = mutex.clone();
         self.location.connect_editing_notify(move |l| {
             if !l.is_editing() {
@@ -299,6 +783,58 @@ impl WeatherApplication {
             }
         });
 
+        let mutex_locate = mutex.clone();
+        self.locate_button.connect_clicked(move |_| {
+            if let Ok(app) = mutex_locate.upgrade().unwrap().try_lock() {
+                if let Err(err) = app.get_sender().send(WeatherUpdate::Locate) {
+                    println!("Unable to request device location: {}", err);
+                }
+            }
+        });
+
+        let mutex_switcher = mutex.clone();
+        self.location_switcher.connect_changed(move |combo| {
+            let index = combo.active_id()
+                .and_then(|id| id.parse::<usize>().ok());
+            if let Some(index) = index {
+                if let Ok(app) = mutex_switcher.upgrade().unwrap().try_lock() {
+                    if let Err(err) = app.get_sender().send(WeatherUpdate::SelectLocation(index)) {
+                        println!("Unable to switch location: {}", err);
+                    }
+                }
+            }
+        });
+
+        let mutex_save_location = mutex.clone();
+        self.save_location_button.connect_clicked(move |_| {
+            if let Ok(app) = mutex_save_location.upgrade().unwrap().try_lock() {
+                if let Err(err) = app.get_sender().send(WeatherUpdate::SaveLocation) {
+                    println!("Unable to save location: {}", err);
+                }
+            }
+        });
+
+        let mutex_remove_location = mutex.clone();
+        self.remove_location_button.connect_clicked(move |_| {
+            if let Ok(app) = mutex_remove_location.upgrade().unwrap().try_lock() {
+                if let Err(err) = app.get_sender().send(WeatherUpdate::RemoveLocation) {
+                    println!("Unable to remove location: {}", err);
+                }
+            }
+        });
+
+        for (delta, button) in self.move_location_buttons.iter() {
+            let mutex_move_location = mutex.clone();
+            let delta = *delta;
+            button.connect_clicked(move |_| {
+                if let Ok(app) = mutex_move_location.upgrade().unwrap().try_lock() {
+                    if let Err(err) = app.get_sender().send(WeatherUpdate::MoveLocation(delta)) {
+                        println!("Unable to move location: {}", err);
+                    }
+                }
+            });
+        }
+
         let mutex_combo = mutex.clone();
         self.location_results.connect_changed(move |combo| {
             if let Some(active_iter) = combo.active_iter() {
@@ -331,26 +867,122 @@ impl WeatherApplication {
             }
         });
 
+        let mutex_history = mutex.clone();
+        self.history.calendar.connect_day_selected(move |calendar| {
+            if let Some(date) = calendar_date(calendar) {
+                if let Ok(app) = mutex_history.upgrade().unwrap().try_lock() {
+                    if let Err(err) = app.get_sender().send(WeatherUpdate::SearchHistory(date)) {
+                        println!("Unable to request historical weather: {}", err);
+                    }
+                }
+            }
+        });
+
+        let mutex_map_layer = mutex.clone();
+        self.map.layer_select.connect_changed(move |combo| {
+            let layer = combo.active_id()
+                .and_then(|id| MapLayer::from_id(&id));
+            if let Some(layer) = layer {
+                if let Ok(app) = mutex_map_layer.upgrade().unwrap().try_lock() {
+                    if let Err(err) = app.get_sender().send(WeatherUpdate::SetMapLayer(layer)) {
+                        println!("Unable to update map layer: {}", err);
+                    }
+                }
+            }
+        });
+
+        for (button, delta) in [(&self.map.zoom_in, 1), (&self.map.zoom_out, -1)].iter() {
+            let mutex_zoom = mutex.clone();
+            let delta = *delta;
+            button.connect_clicked(move |_| {
+                if let Ok(app) = mutex_zoom.upgrade().unwrap().try_lock() {
+                    if let Err(err) = app.get_sender().send(WeatherUpdate::ZoomMap(delta)) {
+                        println!("Unable to zoom map: {}", err);
+                    }
+                }
+            });
+        }
+
+        let mutex_pan = mutex.clone();
+        self.map.drag.connect_drag_end(move |_, dx, dy| {
+            if let Ok(app) = mutex_pan.upgrade().unwrap().try_lock() {
+                if let Err(err) = app.get_sender().send(WeatherUpdate::PanMap(dx, dy)) {
+                    println!("Unable to pan map: {}", err);
+                }
+            }
+        });
+
+        let mutex_pick = mutex.clone();
+        self.map.click.connect_released(move |_, _, x, y| {
+            if let Ok(app) = mutex_pick.upgrade().unwrap().try_lock() {
+                if let Some((lat, lon)) = app.map.coordinates_at(x, y) {
+                    if let Err(err) = app.get_sender().send(WeatherUpdate::PickLocation(lat, lon)) {
+                        println!("Unable to pick location: {}", err);
+                    }
+                }
+            }
+        });
+
+        let mutex_aerodrome = mutex.clone();
+        self.aviation.station_entry.connect_activate(move |_| {
+            if let Ok(app) = mutex_aerodrome.upgrade().unwrap().try_lock() {
+                let station = app.aviation.get_station();
+                if let Err(err) = app.get_sender().send(WeatherUpdate::SetAerodrome(station)) {
+                    println!("Unable to update aerodrome: {}", err);
+                }
+            }
+        });
+
         let mutex_refresh = mutex.clone();
         self.refresh_button.connect_clicked(move |_| {
             if let Ok(app) = mutex_refresh.upgrade().unwrap().try_lock() {
-                if let Err(err) = app.get_sender().send(WeatherUpdate::Refresh) {
+                if let Err(err) = app.get_sender().send(WeatherUpdate::Reload) {
                     println!("Unable to refresh weather: {}", err);
                     let _ = app.get_sender().send(WeatherUpdate::Location(None));
                 }
             }
         });
 
+        let mutex_retry = mutex.clone();
+        self.retry_button.connect_clicked(move |_| {
+            if let Ok(app) = mutex_retry.upgrade().unwrap().try_lock() {
+                let update = match &app.retry_search {
+                    Some(query) => WeatherUpdate::SearchLocations(query.clone()),
+                    None => WeatherUpdate::Reload,
+                };
+                if let Err(err) = app.get_sender().send(update) {
+                    println!("Unable to retry: {}", err);
+                }
+            }
+        });
+
         // must be set before request_weather
         self.mutex = Some(mutex);
 
+        // Refresh in the background once the forecast shown goes stale
+        let mutex_stale = self.get_mutex();
+        gtk::glib::timeout_add_seconds_local(STALE_CHECK_SECONDS, move || {
+            if let Some(mutex) = mutex_stale.upgrade() {
+                if let Ok(app) = mutex.try_lock() {
+                    if app.is_weather_stale() {
+                        if let Err(err) = app.get_sender().send(WeatherUpdate::Refresh) {
+                            println!("Unable to refresh stale weather: {}", err);
+                        }
+                    }
+                }
+                gtk::glib::Continue(true)
+            } else {
+                gtk::glib::Continue(false)
+            }
+        });
+
         // Load current weather if preferences set
-        if let Some(preferences) = &self.preferences {
-            self.request_weather(LocationPoint {
-                location: preferences.location.clone(),
-                lat: preferences.lat,
-                lon: preferences.lon,
-            });
+        let startup = self.preferences.as_ref()
+            .map(|preferences| preferences.current_location());
+        if let Some(interest) = startup {
+            self.map.set_location(interest.lat, interest.lon);
+            self.update_map();
+            self.show_weather(interest);
         } else {
             // No preferences set! Set ui state as no-location
             if let Ok(app) = self.get_mutex().clone().upgrade().unwrap().try_lock() {
@@ -362,53 +994,241 @@ impl WeatherApplication {
 
     }
 
-    fn refresh_weather(&self) {
-        if let Some(prefs) = &self.preferences {
-            self.request_weather(LocationPoint {
-                location: prefs.location.clone(),
-                lat: prefs.lat,
-                lon: prefs.lon,
-            });
+    fn refresh_weather(&mut self) {
+        if let Some(location) = self.preferences.as_ref().map(|prefs| prefs.current_location()) {
+            self.show_weather(location);
+        }
+    }
+
+    fn reload_weather(&mut self) {
+        if let Some(location) = self.preferences.as_ref().map(|prefs| prefs.current_location()) {
+            self.request_weather(location);
+        }
+    }
+
+    /// The cached forecast for a location, if fetched with the current units and provider
+    fn cached_forecast(&self, interest: &LocationPoint) -> Option<CachedForecast> {
+        CachedForecast::load(interest)
+            .filter(|cached| cached.matches(self.get_units(), self.get_provider_kind()))
+    }
+
+    /// Shows the cached forecast straight away, only fetching it again once stale
+    fn show_weather(&mut self, interest: LocationPoint) {
+        let stale = if let Some(cached) = self.cached_forecast(&interest) {
+            let stale = cached.is_stale(self.get_cache_ttl());
+            self.update_location(Some(interest.location.clone()));
+            self.update_cached_weather(cached, false);
+            stale
+        } else {
+            true
+        };
+        if stale {
+            self.request_weather(interest);
+        } else {
+            self.request_alongside(&interest);
         }
     }
 
-    fn request_weather(&self, interest: LocationPoint) {
-        let mutex = self.get_mutex().clone();
+    /// Failed fetches are retried less and less often, up to the refresh interval
+    fn retry_delay(&self) -> Duration {
+        let ttl = Duration::from_secs(self.get_cache_ttl() as u64 * 60);
+        Duration::from_secs(STALE_CHECK_SECONDS as u64)
+            .checked_mul(1 << self.fetch_failures.min(16))
+            .unwrap_or(ttl)
+            .min(ttl)
+    }
 
+    fn is_weather_stale(&self) -> bool {
+        if let Some(last_fetch) = self.last_fetch {
+            if last_fetch.elapsed() < self.retry_delay() {
+                return false;
+            }
+        }
+        self.preferences.as_ref()
+            .map(|prefs| self.cached_forecast(&prefs.current_location())
+                .map(|cached| cached.is_stale(self.get_cache_ttl()))
+                .unwrap_or(true))
+            .unwrap_or(false)
+    }
+
+    fn request_pollen(&self, lat: f64, lon: f64) {
+        let sender = self.get_sender();
         self.spawn_local(async move {
-            if let Ok(app) = mutex.upgrade().unwrap().try_lock() {
-                let sender = app.get_sender();
-
-                let new_prefs = WeatherPreferences {
-                    location: interest.location,
-                    lat: interest.lat,
-                    lon: interest.lon,
-                    units: app.get_units(),
-                };
-                let data = get_weather_data(
-                   app.get_units(),
-                   interest.lat, 
-                   interest.lon,
-                ).await;
+            let pollen = get_pollen(lat, lon).await;
+            if let Err(err) = sender.send_async(WeatherUpdate::Pollen(pollen)).await {
+                println!("Unable to send pollen: {}", err);
+            }
+        });
+    }
 
-                sender.send_async(WeatherUpdate::Data(data)).await.unwrap();
-                sender.send_async(WeatherUpdate::Location(Some(new_prefs.location.clone()))).await.unwrap();
-                if let Err(err) = sender.send_async(WeatherUpdate::SavePreferences(new_prefs)).await {
-                    println!("Unable to save preferences: {}", err);
+    fn request_marine(&self, units: Units, lat: f64, lon: f64) {
+        let sender = self.get_sender();
+        self.spawn_local(async move {
+            let marine = get_marine(units, lat, lon).await;
+            if let Err(err) = sender.send_async(WeatherUpdate::Marine(marine)).await {
+                println!("Unable to send marine conditions: {}", err);
+            }
+        });
+    }
+
+    fn request_models(&self, units: Units, lat: f64, lon: f64) {
+        let sender = self.get_sender();
+        self.spawn_local(async move {
+            let comparison = get_model_comparison(units, lat, lon).await;
+            if let Err(err) = sender.send_async(WeatherUpdate::Models(comparison)).await {
+                println!("Unable to send model comparison: {}", err);
+            }
+        });
+    }
+
+    fn request_air_quality(&self, lat: f64, lon: f64) {
+        let sender = self.get_sender();
+        let api_key = self.get_open_weather_api_key();
+        self.spawn_local(async move {
+            let air_quality = get_air_quality(api_key.as_deref(), lat, lon).await;
+            if let Err(err) = sender.send_async(WeatherUpdate::AirQuality(air_quality)).await {
+                println!("Unable to send air quality: {}", err);
+            }
+        });
+    }
+
+    /// Fetches everything shown alongside the forecast, also needed when a cached forecast is shown
+    fn request_alongside(&mut self, interest: &LocationPoint) {
+        let units = self.get_units();
+        self.request_aviation();
+        self.request_air_quality(interest.lat, interest.lon);
+        self.request_pollen(interest.lat, interest.lon);
+        self.request_marine(units, interest.lat, interest.lon);
+        self.request_models(units, interest.lat, interest.lon);
+        self.request_yesterday(units, interest.lat, interest.lon);
+    }
+
+    fn request_yesterday(&mut self, units: Units, lat: f64, lon: f64) {
+        // Yesterday at the previous location must not be compared with the new one
+        self.yesterday = None;
+        let sender = self.get_sender();
+        self.spawn_local(async move {
+            let yesterday = time::OffsetDateTime::now_utc().unix_timestamp() - 24 * 60 * 60;
+            if let Ok(date) = local_date_from(yesterday) {
+                let history = get_history(units, lat, lon, date).await;
+                if let Err(err) = sender.send_async(WeatherUpdate::Yesterday(history)).await {
+                    println!("Unable to send yesterday's weather: {}", err);
                 }
             }
         });
     }
 
+    /// Fetches the forecast and everything shown alongside it. The futures only hold the sender,
+    /// the application stays unlocked so the main loop can apply each update as it arrives
+    fn request_weather(&mut self, interest: LocationPoint) {
+        let sender = self.get_sender();
+        let provider = self.provider.clone();
+        self.request_alongside(&interest);
+        self.last_fetch = Some(Instant::now());
+
+        let units = self.get_units();
+        let provider_kind = self.get_provider_kind();
+        let locations = self.get_saved_locations();
+        let new_prefs = WeatherPreferences {
+            location: interest.location.clone(),
+            lat: interest.lat,
+            lon: interest.lon,
+            selected_location: locations.iter()
+                .position(|saved| saved.is_same_place(&interest)),
+            locations,
+            units,
+            provider: provider_kind,
+            fallback_providers: self.get_fallback_providers(),
+            geocoder: self.get_geocoder_kind(),
+            open_weather_api_key: self.get_open_weather_api_key(),
+            cache_ttl_minutes: self.get_cache_ttl(),
+            tracked_allergens: self.get_tracked_allergens(),
+            map_base_url: self.get_map_base_url(),
+            map_layer_url: self.get_map_layer_url(),
+            map_layer: self.map.get_layer(),
+            aviation_station: self.aviation.get_station(),
+            metar_url: self.get_metar_url(),
+            taf_url: self.get_taf_url(),
+            station_port: self.get_station_port(),
+        };
+
+        self.spawn_local(async move {
+            let data = get_weather_data(
+               provider.as_ref(),
+               units,
+               interest.lat, 
+               interest.lon,
+            ).await;
+
+            // Fall back to the last forecast for this location when offline
+            let update = match data {
+                Ok(data) => {
+                    CachedForecast::save(&interest, provider_kind, &data);
+                    request_accuracy(sender.clone(), interest.clone(), data.clone());
+                    WeatherUpdate::Data(Ok(data))
+                },
+                Err(err) => match CachedForecast::load(&interest) {
+                    Some(cached) if err.is_offline() && cached.units == units => {
+                        WeatherUpdate::Cached(cached, true)
+                    },
+                    _ => WeatherUpdate::Data(Err(err)),
+                },
+            };
+
+            sender.send_async(update).await.unwrap();
+            sender.send_async(WeatherUpdate::Location(Some(new_prefs.location.clone()))).await.unwrap();
+            if let Err(err) = sender.send_async(WeatherUpdate::SavePreferences(new_prefs)).await {
+                println!("Unable to save preferences: {}", err);
+            }
+        });
+    }
+
     pub fn update(&mut self, update: WeatherUpdate) {
         match update {
-            WeatherUpdate::Data(data) => self.update_weather(data),
+            WeatherUpdate::Data(data) => {
+                self.fetch_failures = if data.is_ok() { 0 } else { self.fetch_failures + 1 };
+                self.update_weather(data)
+            },
+            WeatherUpdate::Cached(cached, offline) => {
+                if offline {
+                    self.fetch_failures += 1;
+                }
+                self.update_cached_weather(cached, offline)
+            },
+            WeatherUpdate::AirQuality(air_quality) => self.update_air_quality(air_quality),
+            WeatherUpdate::Pollen(pollen) => self.update_pollen(pollen),
+            WeatherUpdate::Marine(marine) => self.update_marine(marine),
             WeatherUpdate::Location(location) => self.update_location(location),
             WeatherUpdate::SearchLocations(query) => self.search_location(query),
+            WeatherUpdate::Locate => self.locate(),
+            WeatherUpdate::SelectLocation(index) => self.select_location(index),
+            WeatherUpdate::SaveLocation => self.save_location(),
+            WeatherUpdate::RemoveLocation => self.remove_location(),
+            WeatherUpdate::MoveLocation(delta) => self.move_location(delta),
             WeatherUpdate::SetLocations(locations) => self.update_location_results(locations),
-            WeatherUpdate::SavePreferences(preferences) => self.save_preferences(&preferences),
+            WeatherUpdate::SearchHistory(date) => self.search_history(date),
+            WeatherUpdate::SetHistory(history) => self.update_history(history),
+            WeatherUpdate::Yesterday(history) => self.update_yesterday(history),
+            WeatherUpdate::SavePreferences(preferences) => self.save_preferences(preferences),
             WeatherUpdate::SetUnits(units) => self.update_units(units),
+            WeatherUpdate::SetProvider(kind) => self.update_provider(kind),
+            WeatherUpdate::SetFallbacks(kinds) => self.update_fallbacks(kinds),
+            WeatherUpdate::SetGeocoder(kind) => self.update_geocoder(kind),
+            WeatherUpdate::SetApiKey(api_key) => self.update_api_key(api_key),
+            WeatherUpdate::SetCacheTtl(minutes) => self.update_cache_ttl(minutes),
+            WeatherUpdate::SetAllergens(allergens) => self.update_allergens(allergens),
+            WeatherUpdate::PanMap(dx, dy) => self.pan_map(dx, dy),
+            WeatherUpdate::PickLocation(lat, lon) => self.pick_location(lat, lon),
+            WeatherUpdate::ZoomMap(delta) => self.zoom_map(delta),
+            WeatherUpdate::SetMapLayer(layer) => self.update_map_layer(layer),
+            WeatherUpdate::SetAerodrome(station) => self.update_aerodrome(station),
+            WeatherUpdate::Aviation(report) => self.aviation.populate(report),
+            WeatherUpdate::Models(comparison) => self.models.populate(comparison),
+            WeatherUpdate::Accuracy(report) => self.accuracy.populate(report),
+            WeatherUpdate::SetStationPort(port) => self.update_station_port(port),
+            WeatherUpdate::Station(reading) => self.update_station(reading),
             WeatherUpdate::Refresh => self.refresh_weather(),
+            WeatherUpdate::Reload => self.reload_weather(),
         }
     }
     
@@ -419,6 +1239,7 @@ impl WeatherApplication {
     fn update_daily_weather(&mut self, daily: Option<Vec<DailyWeather>>) {
         if let Some(daily) = daily {
             self.daily.populate(daily, &self.get_units());
+            self.daily.set_pollen(&self.pollen, &self.get_tracked_allergens());
             self.daily.set_visible(true);
         } else {
             self.daily.populate(Vec::new(), &self.get_units());
@@ -436,12 +1257,73 @@ impl WeatherApplication {
         }
     }
 
+    fn update_minutely_weather(&mut self, minutely: Option<(Vec<WeatherMinutely>, &str)>) {
+        if let Some((minutely, precipitation)) = minutely {
+            self.minutely.populate(minutely, precipitation, &self.get_units());
+            self.minutely.set_visible(true);
+        } else {
+            self.minutely.populate(Vec::new(), "", &self.get_units());
+            self.minutely.set_visible(false);
+        }
+    }
+
+    fn update_pollen(&mut self, pollen: Result<Vec<PollenDay>, WeatherError>) {
+        self.pollen = match pollen {
+            Ok(pollen) => pollen,
+            Err(err) => {
+                println!("Unable to get pollen: {}", err);
+                Vec::new()
+            },
+        };
+        self.daily.set_pollen(&self.pollen, &self.get_tracked_allergens());
+    }
+
+    fn update_allergens(&mut self, allergens: Vec<Allergen>) {
+        self.daily.set_pollen(&self.pollen, &allergens);
+        if let Some(prefs) = &mut self.preferences {
+            prefs.tracked_allergens = allergens;
+            prefs.save_config();
+        }
+    }
+
+    fn get_tracked_allergens(&self) -> Vec<Allergen> {
+        self.allergen_checks.iter()
+            .filter(|(_, check)| check.is_active())
+            .map(|(allergen, _)| *allergen)
+            .collect()
+    }
+
+    fn update_air_quality(&mut self, air_quality: Result<AirQualityData, WeatherError>) {
+        if let Err(err) = &air_quality {
+            println!("Unable to get air quality: {}", err);
+        }
+        self.air_quality.populate(air_quality);
+    }
+
+    /// Hides the marine page for locations without waves or sea temperature
+    fn update_marine(&mut self, marine: Result<Option<MarineData>, WeatherError>) {
+        let marine = match marine {
+            Ok(marine) => marine,
+            Err(err) => {
+                println!("Unable to get marine conditions: {}", err);
+                None
+            },
+        };
+        let visible = marine.is_some();
+        self.marine.populate(marine, &self.get_units());
+        self.marine_page.set_visible(visible);
+        self.marine_button.set_visible(visible);
+    }
+
     fn update_current_image(&mut self, current: Option<CurrentWeather>) {
         let picture_path = current_picture_path(current.as_ref());
         self.current_picture.set_filename(picture_path.to_str().unwrap());
     }
 
     fn update_current_weather(&mut self, current: Option<CurrentWeather>) {
+        self.retry_button.set_visible(false);
+        self.comparison_label.set_visible(false);
+        self.current = current.clone();
         if let Some(current) = current {
             let units = self.get_units();
             self.temperature.set_markup(&format!("<big>{}</big>", units.temperature_value(current.temp)));
@@ -455,7 +1337,7 @@ Visibility: {}
 Wind Speed: {}
 Precipitation: {}%
             ", 
-            current.time("[hour]:[minute]"), 
+            current.time("[hour]:[minute]").unwrap_or_else(|_| String::from("--")), 
             current.pressure, 
             current.humidity,
             current.uvi,
@@ -463,6 +1345,8 @@ Precipitation: {}%
             units.speed_value(current.wind_speed),
             current.pop * 100.00));
             self.update_current_image(Some(current));
+            self.render_station();
+            self.render_comparison();
             
         } else {
             self.temperature.set_markup("<big>Invalid Data</big>");
@@ -471,6 +1355,37 @@ Precipitation: {}%
         };
         
     }
+
+    /// Shows what went wrong, retrying either the search query or the weather for the current location
+    fn update_weather_error(&mut self, err: WeatherError, retry_search: Option<String>) {
+        let (hint, retry) = match &err {
+            WeatherError::Network(_) => ("Check your connection and try again", Some("Retry")),
+            WeatherError::Timeout => ("The service took too long to respond", Some("Retry")),
+            WeatherError::HttpStatus(status) if *status >= 500 => ("The service is having problems, try again later", Some("Retry")),
+            WeatherError::HttpStatus(_) => ("The request was refused by the service", Some("Retry")),
+            WeatherError::MissingApiKey => ("Add your OpenWeather API key in preferences, or choose another provider", None),
+            WeatherError::InvalidApiKey => ("OpenWeather rejected the API key, check it in preferences", Some("Retry with new key")),
+            WeatherError::RateLimited(_) => ("The service is receiving too many requests", Some("Retry later")),
+            WeatherError::Decode { .. } => ("The service returned data that could not be read", Some("Retry")),
+            WeatherError::InvalidTimestamp(_) => ("The service returned an invalid date or time", Some("Retry")),
+            WeatherError::Location(_) => ("Turn on location services, or search for your location", None),
+        };
+        let message = gtk::glib::markup_escape_text(&err.to_string());
+        self.temperature.set_markup(&format!("<big>{}</big>", message));
+        self.feels_like.set_markup(hint);
+        self.comparison_label.set_visible(false);
+        self.current = None;
+        self.update_current_image(None);
+        self.updated_label.set_visible(false);
+
+        self.retry_search = retry_search;
+        if let Some(retry) = retry {
+            self.retry_button.set_label(retry);
+            self.retry_button.set_visible(true);
+        } else {
+            self.retry_button.set_visible(false);
+        }
+    }
     
     fn update_alerts(&mut self, weather_alerts: Option<Vec<WeatherAlert>>) {
         if let Some(weather_alerts) = weather_alerts {
@@ -480,48 +1395,166 @@ Precipitation: {}%
         }
     }
 
-    fn update_weather(&mut self, weather: Option<WeatherData>) {
-        if let Some(weather) = weather {
-            let units = weather.units.expect("units");
-            self.update_units(units);
-            self.update_current_weather(Some(weather.current));
-            self.update_daily_weather(Some(weather.daily));
-            self.update_hourly_weather(Some(weather.hourly));
-            self.update_alerts(Some(weather.alerts));
+    fn update_weather(&mut self, weather: Result<WeatherData, WeatherError>) {
+        match weather {
+            Ok(weather) => {
+                self.updated_label.set_visible(false);
+                self.update_source(weather.provider.as_deref(), &weather.provenance);
+                let units = weather.units.expect("units");
+                self.update_units(units);
+                let precipitation = if weather.current.snow.is_some() {
+                    "Snow"
+                } else {
+                    "Rain"
+                };
+                self.update_minutely_weather(Some((weather.minutely, precipitation)));
+                self.update_current_weather(Some(weather.current));
+                self.update_daily_weather(Some(weather.daily));
+                self.update_hourly_weather(Some(weather.hourly));
+                self.update_alerts(Some(weather.alerts));
+            },
+            Err(err) => {
+                self.update_weather_error(err, None);
+                self.update_daily_weather(None);
+                self.update_hourly_weather(None);
+                self.update_minutely_weather(None);
+                self.update_alerts(None);
+            },
+        };
+    }
+
+    fn update_cached_weather(&mut self, cached: CachedForecast, offline: bool) {
+        let updated = if offline {
+            format!("<small>Offline, last updated {}</small>", cached.age_text())
         } else {
-            self.update_current_weather(None);
-            self.update_daily_weather(None);
-            self.update_hourly_weather(None);
-            self.update_alerts(None);
+            format!("<small>Updated {}</small>", cached.age_text())
         };
+        self.update_weather(Ok(cached.data));
+        self.updated_label.set_markup(&updated);
+        self.updated_label.set_visible(true);
     }
 
-    fn search_location(&self, search_query: String) {
+    fn search_location(&mut self, search_query: String) {
         let search_query = search_query.clone();
         if search_query.len() == 0 {
             return;
         }
+        self.retry_search = Some(search_query.clone());
         
-        let mutex = self.get_mutex();
+        self.location.set_visible(false);
+        self.location_search.set_visible(false); 
+        self.location_search_button.set_visible(false);
+        self.locate_button.set_visible(false);
 
+        let sender = self.get_sender();
+        let geocoder = self.geocoder.clone();
         self.spawn_local(async move {
-            match mutex.upgrade().unwrap().try_lock() {
-                Ok(app) => {
-                    app.location.set_visible(false);
-                    app.location_search.set_visible(false); 
-                    app.location_search_button.set_visible(false);
-
-                    let sender = app.get_sender();
-                    let locations = search_locations(&search_query).await;
-                    if let Err(_) = sender.send_async(WeatherUpdate::SetLocations(locations)).await {
-                        println!("Unable to send WeatherUpdate::SetLocations");
-                    }
-                }, 
-                Err(err) => println!("search_location err: {}", err),
+            // Typed coordinates are named after the place there rather than searched for
+            let locations = if let Some((lat, lon)) = parse_coordinates(&search_query) {
+                Ok(vec![name_coordinates(geocoder, lat, lon).await])
+            } else {
+                search_locations(geocoder.as_ref(), &search_query).await
+            };
+            if let Err(_) = sender.send_async(WeatherUpdate::SetLocations(locations)).await {
+                println!("Unable to send WeatherUpdate::SetLocations");
+            }
+        });
+    }
+
+    /// Finds the device's position with GeoClue and shows the weather there, named after the nearest place
+    fn locate(&mut self) {
+        self.retry_search = None;
+        self.location.set_visible(false);
+        self.location_search.set_visible(false);
+        self.location_search_button.set_visible(false);
+        self.locate_button.set_visible(false);
+
+        let sender = self.get_sender();
+        let geocoder = self.geocoder.clone();
+        self.spawn_local(async move {
+            let location = match get_device_location().await {
+                Ok((lat, lon)) => Ok(vec![name_coordinates(geocoder, lat, lon).await]),
+                Err(err) => Err(err),
+            };
+            if let Err(err) = sender.send_async(WeatherUpdate::SetLocations(location)).await {
+                println!("Unable to send device location: {}", err);
+            }
+        });
+    }
+
+    /// Shows the weather at a point picked on the map, named after the place there
+    fn pick_location(&mut self, lat: f64, lon: f64) {
+        self.retry_search = None;
+        self.location.set_visible(false);
+        self.location_search.set_visible(false);
+        self.location_search_button.set_visible(false);
+        self.locate_button.set_visible(false);
+
+        let sender = self.get_sender();
+        let geocoder = self.geocoder.clone();
+        self.spawn_local(async move {
+            let point = name_coordinates(geocoder, lat, lon).await;
+            if let Err(err) = sender.send_async(WeatherUpdate::SetLocations(Ok(vec![point]))).await {
+                println!("Unable to send picked location: {}", err);
+            }
+        });
+    }
+
+    /// Looks up the conditions on a past day at the current location
+    fn search_history(&mut self, date: time::Date) {
+        let prefs = if let Some(prefs) = &self.preferences {
+            prefs
+        } else {
+            self.history.set_message("Search for a location first");
+            return;
+        };
+        let today = time::OffsetDateTime::now_utc().date();
+        if date > today {
+            self.history.set_message("Pick a day in the past");
+            return;
+        }
+        self.history.set_loading(date);
+
+        // The weather request may be holding the application lock, so only the sender is used
+        let sender = self.get_sender();
+        let (units, lat, lon) = (prefs.units, prefs.lat, prefs.lon);
+        self.spawn_local(async move {
+            let history = get_history(units, lat, lon, date).await;
+            if let Err(err) = sender.send_async(WeatherUpdate::SetHistory(history)).await {
+                println!("Unable to send historical weather: {}", err);
             }
         });
     }
 
+    fn update_history(&mut self, history: Result<HistoricalDay, WeatherError>) {
+        self.history.populate(history, &self.get_units());
+    }
+
+    fn update_yesterday(&mut self, history: Result<HistoricalDay, WeatherError>) {
+        match history {
+            Ok(yesterday) => self.yesterday = Some(yesterday),
+            Err(err) => {
+                println!("Unable to get yesterday's weather: {}", err);
+                self.yesterday = None;
+            },
+        }
+        self.render_comparison();
+    }
+
+    /// Compares the current conditions with the same time yesterday, whichever arrived last
+    fn render_comparison(&self) {
+        let comparison = match (&self.current, &self.yesterday) {
+            (Some(current), Some(yesterday)) => comparison_text(current, yesterday, &self.get_units()),
+            _ => None,
+        };
+        if let Some(comparison) = comparison {
+            self.comparison_label.set_markup(&format!("<small>{}</small>", comparison));
+            self.comparison_label.set_visible(true);
+        } else {
+            self.comparison_label.set_visible(false);
+        }
+    }
+
     fn locations_to_store(locations: Vec<LocationPoint>) -> ListStore {
         let col_types: [gtk::glib::Type; 3] = [
             gtk::glib::Type::STRING, 
@@ -538,34 +1571,43 @@ Precipitation: {}%
         model
     }
 
-    fn update_location_results(&mut self, location_results: Option<Vec<LocationPoint>>) {
-        if let Some(location_results) = location_results {
-            let results_count = location_results.len();
-            let first_result = if results_count == 1 {
-                Some(location_results[0].clone())
-            } else {
-                None
-            };
-            let list_model = Self::locations_to_store(location_results);
-            self.location_results.set_model(Some(&list_model));
-            self.location_results.set_visible(true);
-
-            match results_count {
-                1 => {
-                    if let Some(first) = first_result {
-                        // Force change trigger
-                        self.location_results.set_active_id(Some(&first.location));
-                        self.request_weather(first);
-                    } 
-                }, 
-                _ => {
-                    self.location_results.popup();
-                },
-            }
-        } else {
-            if let Err(err) = self.get_sender().send(WeatherUpdate::Location(None)) {
-                println!("Unable to send WeatherUpdate::Location(None): {}", err);
-            }
+    fn update_location_results(&mut self, location_results: Result<Vec<LocationPoint>, WeatherError>) {
+        match location_results {
+            Ok(location_results) => {
+                let results_count = location_results.len();
+                let first_result = if results_count == 1 {
+                    Some(location_results[0].clone())
+                } else {
+                    None
+                };
+                let list_model = Self::locations_to_store(location_results);
+                self.location_results.set_model(Some(&list_model));
+                self.location_results.set_visible(true);
+
+                match results_count {
+                    0 => {
+                        self.update_location(None);
+                        self.temperature.set_markup("<big>No places found</big>");
+                        self.feels_like.set_markup(&format!("{} found nothing, try another search or location service",
+                            gtk::glib::markup_escape_text(self.geocoder.name())));
+                    },
+                    1 => {
+                        if let Some(first) = first_result {
+                            // Force change trigger
+                            self.location_results.set_active_id(Some(&first.location));
+                            self.request_weather(first);
+                        } 
+                    }, 
+                    _ => {
+                        self.location_results.popup();
+                    },
+                }
+            },
+            Err(err) => {
+                self.update_location(None);
+                let retry_search = self.retry_search.clone();
+                self.update_weather_error(err, retry_search);
+            },
         }
     }
 
@@ -575,6 +1617,7 @@ Precipitation: {}%
             self.location_search.set_visible(false);
             self.location_results.set_visible(false);
             self.location_search_button.set_visible(false);
+            self.locate_button.set_visible(false);
             self.refresh_button.set_visible(true);
             self.daily.set_visible(true);
             self.location.set_text(&location);
@@ -584,12 +1627,14 @@ Precipitation: {}%
             self.location.set_visible(false);
             self.location_search.set_visible(true);    
             self.location_search_button.set_visible(true);
+            self.locate_button.set_visible(true);
             self.refresh_button.set_visible(false);
             self.daily.set_visible(false);
             self.location_search.set_text("");
             self.update_current_weather(None);
             self.update_daily_weather(None);
             self.update_hourly_weather(None);
+            self.update_minutely_weather(None);
             self.set_stack_components_visible(false);
         }
     }
@@ -604,8 +1649,168 @@ Precipitation: {}%
         }
     }
 
-    fn save_preferences(&self, preferences: &WeatherPreferences) {
+    fn save_preferences(&mut self, preferences: WeatherPreferences) {
         preferences.save_config();
+        let moved = self.map.set_location(preferences.lat, preferences.lon);
+        self.preferences = Some(preferences);
+        self.update_saved_locations();
+        if moved {
+            self.update_map();
+        }
+    }
+
+    fn get_saved_locations(&self) -> Vec<LocationPoint> {
+        self.preferences.as_ref()
+            .map(|prefs| prefs.locations.clone())
+            .unwrap_or_default()
+    }
+
+    /// Lists the saved locations in the switcher, selecting the one shown
+    fn update_saved_locations(&mut self) {
+        let (locations, selected) = self.preferences.as_ref()
+            .map(|prefs| (prefs.locations.clone(), prefs.selected_location))
+            .unwrap_or_default();
+
+        self.location_switcher.remove_all();
+        for (index, location) in locations.iter().enumerate() {
+            self.location_switcher.append(Some(&index.to_string()), &location.location);
+        }
+        self.location_switcher.set_active_id(selected.map(|index| index.to_string()).as_deref());
+        self.location_switcher.set_visible(locations.len() > 1);
+
+        self.save_location_button.set_sensitive(self.preferences.is_some() && selected.is_none());
+        self.remove_location_button.set_sensitive(selected.is_some());
+        for (delta, button) in self.move_location_buttons.iter() {
+            let movable = selected
+                .map(|index| index as i32 + delta)
+                .map_or(false, |moved| moved >= 0 && (moved as usize) < locations.len());
+            button.set_sensitive(movable);
+        }
+    }
+
+    fn select_location(&mut self, index: usize) {
+        let location = match &self.preferences {
+            Some(prefs) if prefs.selected_location != Some(index) => prefs.locations.get(index).cloned(),
+            _ => None,
+        };
+        if let Some(location) = location {
+            self.request_weather(location);
+        }
+    }
+
+    fn save_location(&mut self) {
+        if let Some(prefs) = &mut self.preferences {
+            let current = prefs.current_location();
+            if prefs.locations.iter().any(|saved| saved.is_same_place(&current)) {
+                return;
+            }
+            prefs.locations.push(current);
+            prefs.selected_location = Some(prefs.locations.len() - 1);
+            prefs.save_config();
+        }
+        self.update_saved_locations();
+    }
+
+    /// Removes the location shown from the saved locations, it stays shown until switching away
+    fn remove_location(&mut self) {
+        if let Some(prefs) = &mut self.preferences {
+            if let Some(index) = prefs.selected_location.take() {
+                if index < prefs.locations.len() {
+                    prefs.locations.remove(index);
+                }
+                prefs.save_config();
+            }
+        }
+        self.update_saved_locations();
+    }
+
+    fn move_location(&mut self, delta: i32) {
+        if let Some(prefs) = &mut self.preferences {
+            if let Some(index) = prefs.selected_location {
+                let moved = index as i32 + delta;
+                if moved >= 0 && (moved as usize) < prefs.locations.len() {
+                    prefs.locations.swap(index, moved as usize);
+                    prefs.selected_location = Some(moved as usize);
+                    prefs.save_config();
+                }
+            }
+        }
+        self.update_saved_locations();
+    }
+
+    fn update_map(&mut self) {
+        let base_url = self.get_map_base_url();
+        let layer_url = self.get_map_layer_url();
+        let api_key = self.get_open_weather_api_key();
+        self.map.render(&base_url, &layer_url, api_key);
+    }
+
+    fn pan_map(&mut self, dx: f64, dy: f64) {
+        self.map.pan(dx, dy);
+        self.update_map();
+    }
+
+    fn zoom_map(&mut self, delta: i8) {
+        self.map.zoom(delta);
+        self.update_map();
+    }
+
+    fn update_map_layer(&mut self, layer: MapLayer) {
+        if let Some(prefs) = &mut self.preferences {
+            prefs.map_layer = layer;
+            prefs.save_config();
+        }
+        self.update_map();
+    }
+
+    fn update_aerodrome(&mut self, station: Option<String>) {
+        if let Some(prefs) = &mut self.preferences {
+            prefs.aviation_station = station;
+            prefs.save_config();
+        }
+        self.request_aviation();
+    }
+
+    /// Fetches the METAR and TAF for the chosen aerodrome, independently of the forecast
+    fn request_aviation(&self) {
+        let station = if let Some(station) = self.aviation.get_station() {
+            station
+        } else {
+            return;
+        };
+        let sender = self.get_sender();
+        let metar_url = self.get_metar_url();
+        let taf_url = self.get_taf_url();
+        self.spawn_local(async move {
+            let report = get_aviation(&metar_url, &taf_url, &station).await;
+            if let Err(err) = sender.send_async(WeatherUpdate::Aviation(report)).await {
+                println!("Unable to send aviation report: {}", err);
+            }
+        });
+    }
+
+    fn get_metar_url(&self) -> String {
+        self.preferences.as_ref()
+            .map(|prefs| prefs.metar_url.clone())
+            .unwrap_or_else(|| String::from(DEFAULT_METAR_URL))
+    }
+
+    fn get_taf_url(&self) -> String {
+        self.preferences.as_ref()
+            .map(|prefs| prefs.taf_url.clone())
+            .unwrap_or_else(|| String::from(DEFAULT_TAF_URL))
+    }
+
+    fn get_map_base_url(&self) -> String {
+        self.preferences.as_ref()
+            .map(|prefs| prefs.map_base_url.clone())
+            .unwrap_or_else(|| String::from(DEFAULT_BASE_TILE_URL))
+    }
+
+    fn get_map_layer_url(&self) -> String {
+        self.preferences.as_ref()
+            .map(|prefs| prefs.map_layer_url.clone())
+            .unwrap_or_else(|| String::from(DEFAULT_LAYER_TILE_URL))
     }
 
     fn update_units(&mut self, units: Units) {
@@ -614,6 +1819,190 @@ Precipitation: {}%
         }
     }
 
+    fn update_provider(&mut self, kind: ProviderKind) {
+        if let Some(prefs) = &mut self.preferences {
+            prefs.provider = kind;
+            prefs.save_config();
+        }
+        self.provider = self.create_provider();
+    }
+
+    fn update_fallbacks(&mut self, kinds: Vec<ProviderKind>) {
+        if let Some(prefs) = &mut self.preferences {
+            prefs.fallback_providers = kinds;
+            prefs.save_config();
+        }
+        self.provider = self.create_provider();
+    }
+
+    fn update_geocoder(&mut self, kind: GeocoderKind) {
+        if let Some(prefs) = &mut self.preferences {
+            prefs.geocoder = kind;
+            prefs.save_config();
+        }
+        self.geocoder = kind.create();
+    }
+
+    fn get_geocoder_kind(&self) -> GeocoderKind {
+        self.geocoder_select.active_id()
+            .and_then(|id| GeocoderKind::from_id(&id))
+            .unwrap_or_default()
+    }
+
+    fn update_api_key(&mut self, api_key: Option<String>) {
+        if let Some(prefs) = &mut self.preferences {
+            prefs.open_weather_api_key = api_key;
+            prefs.save_config();
+        }
+        self.provider = self.create_provider();
+        self.update_map();
+    }
+
+    /// Shows which provider served the forecast, noting when it was not the primary one
+    fn update_source(&mut self, served_by: Option<&str>, provenance: &[FieldProvenance]) {
+        if let Some(served_by) = served_by {
+            let mut markup = if served_by == self.provider.name() {
+                format!("<small>Data from {}</small>", served_by)
+            } else {
+                format!("<small>Data from {} (fallback)</small>", served_by)
+            };
+            // Blended forecasts say where each field came from
+            for field in provenance.iter() {
+                markup += &format!("\n<small>{}: {}</small>",
+                    gtk::glib::markup_escape_text(&field.field),
+                    gtk::glib::markup_escape_text(&field.source));
+            }
+            self.source_label.set_markup(&markup);
+            self.source_label.set_visible(true);
+        } else {
+            self.source_label.set_visible(false);
+        }
+    }
+
+    fn create_provider(&self) -> Arc<dyn WeatherProvider> {
+        create_chain(
+            self.get_provider_kind(),
+            &self.get_fallback_providers(),
+            self.get_open_weather_api_key(),
+            self.provider_health.clone())
+    }
+
+    /// Checked fallback providers, keeping the order given in preferences
+    fn get_fallback_providers(&self) -> Vec<ProviderKind> {
+        let checked = self.fallback_checks.iter()
+            .filter(|(_, check)| check.is_active())
+            .map(|(kind, _)| *kind)
+            .collect::<Vec<_>>();
+        let mut ordered = self.preferences.as_ref()
+            .map(|prefs| prefs.fallback_providers.iter()
+                .filter(|kind| checked.contains(kind))
+                .copied()
+                .collect::<Vec<_>>())
+            .unwrap_or_default();
+        for kind in checked {
+            if !ordered.contains(&kind) {
+                ordered.push(kind);
+            }
+        }
+
+        ordered
+    }
+
+    fn update_station_port(&mut self, port: Option<u16>) {
+        if let Some(prefs) = &mut self.preferences {
+            prefs.station_port = port;
+            prefs.save_config();
+        }
+        self.listen_for_station();
+    }
+
+    /// Starts, moves or stops the listener for station uploads to match the preferences
+    fn listen_for_station(&mut self) {
+        let port = self.get_station_port();
+        if self.station_server.as_ref().map(|server| server.port()) == port {
+            return;
+        }
+        // Waits for the old listener to close its socket, so moving back to a port just
+        // left does not fail with the address in use
+        self.station_server = None;
+        let port = if let Some(port) = port {
+            port
+        } else {
+            self.station = None;
+            self.station_label.set_visible(false);
+            return;
+        };
+
+        let sender = self.get_sender();
+        let server = StationServer::start(port, move |reading| {
+            if let Err(err) = sender.send(WeatherUpdate::Station(reading)) {
+                println!("Unable to send station reading: {}", err);
+            }
+        });
+        match server {
+            Ok(server) => {
+                self.station_server = Some(server);
+                if self.station.is_none() {
+                    self.station_label.set_markup(&format!("<b>Station</b> waiting for uploads on port {}", port));
+                    self.station_label.set_visible(true);
+                }
+            },
+            Err(err) => {
+                let message = format!("Unable to listen on port {}: {}", port, err);
+                self.station_label.set_markup(&format!("<b>Station</b> {}",
+                    gtk::glib::markup_escape_text(&message)));
+                self.station_label.set_visible(true);
+            },
+        }
+    }
+
+    fn update_station(&mut self, reading: StationReading) {
+        self.station = Some(reading);
+        self.render_station();
+    }
+
+    fn render_station(&mut self) {
+        if let Some(reading) = &self.station {
+            let now = time::OffsetDateTime::now_utc().unix_timestamp();
+            self.station_label.set_markup(&station_markup(reading, self.get_units(), now));
+            self.station_label.set_visible(true);
+        }
+    }
+
+    fn get_station_port(&self) -> Option<u16> {
+        if self.station_switch.state() {
+            Some(self.station_port.value_as_int() as u16)
+        } else {
+            None
+        }
+    }
+
+    fn update_cache_ttl(&mut self, minutes: u32) {
+        if let Some(prefs) = &mut self.preferences {
+            prefs.cache_ttl_minutes = minutes;
+            prefs.save_config();
+        }
+    }
+
+    fn get_cache_ttl(&self) -> u32 {
+        self.cache_ttl.value_as_int() as u32
+    }
+
+    fn get_open_weather_api_key(&self) -> Option<String> {
+        let api_key = self.api_key_entry.text().trim().to_string();
+        if api_key.is_empty() {
+            None
+        } else {
+            Some(api_key)
+        }
+    }
+
+    fn get_provider_kind(&self) -> ProviderKind {
+        self.provider_select.active_id()
+            .and_then(|id| ProviderKind::from_id(&id))
+            .unwrap_or_default()
+    }
+
     fn get_units(&self) -> Units {
         if let Some(prefs) = &self.preferences {
             match prefs.units {
@@ -0,0 +1,94 @@
+use crate::accuracy::{
+    AccuracyReport,
+    ErrorStats,
+};
+
+use gtk::prelude::*;
+use gtk::{
+    Grid,
+    Label,
+};
+
+pub struct AccuracyView {
+    pub container: gtk::Box,
+    headline: Label,
+    table: Grid,
+}
+
+fn stats_text(stats: &ErrorStats, unit: &str) -> String {
+    format!("{:.1} ({:+.1}) {}", stats.mae, stats.bias, unit)
+}
+
+fn cell(markup: &str) -> Label {
+    let label = Label::new(None);
+    label.set_markup(markup);
+    label.set_xalign(0.0);
+
+    label
+}
+
+impl AccuracyView {
+    pub fn new() -> Self {
+        let container = gtk::Box::new(gtk::Orientation::Vertical, 10);
+
+        let headline = Label::new(None);
+        headline.set_wrap(true);
+        headline.set_text("Forecasts are compared with the conditions observed once their hour comes");
+        container.append(&headline);
+
+        let table = Grid::new();
+        table.set_row_spacing(4);
+        table.set_column_spacing(12);
+        container.append(&table);
+
+        Self {
+            container,
+            headline,
+            table,
+        }
+    }
+
+    pub fn populate(&mut self, report: AccuracyReport) {
+        while let Some(child) = self.table.first_child() {
+            self.table.remove(&child);
+        }
+
+        if report.providers.is_empty() {
+            self.headline.set_text(&format!("Waiting to compare {} forecast hours with what is observed",
+                report.pending));
+            return;
+        }
+
+        let best = &report.providers[0];
+        self.headline.set_markup(&format!("Most accurate here: <b>{}</b>\n\
+<small>Mean absolute error (bias, + when forecasting too high) by hours ahead</small>",
+            gtk::glib::markup_escape_text(&best.provider)));
+
+        let units = report.units;
+        let mut row = 0;
+        for provider in report.providers.iter() {
+            self.table.attach(&cell(&format!("<b>{}</b> <small>{} forecasts</small>",
+                gtk::glib::markup_escape_text(&provider.provider),
+                provider.samples())), 0, row, 4, 1);
+            row += 1;
+
+            for (column, title) in ["Ahead", "Temperature", "Wind", "Precipitation"].iter().enumerate() {
+                self.table.attach(&cell(&format!("<i>{}</i>", title)), column as i32, row, 1, 1);
+            }
+            row += 1;
+
+            for lead in provider.leads.iter().filter(|l| l.samples > 0) {
+                let cells = [
+                    format!("{}-{} h", lead.from_hours, lead.to_hours),
+                    stats_text(&lead.temp, units.temperature_unit()),
+                    stats_text(&lead.wind_speed, units.speed_unit()),
+                    stats_text(&lead.precipitation, units.volume_unit()),
+                ];
+                for (column, text) in cells.iter().enumerate() {
+                    self.table.attach(&cell(text), column as i32, row, 1, 1);
+                }
+                row += 1;
+            }
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use crate::api::air_quality::{
+    AirQuality,
+    AirQualityData,
+    POLLUTANTS,
+};
+use crate::api::weather::time_from;
+use crate::api::error::WeatherError;
+
+use gtk::prelude::*;
+use gtk::{
+    Label,
+    LevelBar,
+};
+
+/// Hours of air quality forecast looked ahead for the worst conditions
+const FORECAST_HOURS: i64 = 24;
+
+pub struct AirQualityView {
+    pub container: gtk::Box,
+    headline: Label,
+    advice: Label,
+    forecast: Label,
+    pollutants: gtk::Box,
+    rows: Vec<gtk::Box>,
+}
+
+fn build_pollutant_row(name: &str, value: f64, very_poor: f64) -> gtk::Box {
+    let row = gtk::Box::new(gtk::Orientation::Horizontal, 10);
+
+    let label = Label::new(Some(name));
+    label.set_width_chars(6);
+    label.set_xalign(0.0);
+    row.append(&label);
+
+    let bar = LevelBar::for_interval(0.0, very_poor);
+    bar.set_value(value.min(very_poor));
+    bar.set_hexpand(true);
+    bar.set_size_request(160, -1);
+    row.append(&bar);
+
+    let amount = Label::new(Some(&format!("{:.1} μg/m³", value)));
+    amount.set_width_chars(12);
+    amount.set_xalign(1.0);
+    row.append(&amount);
+
+    row
+}
+
+fn worst_forecast(current: &AirQuality, forecast: &[AirQuality]) -> Option<String> {
+    let until = current.dt + FORECAST_HOURS * 60 * 60;
+    let worst = forecast.iter()
+        .filter(|f| f.dt > current.dt && f.dt <= until)
+        .max_by_key(|f| f.aqi)?;
+    if worst.aqi <= current.aqi {
+        return Some(String::from("Not expected to get worse over the next 24 hours"));
+    }
+    let when = time_from(worst.dt, "[hour]:[minute]").ok()?;
+
+    Some(format!("Getting worse, {} by {}", worst.category(), when))
+}
+
+impl AirQualityView {
+    pub fn new() -> Self {
+        let container = gtk::Box::new(gtk::Orientation::Vertical, 10);
+
+        let headline = Label::new(None);
+        container.append(&headline);
+
+        let advice = Label::new(None);
+        advice.set_wrap(true);
+        advice.set_max_width_chars(60);
+        container.append(&advice);
+
+        let pollutants = gtk::Box::new(gtk::Orientation::Vertical, 5);
+        container.append(&pollutants);
+
+        let forecast = Label::new(None);
+        container.append(&forecast);
+
+        Self {
+            container,
+            headline,
+            advice,
+            forecast,
+            pollutants,
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn populate(&mut self, data: Result<AirQualityData, WeatherError>) {
+        for row in self.rows.iter() {
+            self.pollutants.remove(row);
+        }
+        self.rows.clear();
+
+        let data = match data {
+            Ok(data) => data,
+            Err(err) => {
+                self.headline.set_markup("<b>No air quality data</b>");
+                match err {
+                    WeatherError::MissingApiKey => self.advice.set_text("Air quality needs an OpenWeather API key, add one in preferences"),
+                    err => self.advice.set_text(&err.to_string()),
+                }
+                self.forecast.set_visible(false);
+                return;
+            },
+        };
+
+        let current = &data.current;
+        self.headline.set_markup(&format!("<big>Air quality: <b>{}</b></big> <small>(AQI {})</small>",
+            current.category(),
+            current.aqi));
+        self.advice.set_text(current.sensitive_advice());
+
+        for pollutant in POLLUTANTS.iter() {
+            let value = (pollutant.value)(&current.pollutants);
+            let row = build_pollutant_row(pollutant.name, value, pollutant.very_poor);
+            self.pollutants.append(&row);
+            self.rows.push(row);
+        }
+
+        if let Some(forecast) = worst_forecast(current, &data.forecast) {
+            self.forecast.set_markup(&format!("<small>{}</small>", forecast));
+            self.forecast.set_visible(true);
+        } else {
+            self.forecast.set_visible(false);
+        }
+    }
+
+    pub fn set_visible(&self, visible: bool) {
+        self.container.set_visible(visible);
+    }
+}
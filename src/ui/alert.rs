@@ -6,6 +6,10 @@ use gtk::{
     MessageType,
 };
 
+fn escape(text: &str) -> String {
+    gtk::glib::markup_escape_text(text).to_string()
+}
+
 pub struct WeatherAlerts {
     pub container: gtk::ScrolledWindow,
     pub contents: gtk::Box,
@@ -24,21 +28,46 @@ fn create_empty_alert() -> InfoBar {
     i
 }
 
+fn alert_message_type(alert: &WeatherAlert) -> MessageType {
+    match alert.severity.as_deref() {
+        Some("Extreme") | Some("Severe") => MessageType::Error,
+        Some("Minor") => MessageType::Info,
+        _ => MessageType::Warning,
+    }
+}
+
 fn create_infobar_alert(alert: &WeatherAlert) -> InfoBar {
     let i = InfoBar::new();
-    i.set_message_type(MessageType::Warning);
+    i.set_message_type(alert_message_type(alert));
+
+    let when = alert.when()
+        .unwrap_or_else(|_| String::from("unknown time"));
+    let mut classification = Vec::new();
+    if let Some(severity) = &alert.severity {
+        classification.push(format!("Severity: {}", escape(severity)));
+    }
+    if let Some(urgency) = &alert.urgency {
+        classification.push(format!("Urgency: {}", escape(urgency)));
+    }
+    let mut details = String::new();
+    if !classification.is_empty() {
+        details += &format!("\n<small>{}</small>", classification.join(", "));
+    }
+    if let Some(area) = &alert.area {
+        details += &format!("\n<small><i>{}</i></small>", escape(area));
+    }
 
-    let when = alert.when();
     let label = Label::new(Some(&format!("<b>{}</b> 
-<small>({})</small>
+<small>({})</small>{}
 
 {}
 
 - <i>{}</i>", 
-    alert.event, 
+    escape(&alert.event), 
     when,
-    alert.description,
-    alert.sender_name)));
+    details,
+    escape(&alert.description),
+    escape(&alert.sender_name))));
 
     label.set_use_markup(true);
     label.set_max_width_chars(80);
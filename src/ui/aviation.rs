@@ -0,0 +1,164 @@
+use crate::api::aviation::{
+    AviationReport,
+    Conditions,
+    FlightCategory,
+    Metar,
+    Taf,
+};
+use crate::api::error::WeatherError;
+
+use gtk::prelude::*;
+use gtk::{
+    Entry,
+    Label,
+};
+
+pub struct AviationView {
+    pub container: gtk::Box,
+    pub station_entry: Entry,
+    headline: Label,
+    metar: Label,
+    taf: Label,
+}
+
+/// The usual chart colours for each flight category
+fn category_markup(category: Option<FlightCategory>) -> String {
+    match category {
+        Some(category) => {
+            let colour = match category {
+                FlightCategory::Vfr => "green",
+                FlightCategory::Mvfr => "blue",
+                FlightCategory::Ifr => "red",
+                FlightCategory::Lifr => "magenta",
+            };
+            format!("<span foreground=\"{}\"><b>{}</b></span>", colour, category)
+        },
+        None => String::from("<b>--</b>"),
+    }
+}
+
+fn conditions_markup(conditions: &Conditions) -> String {
+    let mut lines = vec![
+        format!("Wind: {}", conditions.wind.as_ref()
+            .map(|w| w.to_string())
+            .unwrap_or_else(|| String::from("--"))),
+        format!("Visibility: {}", conditions.visibility_text()
+            .unwrap_or_else(|| String::from("--"))),
+        format!("Clouds: {}", conditions.clouds_text()),
+        format!("Ceiling: {}", conditions.ceiling()
+            .map(|c| format!("{} ft", c))
+            .unwrap_or_else(|| String::from("None"))),
+    ];
+    if !conditions.weather.is_empty() {
+        lines.push(format!("Weather: {}", conditions.weather.join(" ")));
+    }
+
+    lines.join("\n")
+}
+
+fn raw_markup(raw: &str) -> String {
+    format!("<tt><small>{}</small></tt>", gtk::glib::markup_escape_text(raw))
+}
+
+fn metar_markup(metar: &Metar) -> String {
+    let mut markup = format!("<b>METAR</b> {}\n{}",
+        metar.time.as_deref().unwrap_or(""),
+        conditions_markup(&metar.conditions));
+    if let (Some(temperature), Some(dew_point)) = (metar.temperature, metar.dew_point) {
+        markup += &format!("\nTemperature: {} °C, dew point {} °C", temperature, dew_point);
+    }
+    if let Some(pressure) = metar.pressure {
+        markup += &format!("\nPressure: {:.0} hPa", pressure);
+    }
+    markup += &format!("\n{}", raw_markup(&metar.raw));
+
+    markup
+}
+
+fn taf_markup(taf: &Taf) -> String {
+    let mut markup = String::from("<b>TAF</b>");
+    for group in taf.groups.iter() {
+        let title = match (group.change.as_str(), &group.period) {
+            ("", Some(period)) => format!("Valid {}", period),
+            ("", None) => String::from("Forecast"),
+            (change, Some(period)) => format!("{} {}", change, period),
+            (change, None) => String::from(change),
+        };
+        markup += &format!("\n\n<i>{}</i> {}\n{}",
+            gtk::glib::markup_escape_text(&title),
+            category_markup(group.conditions.flight_category()),
+            conditions_markup(&group.conditions));
+    }
+    markup += &format!("\n\n{}", raw_markup(&taf.raw));
+
+    markup
+}
+
+impl AviationView {
+    pub fn new() -> Self {
+        let container = gtk::Box::new(gtk::Orientation::Vertical, 10);
+
+        let station_entry = Entry::new();
+        station_entry.set_placeholder_text(Some("Aerodrome ICAO code, e.g. EGLL"));
+        station_entry.set_max_length(4);
+        container.append(&station_entry);
+
+        let headline = Label::new(None);
+        headline.set_markup("Enter an aerodrome to see its METAR and TAF");
+        container.append(&headline);
+
+        let metar = Label::new(None);
+        metar.set_selectable(true);
+        metar.set_wrap(true);
+        metar.set_max_width_chars(60);
+        container.append(&metar);
+
+        let taf = Label::new(None);
+        taf.set_selectable(true);
+        taf.set_wrap(true);
+        taf.set_max_width_chars(60);
+        container.append(&taf);
+
+        Self {
+            container,
+            station_entry,
+            headline,
+            metar,
+            taf,
+        }
+    }
+
+    pub fn get_station(&self) -> Option<String> {
+        let station = self.station_entry.text().trim().to_uppercase();
+        if station.is_empty() {
+            None
+        } else {
+            Some(station)
+        }
+    }
+
+    pub fn set_station(&self, station: Option<&str>) {
+        self.station_entry.set_text(station.unwrap_or(""));
+    }
+
+    pub fn populate(&mut self, report: Result<AviationReport, WeatherError>) {
+        let report = match report {
+            Ok(report) => report,
+            Err(err) => {
+                self.headline.set_text(&err.to_string());
+                self.metar.set_text("");
+                self.taf.set_text("");
+                return;
+            },
+        };
+
+        self.headline.set_markup(&format!("<big>{} {}</big>",
+            report.metar.station,
+            category_markup(report.metar.conditions.flight_category())));
+        self.metar.set_markup(&metar_markup(&report.metar));
+        match &report.taf {
+            Some(taf) => self.taf.set_markup(&taf_markup(taf)),
+            None => self.taf.set_text("No TAF issued for this aerodrome"),
+        }
+    }
+}
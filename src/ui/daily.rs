@@ -3,8 +3,15 @@ use gtk::{
     Image,
     Label,
 };
-use crate::api::weather::DailyWeather;
+use crate::api::weather::{
+    DailyWeather,
+    date_at,
+};
 use crate::api::units::Units;
+use crate::api::pollen::{
+    Allergen,
+    PollenDay,
+};
 use super::icon_path;
 use crate::ui::hourly::{
     build_precipitation_component,
@@ -14,6 +21,22 @@ use crate::ui::hourly::{
 
 pub struct DayView {
     container: gtk::Box,
+    dt: i64,
+    pollen: Label,
+}
+
+fn pollen_markup(day: &PollenDay, tracked: &[Allergen]) -> String {
+    let mut markup = String::from("<b>Pollen</b>");
+    for (allergen, grains) in day.levels.iter() {
+        let line = format!("{}: {} ({:.0})", allergen, allergen.level(*grains), grains);
+        // Highlight the allergens the user tracks, dimming the rest
+        if tracked.contains(allergen) {
+            markup += &format!("\n  <b>{}</b>", line);
+        } else {
+            markup += &format!("\n  <span fgalpha=\"50%\">{}</span>", line);
+        }
+    }
+    markup
 }
 
 impl DayView {
@@ -27,7 +50,8 @@ impl DayView {
         };
 
         let date = Label::new(None);
-        date.set_markup(&format!("<b>{}</b>", &data.day_of_week()));
+        date.set_markup(&format!("<b>{}</b>", &data.day_of_week()
+            .unwrap_or_else(|_| String::from("--"))));
         container.append(&date);
 
         let status = Image::from_file(icon_path);
@@ -97,8 +121,24 @@ impl DayView {
         }
         container.append(&sun_box);
 
+        let pollen = Label::new(None);
+        pollen.set_visible(false);
+        container.append(&pollen);
+
         Self {
             container, 
+            dt: data.dt,
+            pollen,
+        }
+    }
+
+    pub fn set_pollen(&self, pollen: Option<&PollenDay>, tracked: &[Allergen]) {
+        match pollen {
+            Some(day) if !day.levels.is_empty() => {
+                self.pollen.set_markup(&pollen_markup(day, tracked));
+                self.pollen.set_visible(true);
+            },
+            _ => self.pollen.set_visible(false),
         }
     }
 }
@@ -139,6 +179,15 @@ impl DailyView {
         }
     }
 
+    /// Adds the pollen outlook to each day it covers, both dated in the location's time zone
+    pub fn set_pollen(&self, pollen: &[PollenDay], tracked: &[Allergen]) {
+        for view in self.views.iter() {
+            let day = pollen.iter()
+                .find(|p| date_at(view.dt, p.utc_offset).ok() == Some(p.date));
+            view.set_pollen(day, tracked);
+        }
+    }
+
     pub fn set_visible(&self, visible: bool) {
         self.container.set_visible(visible);
     }
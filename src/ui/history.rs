@@ -0,0 +1,141 @@
+use std::convert::TryFrom;
+
+use crate::api::units::Units;
+use crate::api::history::HistoricalDay;
+use crate::api::weather::CurrentWeather;
+use crate::api::error::WeatherError;
+use super::hourly::HourlyView;
+
+use gtk::prelude::*;
+use gtk::{
+    Calendar,
+    Label,
+};
+use time::{
+    Date,
+    Month,
+};
+
+pub struct HistoryView {
+    pub container: gtk::Box,
+    pub calendar: Calendar,
+    headline: Label,
+    summary: Label,
+    hourly: HourlyView,
+}
+
+/// The day picked in the calendar
+pub fn calendar_date(calendar: &Calendar) -> Option<Date> {
+    let date = calendar.date();
+    let month = Month::try_from(date.month() as u8).ok()?;
+
+    Date::from_calendar_date(date.year(), month, date.day_of_month() as u8).ok()
+}
+
+/// Seconds in a day, to find the same time yesterday
+const DAY: i64 = 24 * 60 * 60;
+
+fn difference(value: f64, more: &str, less: &str) -> (f64, String) {
+    let comparative = if value >= 0.0 { more } else { less };
+    (value.abs(), String::from(comparative))
+}
+
+/// A comparison such as "3 °C warmer than yesterday at this time, wind 2.1 m/s stronger, humidity 10% lower"
+pub fn comparison_text(current: &CurrentWeather, yesterday: &HistoricalDay, units: &Units) -> Option<String> {
+    let then = yesterday.at(current.dt - DAY)?;
+
+    let (temp, warmer) = difference(current.temp - then.temp, "warmer", "colder");
+    let mut parts = if temp < 0.5 {
+        vec![String::from("Same temperature as yesterday at this time")]
+    } else {
+        vec![format!("{} {} than yesterday at this time",
+            units.temperature_value(format!("{:.0}", temp)),
+            warmer)]
+    };
+
+    let (wind, stronger) = difference(current.wind_speed - then.wind_speed, "stronger", "lighter");
+    if wind >= 0.5 {
+        parts.push(format!("wind {} {}", units.speed_value(format!("{:.1}", wind)), stronger));
+    }
+
+    let (humidity, higher) = difference(current.humidity as f64 - then.humidity as f64, "higher", "lower");
+    if humidity >= 1.0 {
+        parts.push(format!("humidity {:.0}% {}", humidity, higher));
+    }
+
+    Some(parts.join(", "))
+}
+
+fn date_text(date: Date) -> String {
+    let format_desc = time::format_description::parse("[weekday] [day] [month repr:long] [year]")
+        .expect("Invalid format description supplied");
+
+    date.format(&format_desc)
+        .unwrap_or_else(|_| date.to_string())
+}
+
+impl HistoryView {
+    pub fn new() -> Self {
+        let container = gtk::Box::new(gtk::Orientation::Vertical, 10);
+
+        let calendar = Calendar::new();
+        container.append(&calendar);
+
+        let headline = Label::new(None);
+        headline.set_markup("Pick a day to see what the weather was");
+        container.append(&headline);
+
+        let summary = Label::new(None);
+        summary.set_visible(false);
+        container.append(&summary);
+
+        let hourly = HourlyView::new();
+        hourly.set_visible(false);
+        container.append(&hourly.container);
+
+        Self {
+            container,
+            calendar,
+            headline,
+            summary,
+            hourly,
+        }
+    }
+
+    pub fn set_loading(&mut self, date: Date) {
+        self.headline.set_markup(&format!("<big>{}</big>", date_text(date)));
+        self.summary.set_text("Loading...");
+        self.summary.set_visible(true);
+        self.hourly.set_visible(false);
+    }
+
+    pub fn set_message(&mut self, message: &str) {
+        self.headline.set_text(message);
+        self.summary.set_visible(false);
+        self.hourly.populate(Vec::new(), &Units::Metric);
+        self.hourly.set_visible(false);
+    }
+
+    pub fn populate(&mut self, history: Result<HistoricalDay, WeatherError>, units: &Units) {
+        let history = match history {
+            Ok(history) if !history.hourly.is_empty() => history,
+            Ok(_) => return self.set_message("No observations are available for this day"),
+            Err(err) => return self.set_message(&err.to_string()),
+        };
+
+        self.headline.set_markup(&format!("<big>{}</big>", date_text(history.date)));
+        let temperatures = match (history.temp_max(), history.temp_min()) {
+            (Some(max), Some(min)) => format!("High {}, low {}",
+                units.temperature_value(format!("{:.1}", max)),
+                units.temperature_value(format!("{:.1}", min))),
+            _ => String::new(),
+        };
+        self.summary.set_text(&format!("{}, {} precipitation",
+            temperatures,
+            units.volume_value(format!("{:.1}", history.precipitation()))));
+        self.summary.set_visible(true);
+
+        self.hourly.populate(history.hourly, units);
+        self.hourly.set_visible(true);
+    }
+}
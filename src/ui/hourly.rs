@@ -51,7 +51,8 @@ pub fn build_precipitation_component(icon: &str, data: Option<f64>, units: &Unit
 fn build_hourly_component(data: &HourlyWeather, units: &Units) -> gtk::Box {
     let component = gtk::Box::new(gtk::Orientation::Vertical, 5);
 
-    let time = Label::new(Some(&data.time("[hour]:[minute]")));
+    let time = Label::new(Some(&data.time("[hour]:[minute]")
+        .unwrap_or_else(|_| String::from("--"))));
     component.append(&time);
 
     let icon_path = if data.status.len() > 0 {
@@ -66,15 +67,11 @@ fn build_hourly_component(data: &HourlyWeather, units: &Units) -> gtk::Box {
     let temperature = Label::new(Some(&units.temperature_value(data.temp)));
     component.append(&temperature);
 
-    if let Some(data) = &data.rain {
-        if let Some(rain_box) = build_precipitation_component(RAIN_ICON, data.volume, units) {
-            component.append(&rain_box);
-        }
+    if let Some(rain_box) = build_precipitation_component(RAIN_ICON, data.rain, units) {
+        component.append(&rain_box);
     }
-    if let Some(data) = &data.snow {
-        if let Some(snow_box) = build_precipitation_component(SNOW_ICON, data.volume, units) {
-            component.append(&snow_box);
-        }
+    if let Some(snow_box) = build_precipitation_component(SNOW_ICON, data.snow, units) {
+        component.append(&snow_box);
     }
     if let Some(gust_box) = build_wind_component(data.wind_gust, units) {
         component.append(&gust_box);
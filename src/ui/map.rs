@@ -0,0 +1,248 @@
+use std::rc::Rc;
+use std::cell::Cell;
+
+use crate::api::map::{
+    MapLayer,
+    Tile,
+    get_tile,
+    tile_cache_dir,
+    tile_coordinates,
+    tile_position,
+    MIN_ZOOM,
+    MAX_ZOOM,
+};
+
+use gtk::prelude::*;
+use gtk::{
+    Button,
+    ComboBoxText,
+    GestureClick,
+    GestureDrag,
+    Grid,
+    Image,
+    Label,
+    Overlay,
+    Picture,
+};
+
+/// Size tiles are drawn at, half their 256px so the grid fits on a phone
+const TILE_SIZE: i32 = 128;
+/// Tiles across and down the grid
+const GRID_SIZE: i64 = 3;
+const DEFAULT_ZOOM: u8 = 7;
+
+pub struct MapView {
+    pub container: gtk::Box,
+    pub layer_select: ComboBoxText,
+    pub zoom_in: Button,
+    pub zoom_out: Button,
+    pub drag: GestureDrag,
+    pub click: GestureClick,
+    note: Label,
+    marker: Image,
+    base_tiles: Vec<Picture>,
+    layer_tiles: Vec<Picture>,
+    location: Option<(f64, f64)>,
+    /// Centre of the map in tiles at the current zoom
+    center: (f64, f64),
+    zoom: u8,
+    /// Bumped on every render so tiles still loading for an earlier view are dropped
+    generation: Rc<Cell<u64>>,
+}
+
+fn build_tile_grid() -> (Grid, Vec<Picture>) {
+    let grid = Grid::new();
+    let mut pictures = Vec::new();
+    for row in 0..GRID_SIZE {
+        for column in 0..GRID_SIZE {
+            let picture = Picture::new();
+            picture.set_size_request(TILE_SIZE, TILE_SIZE);
+            picture.set_can_shrink(true);
+            grid.attach(&picture, column as i32, row as i32, 1, 1);
+            pictures.push(picture);
+        }
+    }
+
+    (grid, pictures)
+}
+
+fn load_tile(picture: &Picture, tile: Tile, template: String, layer: Option<MapLayer>, api_key: Option<String>, generation: Rc<Cell<u64>>) {
+    picture.set_paintable(None::<&gtk::gdk::Paintable>);
+    if !tile.is_valid() {
+        return;
+    }
+
+    let cache_root = if let Some(cache_root) = tile_cache_dir() {
+        cache_root
+    } else {
+        println!("Unable to resolve tile cache path");
+        return;
+    };
+    let expected = generation.get();
+    let picture = picture.clone();
+    gtk::glib::MainContext::default().spawn_local(async move {
+        match get_tile(&cache_root, tile, &template, layer, api_key.as_deref()).await {
+            Ok(path) if generation.get() == expected => picture.set_filename(path.to_str().unwrap()),
+            Ok(_) => {},
+            Err(err) => println!("Unable to load map tile {}/{}/{}: {}", tile.z, tile.x, tile.y, err),
+        }
+    });
+}
+
+impl MapView {
+    pub fn new() -> Self {
+        let container = gtk::Box::new(gtk::Orientation::Vertical, 10);
+
+        let controls = gtk::Box::new(gtk::Orientation::Horizontal, 5);
+        let layer_select = ComboBoxText::new();
+        for layer in MapLayer::ALL.iter() {
+            layer_select.append(Some(layer.id()), &layer.to_string());
+        }
+        layer_select.set_active_id(Some(MapLayer::default().id()));
+        controls.append(&layer_select);
+
+        let zoom_out = Button::from_icon_name(Some("zoom-out"));
+        controls.append(&zoom_out);
+        let zoom_in = Button::from_icon_name(Some("zoom-in"));
+        controls.append(&zoom_in);
+        container.append(&controls);
+
+        let (base_grid, base_tiles) = build_tile_grid();
+        let (layer_grid, layer_tiles) = build_tile_grid();
+
+        let marker = Image::from_icon_name(Some("mark-location"));
+        marker.set_halign(gtk::Align::Start);
+        marker.set_valign(gtk::Align::Start);
+        marker.set_visible(false);
+
+        let overlay = Overlay::new();
+        overlay.set_child(Some(&base_grid));
+        overlay.add_overlay(&layer_grid);
+        overlay.add_overlay(&marker);
+
+        let drag = GestureDrag::new();
+        overlay.add_controller(&drag);
+        // Dragging past the threshold cancels the click, so panning does not pick a location
+        let click = GestureClick::new();
+        overlay.add_controller(&click);
+        container.append(&overlay);
+
+        let note = Label::new(None);
+        note.set_visible(false);
+        container.append(&note);
+
+        Self {
+            container,
+            layer_select,
+            zoom_in,
+            zoom_out,
+            drag,
+            click,
+            note,
+            marker,
+            base_tiles,
+            layer_tiles,
+            location: None,
+            center: (0.0, 0.0),
+            zoom: DEFAULT_ZOOM,
+            generation: Rc::new(Cell::new(0)),
+        }
+    }
+
+    /// Centres the map on a location, returning false if it was already there
+    pub fn set_location(&mut self, lat: f64, lon: f64) -> bool {
+        if self.location == Some((lat, lon)) {
+            return false;
+        }
+        self.location = Some((lat, lon));
+        self.center = tile_position(lat, lon, self.zoom);
+
+        true
+    }
+
+    pub fn pan(&mut self, dx: f64, dy: f64) {
+        self.center.0 -= dx / TILE_SIZE as f64;
+        self.center.1 -= dy / TILE_SIZE as f64;
+    }
+
+    /// Zooms about the centre of the map, keeping within the zoom levels tiles are fetched for
+    pub fn zoom(&mut self, delta: i8) {
+        let zoom = (self.zoom as i16 + delta as i16)
+            .max(MIN_ZOOM as i16)
+            .min(MAX_ZOOM as i16) as u8;
+        let scale = 2f64.powi(zoom as i32 - self.zoom as i32);
+        self.center = (self.center.0 * scale, self.center.1 * scale);
+        self.zoom = zoom;
+    }
+
+    /// Latitude and longitude under a point on the map, None before the map is shown or off its edge
+    pub fn coordinates_at(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        if self.location.is_none() {
+            return None;
+        }
+        let origin_x = self.center.0.floor() - (GRID_SIZE / 2) as f64;
+        let origin_y = self.center.1.floor() - (GRID_SIZE / 2) as f64;
+        let tile_y = origin_y + y / TILE_SIZE as f64;
+        if tile_y < 0.0 || tile_y >= (1u64 << self.zoom) as f64 {
+            return None;
+        }
+
+        Some(tile_coordinates(origin_x + x / TILE_SIZE as f64, tile_y, self.zoom))
+    }
+
+    pub fn get_layer(&self) -> MapLayer {
+        self.layer_select.active_id()
+            .and_then(|id| MapLayer::from_id(&id))
+            .unwrap_or_default()
+    }
+
+    pub fn render(&mut self, base_url: &str, layer_url: &str, api_key: Option<String>) {
+        if self.location.is_none() {
+            return;
+        }
+        self.generation.set(self.generation.get() + 1);
+
+        let origin_x = self.center.0.floor() as i64 - GRID_SIZE / 2;
+        let origin_y = self.center.1.floor() as i64 - GRID_SIZE / 2;
+        let layer = self.get_layer();
+        let needs_key = layer_url.contains("{api_key}") && api_key.is_none();
+
+        for (i, (base, overlay)) in self.base_tiles.iter().zip(self.layer_tiles.iter()).enumerate() {
+            let tile = Tile {
+                z: self.zoom,
+                x: origin_x + i as i64 % GRID_SIZE,
+                y: origin_y + i as i64 / GRID_SIZE,
+            };
+            load_tile(base, tile, base_url.to_string(), None, None, self.generation.clone());
+            if needs_key {
+                overlay.set_paintable(None::<&gtk::gdk::Paintable>);
+            } else {
+                load_tile(overlay, tile, layer_url.to_string(), Some(layer), api_key.clone(), self.generation.clone());
+            }
+        }
+
+        if needs_key {
+            self.note.set_text("Weather layers need an OpenWeather API key, add one in preferences");
+        }
+        self.note.set_visible(needs_key);
+
+        // Mark the location when it is within the grid
+        if let Some((lat, lon)) = self.location {
+            let (x, y) = tile_position(lat, lon, self.zoom);
+            let left = ((x - origin_x as f64) * TILE_SIZE as f64) as i32;
+            let top = ((y - origin_y as f64) * TILE_SIZE as f64) as i32;
+            let extent = GRID_SIZE as i32 * TILE_SIZE;
+            if left >= 0 && left < extent && top >= 0 && top < extent {
+                self.marker.set_margin_start((left - 8).max(0));
+                self.marker.set_margin_top((top - 16).max(0));
+                self.marker.set_visible(true);
+            } else {
+                self.marker.set_visible(false);
+            }
+        }
+    }
+
+    pub fn set_visible(&self, visible: bool) {
+        self.container.set_visible(visible);
+    }
+}
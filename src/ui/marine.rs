@@ -0,0 +1,125 @@
+use crate::api::units::Units;
+use crate::api::marine::{
+    MarineConditions,
+    MarineData,
+    compass_point,
+};
+use crate::api::weather::time_from;
+
+use gtk::prelude::*;
+use gtk::{
+    Label,
+    ScrolledWindow,
+};
+
+pub struct MarineView {
+    pub container: gtk::Box,
+    headline: Label,
+    details: Label,
+    contents: gtk::Box,
+    hours: Vec<gtk::Box>,
+}
+
+fn height(value: Option<f64>, units: &Units) -> String {
+    value.map(|v| units.height_value(format!("{:.1}", v)))
+        .unwrap_or_else(|| String::from("--"))
+}
+
+fn period(value: Option<f64>) -> String {
+    value.map(|v| format!("{:.0} s", v))
+        .unwrap_or_else(|| String::from("--"))
+}
+
+fn direction(value: Option<f64>) -> String {
+    value.map(|v| format!("from {}", compass_point(v)))
+        .unwrap_or_else(|| String::from("--"))
+}
+
+fn build_marine_component(data: &MarineConditions, units: &Units) -> gtk::Box {
+    let component = gtk::Box::new(gtk::Orientation::Vertical, 5);
+
+    let time = Label::new(Some(&time_from(data.dt, "[hour]:[minute]")
+        .unwrap_or_else(|_| String::from("--"))));
+    component.append(&time);
+
+    let waves = Label::new(None);
+    waves.set_markup(&format!("<b>{}</b>", height(data.wave_height, units)));
+    component.append(&waves);
+
+    let swell = Label::new(Some(&format!("{} {}",
+        height(data.swell_wave_height, units),
+        period(data.swell_wave_period))));
+    component.append(&swell);
+
+    component
+}
+
+impl MarineView {
+    pub fn new() -> Self {
+        let container = gtk::Box::new(gtk::Orientation::Vertical, 10);
+
+        let headline = Label::new(None);
+        container.append(&headline);
+
+        let details = Label::new(None);
+        container.append(&details);
+
+        let contents = gtk::Box::new(gtk::Orientation::Horizontal, 10);
+        let scroller = ScrolledWindow::new();
+        scroller.set_child(Some(&contents));
+        scroller.set_propagate_natural_height(true);
+        scroller.set_propagate_natural_width(true);
+        scroller.set_kinetic_scrolling(true);
+        container.append(&scroller);
+
+        Self {
+            container,
+            headline,
+            details,
+            contents,
+            hours: Vec::new(),
+        }
+    }
+
+    pub fn populate(&mut self, marine: Option<MarineData>, units: &Units) {
+        for hour in self.hours.iter() {
+            self.contents.remove(hour);
+        }
+        self.hours.clear();
+
+        let marine = if let Some(marine) = marine {
+            marine
+        } else {
+            self.headline.set_text("");
+            self.details.set_text("");
+            return;
+        };
+
+        let current = &marine.current;
+        self.headline.set_markup(&format!("<big>Waves: <b>{}</b></big> {}",
+            height(current.wave_height, units),
+            direction(current.wave_direction)));
+        self.details.set_markup(&format!("
+Wave period: {}
+Swell: {} {}, {}
+Sea temperature: {}
+            ",
+            period(current.wave_period),
+            height(current.swell_wave_height, units),
+            direction(current.swell_wave_direction),
+            period(current.swell_wave_period),
+            current.sea_surface_temperature
+                .map(|t| units.temperature_value(t))
+                .unwrap_or_else(|| String::from("--"))));
+
+        for hour in marine.hourly.iter() {
+            let hour_component = build_marine_component(hour, units);
+            self.contents.append(&hour_component);
+            self.hours.push(hour_component);
+        }
+    }
+
+    pub fn set_visible(&self, visible: bool) {
+        self.container.set_visible(visible);
+    }
+}
@@ -0,0 +1,129 @@
+use crate::api::units::Units;
+use crate::api::weather::{
+    WeatherMinutely,
+    TimeStamped,
+    precipitation_window,
+};
+
+use gtk::prelude::*;
+use gtk::{
+    Label,
+    LevelBar,
+};
+
+/// Minutes of forecast shown in the nowcast chart
+const NOWCAST_MINUTES: usize = 60;
+
+pub struct MinutelyView {
+    pub container: gtk::Box,
+    headline: Label,
+    chart: gtk::Box,
+    bars: Vec<LevelBar>,
+    scale: Label,
+}
+
+fn now() -> i64 {
+    time::OffsetDateTime::now_utc().unix_timestamp()
+}
+
+fn minutes(value: i64) -> String {
+    if value == 1 {
+        String::from("1 min")
+    } else {
+        format!("{} min", value)
+    }
+}
+
+/// A headline such as "Rain starting in 12 min, stopping in 35 min"
+pub fn nowcast_headline(minutely: &[WeatherMinutely], precipitation: &str) -> String {
+    match precipitation_window(minutely, now()) {
+        (None, _) => String::from("No precipitation expected in the next hour"),
+        (Some(0), None) => format!("{} continuing for the next hour", precipitation),
+        (Some(0), Some(stop)) => format!("{} stopping in {}", precipitation, minutes(stop)),
+        (Some(start), None) => format!("{} starting in {}", precipitation, minutes(start)),
+        (Some(start), Some(stop)) => format!("{} starting in {}, stopping in {}", precipitation, minutes(start), minutes(stop)),
+    }
+}
+
+fn build_bar(value: f64, max: f64) -> LevelBar {
+    let bar = LevelBar::for_interval(0.0, max);
+    bar.set_orientation(gtk::Orientation::Vertical);
+    bar.set_inverted(true);
+    bar.set_value(value.min(max));
+    bar.set_size_request(4, 80);
+
+    bar
+}
+
+impl MinutelyView {
+    pub fn new() -> Self {
+        let container = gtk::Box::new(gtk::Orientation::Vertical, 10);
+
+        let headline = Label::new(None);
+        container.append(&headline);
+
+        let chart = gtk::Box::new(gtk::Orientation::Horizontal, 1);
+        container.append(&chart);
+
+        let scale = Label::new(None);
+        container.append(&scale);
+
+        Self {
+            container,
+            headline,
+            chart,
+            bars: Vec::new(),
+            scale,
+        }
+    }
+
+    /// Precipitation names what is falling for the headline, e.g. Rain or Snow
+    pub fn populate(&mut self, minutely: Vec<WeatherMinutely>, precipitation: &str, units: &Units) {
+        for bar in self.bars.iter() {
+            self.chart.remove(bar);
+        }
+        self.bars.clear();
+
+        let now = now();
+        let minutely = minutely.into_iter()
+            .filter(|m| m.dt + 60 > now)
+            .take(NOWCAST_MINUTES)
+            .collect::<Vec<_>>();
+        if minutely.is_empty() {
+            self.headline.set_markup("<b>No minutely forecast is available from this provider</b>");
+            self.scale.set_visible(false);
+            return;
+        }
+
+        self.headline.set_markup(&format!("<b>{}</b>", nowcast_headline(&minutely, precipitation)));
+
+        // Keep light rain visible by never scaling below 1mm/h
+        let max = minutely.iter()
+            .map(|m| m.precipitation)
+            .fold(1.0, f64::max);
+        for minute in minutely.iter() {
+            let bar = build_bar(minute.precipitation, max);
+            bar.set_tooltip_text(Some(&format!("{} {}/h",
+                minute.time("[hour]:[minute]").unwrap_or_else(|_| String::from("--")),
+                units.volume_value(format!("{:.1}", minute.precipitation)))));
+            self.chart.append(&bar);
+            self.bars.push(bar);
+        }
+
+        let first = minutely.first()
+            .and_then(|m| m.time("[hour]:[minute]").ok())
+            .unwrap_or_default();
+        let last = minutely.last()
+            .and_then(|m| m.time("[hour]:[minute]").ok())
+            .unwrap_or_default();
+        self.scale.set_markup(&format!("<small>{} to {}, up to {}/h</small>",
+            first,
+            last,
+            units.volume_value(format!("{:.1}", max))));
+        self.scale.set_visible(true);
+    }
+
+    pub fn set_visible(&self, visible: bool) {
+        self.container.set_visible(visible);
+    }
+}
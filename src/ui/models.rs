@@ -0,0 +1,207 @@
+use std::sync::{Arc, Mutex};
+
+use crate::api::models::{
+    Confidence,
+    ModelComparison,
+};
+use crate::api::error::WeatherError;
+
+use gtk::prelude::*;
+use gtk::cairo::Context;
+use gtk::{
+    DrawingArea,
+    Label,
+};
+
+const CHART_WIDTH: i32 = 360;
+const CHART_HEIGHT: i32 = 200;
+/// Share of the chart height given to precipitation at the bottom
+const PRECIPITATION_SHARE: f64 = 0.25;
+/// Precipitation (mm per hour) drawn at full height
+const PRECIPITATION_SCALE: f64 = 5.0;
+
+/// Line colours for each model shown, as RGB and as markup
+const COLOURS: &[(f64, f64, f64, &str)] = &[
+    (0.85, 0.20, 0.20, "#d93333"),
+    (0.20, 0.45, 0.85, "#3373d9"),
+    (0.15, 0.65, 0.30, "#26a64d"),
+    (0.90, 0.55, 0.10, "#e68c1a"),
+    (0.55, 0.30, 0.75, "#8c4dbf"),
+    (0.40, 0.40, 0.40, "#666666"),
+];
+
+pub struct ModelsView {
+    pub container: gtk::Box,
+    headline: Label,
+    legend: Label,
+    chart: DrawingArea,
+    comparison: Arc<Mutex<Option<ModelComparison>>>,
+}
+
+fn colour(index: usize) -> (f64, f64, f64, &'static str) {
+    COLOURS[index % COLOURS.len()]
+}
+
+/// Temperature lines for each model over the min/max spread, with precipitation beneath
+fn draw_chart(comparison: &ModelComparison, cr: &Context, width: i32, height: i32) {
+    let hours = comparison.hours.len();
+    if hours < 2 {
+        return;
+    }
+    let (width, height) = (width as f64, height as f64);
+    let temperature_height = height * (1.0 - PRECIPITATION_SHARE);
+
+    let ranges = (0..hours)
+        .map(|i| comparison.temperature_range(i))
+        .collect::<Vec<_>>();
+    let low = ranges.iter().flatten().map(|(min, _)| *min).reduce(f64::min);
+    let high = ranges.iter().flatten().map(|(_, max)| *max).reduce(f64::max);
+    let (low, high) = match (low, high) {
+        (Some(low), Some(high)) => (low - 1.0, high + 1.0),
+        _ => return,
+    };
+
+    let x = |i: usize| i as f64 / (hours - 1) as f64 * width;
+    let y = |t: f64| (high - t) / (high - low) * temperature_height;
+
+    // Spread between the models, the wider it is the less certain the forecast
+    cr.set_source_rgba(0.5, 0.5, 0.5, 0.25);
+    let mut started = false;
+    for (i, range) in ranges.iter().enumerate() {
+        if let Some((_, max)) = range {
+            if started {
+                cr.line_to(x(i), y(*max));
+            } else {
+                cr.move_to(x(i), y(*max));
+                started = true;
+            }
+        }
+    }
+    for (i, range) in ranges.iter().enumerate().rev() {
+        if let Some((min, _)) = range {
+            cr.line_to(x(i), y(*min));
+        }
+    }
+    cr.close_path();
+    let _ = cr.fill();
+
+    let bar_width = width / hours as f64 / comparison.series.len().max(1) as f64;
+    for (index, series) in comparison.series.iter().enumerate() {
+        let (r, g, b, _) = colour(index);
+
+        cr.set_source_rgb(r, g, b);
+        cr.set_line_width(1.5);
+        let mut started = false;
+        for (i, temperature) in series.temperature.iter().enumerate().take(hours) {
+            match temperature {
+                Some(t) if started => cr.line_to(x(i), y(*t)),
+                Some(t) => {
+                    cr.move_to(x(i), y(*t));
+                    started = true;
+                },
+                None => started = false,
+            }
+        }
+        let _ = cr.stroke();
+
+        cr.set_source_rgba(r, g, b, 0.7);
+        for (i, precipitation) in series.precipitation.iter().enumerate().take(hours) {
+            let amount = precipitation.unwrap_or(0.0).min(PRECIPITATION_SCALE);
+            if amount <= 0.0 {
+                continue;
+            }
+            let bar_height = amount / PRECIPITATION_SCALE * height * PRECIPITATION_SHARE;
+            let left = i as f64 / hours as f64 * width + index as f64 * bar_width;
+            cr.rectangle(left, height - bar_height, bar_width, bar_height);
+        }
+        let _ = cr.fill();
+    }
+}
+
+impl ModelsView {
+    pub fn new() -> Self {
+        let container = gtk::Box::new(gtk::Orientation::Vertical, 10);
+
+        let headline = Label::new(None);
+        container.append(&headline);
+
+        let chart = DrawingArea::new();
+        chart.set_content_width(CHART_WIDTH);
+        chart.set_content_height(CHART_HEIGHT);
+        container.append(&chart);
+
+        let legend = Label::new(None);
+        legend.set_wrap(true);
+        container.append(&legend);
+
+        let comparison: Arc<Mutex<Option<ModelComparison>>> = Arc::new(Mutex::new(None));
+        let chart_comparison = comparison.clone();
+        chart.set_draw_func(move |_, cr, width, height| {
+            if let Ok(comparison) = chart_comparison.try_lock() {
+                if let Some(comparison) = comparison.as_ref() {
+                    draw_chart(comparison, cr, width, height);
+                }
+            }
+        });
+
+        Self {
+            container,
+            headline,
+            legend,
+            chart,
+            comparison,
+        }
+    }
+
+    pub fn populate(&mut self, comparison: Result<ModelComparison, WeatherError>) {
+        let comparison = match comparison {
+            Ok(comparison) if !comparison.series.is_empty() => comparison,
+            Ok(_) => {
+                self.set_message("No model forecasts for this location");
+                return;
+            },
+            Err(err) => {
+                self.set_message(&err.to_string());
+                return;
+            },
+        };
+
+        let units = comparison.units;
+        let confidence = match comparison.confidence() {
+            Some(Confidence::High) => "<b>High confidence</b>, the models agree",
+            Some(Confidence::Moderate) => "<b>Moderate confidence</b>, the models differ somewhat",
+            Some(Confidence::Low) => "<b>Low confidence</b>, the models disagree",
+            None => "Confidence unknown",
+        };
+        let spread = comparison.temperature_spread()
+            .map(|s| format!("\nTemperatures differ by {} on average",
+                units.temperature_value(format!("{:.1}", s))))
+            .unwrap_or_default();
+        self.headline.set_markup(&format!("{}{}\n{} of {} models expect precipitation",
+            confidence,
+            spread,
+            comparison.models_with_precipitation(),
+            comparison.series.len()));
+
+        let legend = comparison.series.iter()
+            .enumerate()
+            .map(|(index, series)| format!("<span foreground=\"{}\">■</span> {}",
+                colour(index).3,
+                gtk::glib::markup_escape_text(&series.model.to_string())))
+            .collect::<Vec<_>>()
+            .join("  ");
+        self.legend.set_markup(&legend);
+
+        if let Ok(mut current) = self.comparison.try_lock() {
+            *current = Some(comparison);
+        }
+        self.chart.set_visible(true);
+        self.chart.queue_draw();
+    }
+
+    fn set_message(&mut self, message: &str) {
+        self.headline.set_text(message);
+        self.legend.set_text("");
+        self.chart.set_visible(false);
+    }
+}
@@ -0,0 +1,53 @@
+use crate::api::station::StationReading;
+use crate::api::marine::compass_point;
+use crate::api::weather::time_from;
+use crate::api::units::Units;
+
+/// Readings older than this are marked as stale, stations upload every minute or so
+const STALE_SECONDS: i64 = 15 * 60;
+
+/// Live readings from the station, shown alongside the forecast's current conditions
+pub fn station_markup(reading: &StationReading, units: Units, now: i64) -> String {
+    let name = reading.station.as_deref()
+        .map(|name| format!(" {}", gtk::glib::markup_escape_text(name)))
+        .unwrap_or_default();
+    let mut parts = Vec::new();
+    if let Some(temperature) = reading.temperature(units) {
+        parts.push(units.temperature_value(format!("{:.1}", temperature)));
+    }
+    if let Some(humidity) = reading.humidity {
+        parts.push(format!("humidity {:.0}%", humidity));
+    }
+    if let Some(wind_speed) = reading.wind_speed(units) {
+        let mut wind = format!("wind {}", units.speed_value(format!("{:.1}", wind_speed)));
+        if let Some(direction) = reading.wind_deg {
+            wind += &format!(" from {}", compass_point(direction));
+        }
+        if let Some(gust) = reading.wind_gust(units) {
+            wind += &format!(", gusts {}", units.speed_value(format!("{:.1}", gust)));
+        }
+        parts.push(wind);
+    }
+    if let Some(pressure) = reading.pressure_hpa() {
+        parts.push(format!("{:.0} hPa", pressure));
+    }
+    if let Some(rain) = reading.rain_rate_mm() {
+        parts.push(format!("rain {}/h", units.volume_value(format!("{:.1}", rain))));
+    }
+    if let Some(rain) = reading.daily_rain_mm() {
+        parts.push(format!("{} today", units.volume_value(format!("{:.1}", rain))));
+    }
+    if let Some(uvi) = reading.uvi {
+        parts.push(format!("UV {:.0}", uvi));
+    }
+
+    let time = time_from(reading.received_at, "[hour]:[minute]")
+        .unwrap_or_else(|_| String::from("--"));
+    let age = if now - reading.received_at > STALE_SECONDS {
+        format!("<i>last heard at {}</i>", time)
+    } else {
+        format!("at {}", time)
+    };
+
+    format!("<b>Station</b>{} {}\n{}", name, age, parts.join(" · "))
+}